@@ -8,9 +8,71 @@ pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i32,
     pub image_path: String,
+    pub mime_type: String,
+    pub original_filename: Option<String>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub captured_at: Option<DateTime>,
+    pub content_hash: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub caption: Option<String>,
+    pub credit: Option<String>,
+    pub license: Option<String>,
+    pub byte_size: Option<i64>,
+    pub uploader_id: Option<i32>,
+    pub created_at: DateTimeWithTimeZone,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub placeholder: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(has_many = "super::album::Entity")]
+    Album,
+    #[sea_orm(has_many = "super::album_image::Entity")]
+    AlbumImage,
+    #[sea_orm(has_many = "super::image_alt_text::Entity")]
+    ImageAltText,
+    #[sea_orm(has_many = "super::image_usage::Entity")]
+    ImageUsage,
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::UploaderId",
+        to = "super::user::Column::Id",
+        on_update = "Cascade",
+        on_delete = "SetNull"
+    )]
+    User,
+}
+
+impl Related<super::album::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Album.def()
+    }
+}
+
+impl Related<super::album_image::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::AlbumImage.def()
+    }
+}
+
+impl Related<super::image_alt_text::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ImageAltText.def()
+    }
+}
+
+impl Related<super::image_usage::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ImageUsage.def()
+    }
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
 
 impl ActiveModelBehavior for ActiveModel {}
@@ -2,11 +2,21 @@
 
 pub mod prelude;
 
+pub mod actor_key;
+pub mod album;
+pub mod album_image;
+pub mod follower;
+pub mod image_alt_text;
 pub mod image_metadata;
+pub mod image_usage;
 pub mod notification;
 pub mod permission;
 pub mod post_metadata;
 pub mod role;
 pub mod role_permissions;
+pub mod social_link;
+pub mod subscriber;
 pub mod user;
+pub mod user_profile;
 pub mod user_roles;
+pub mod webmention;
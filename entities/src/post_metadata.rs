@@ -15,6 +15,9 @@ pub struct Model {
     pub created_at: DateTimeWithTimeZone,
     pub updated_at: DateTimeWithTimeZone,
     pub content_path: String,
+    pub pinned: bool,
+    pub pin_order: i32,
+    pub cover_image: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -27,6 +30,16 @@ pub enum Relation {
         on_delete = "Cascade"
     )]
     User,
+    #[sea_orm(has_many = "super::image_usage::Entity")]
+    ImageUsage,
+    #[sea_orm(has_many = "super::webmention::Entity")]
+    Webmention,
+}
+
+impl Related<super::image_usage::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ImageUsage.def()
+    }
 }
 
 impl Related<super::user::Entity> for Entity {
@@ -35,4 +48,10 @@ impl Related<super::user::Entity> for Entity {
     }
 }
 
+impl Related<super::webmention::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Webmention.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
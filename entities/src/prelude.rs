@@ -1,10 +1,20 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.15
 
+pub use super::actor_key::Entity as ActorKey;
+pub use super::album::Entity as Album;
+pub use super::album_image::Entity as AlbumImage;
+pub use super::follower::Entity as Follower;
+pub use super::image_alt_text::Entity as ImageAltText;
 pub use super::image_metadata::Entity as ImageMetadata;
+pub use super::image_usage::Entity as ImageUsage;
 pub use super::notification::Entity as Notification;
 pub use super::permission::Entity as Permission;
 pub use super::post_metadata::Entity as PostMetadata;
 pub use super::role::Entity as Role;
 pub use super::role_permissions::Entity as RolePermissions;
+pub use super::social_link::Entity as SocialLink;
+pub use super::subscriber::Entity as Subscriber;
 pub use super::user::Entity as User;
+pub use super::user_profile::Entity as UserProfile;
 pub use super::user_roles::Entity as UserRoles;
+pub use super::webmention::Entity as Webmention;
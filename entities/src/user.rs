@@ -16,14 +16,26 @@ pub struct Model {
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::image_metadata::Entity")]
+    ImageMetadata,
     #[sea_orm(has_many = "super::notification::Entity")]
     Notification,
     #[sea_orm(has_many = "super::post_metadata::Entity")]
     PostMetadata,
+    #[sea_orm(has_many = "super::social_link::Entity")]
+    SocialLink,
+    #[sea_orm(has_one = "super::user_profile::Entity")]
+    UserProfile,
     #[sea_orm(has_many = "super::user_roles::Entity")]
     UserRoles,
 }
 
+impl Related<super::image_metadata::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ImageMetadata.def()
+    }
+}
+
 impl Related<super::notification::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Notification.def()
@@ -36,6 +48,18 @@ impl Related<super::post_metadata::Entity> for Entity {
     }
 }
 
+impl Related<super::social_link::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::SocialLink.def()
+    }
+}
+
+impl Related<super::user_profile::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::UserProfile.def()
+    }
+}
+
 impl Related<super::user_roles::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::UserRoles.def()
@@ -8,6 +8,21 @@ mod m20240404_131352_create_posts_table;
 mod m20240404_150409_create_images_table;
 mod m20240406_123952_create_notification_table;
 mod m20240412_023852_seed_admin_user;
+mod m20240418_101500_create_user_profile;
+mod m20240420_143000_add_post_pinning;
+mod m20240424_201500_create_webmention_table;
+mod m20240426_183000_create_federation_tables;
+mod m20240429_090000_create_subscriber_table;
+mod m20240502_110000_add_image_mime_type;
+mod m20240505_093000_add_image_safe_metadata;
+mod m20240507_154500_add_image_content_hash;
+mod m20240510_120000_add_image_rich_metadata;
+mod m20240514_100000_create_album_tables;
+mod m20240517_090000_seed_gallery_permission;
+mod m20240519_100000_create_image_usage_table;
+mod m20240521_090000_add_image_placeholder;
+mod m20240523_090000_add_subscriber_token_sent_at;
+mod m20240525_090000_share_image_content_hash;
 
 pub struct Migrator;
 
@@ -23,6 +38,21 @@ impl MigratorTrait for Migrator {
             Box::new(m20240404_150409_create_images_table::Migration),
             Box::new(m20240406_123952_create_notification_table::Migration),
             Box::new(m20240412_023852_seed_admin_user::Migration),
+            Box::new(m20240418_101500_create_user_profile::Migration),
+            Box::new(m20240420_143000_add_post_pinning::Migration),
+            Box::new(m20240424_201500_create_webmention_table::Migration),
+            Box::new(m20240426_183000_create_federation_tables::Migration),
+            Box::new(m20240429_090000_create_subscriber_table::Migration),
+            Box::new(m20240502_110000_add_image_mime_type::Migration),
+            Box::new(m20240505_093000_add_image_safe_metadata::Migration),
+            Box::new(m20240507_154500_add_image_content_hash::Migration),
+            Box::new(m20240510_120000_add_image_rich_metadata::Migration),
+            Box::new(m20240514_100000_create_album_tables::Migration),
+            Box::new(m20240517_090000_seed_gallery_permission::Migration),
+            Box::new(m20240519_100000_create_image_usage_table::Migration),
+            Box::new(m20240521_090000_add_image_placeholder::Migration),
+            Box::new(m20240523_090000_add_subscriber_token_sent_at::Migration),
+            Box::new(m20240525_090000_share_image_content_hash::Migration),
         ]
     }
 }
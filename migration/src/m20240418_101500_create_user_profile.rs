@@ -0,0 +1,106 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // A table of it's own, as the `user` entity is used by the migrations before this one
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserProfile::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(UserProfile::UserId)
+                            .integer()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK-user_profile_user_foreign_key")
+                            .from(UserProfile::Table, UserProfile::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade)
+                    )
+                    .col(ColumnDef::new(UserProfile::DisplayName).string_len(100))
+                    .col(ColumnDef::new(UserProfile::Avatar).string())
+                    .col(ColumnDef::new(UserProfile::Bio).text())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(SocialLink::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(SocialLink::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(SocialLink::UserId).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK-social_link_user_foreign_key")
+                            .from(SocialLink::Table, SocialLink::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade)
+                    )
+                    .col(ColumnDef::new(SocialLink::Label).string_len(100).not_null())
+                    .col(ColumnDef::new(SocialLink::Url).string().not_null())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SocialLink::Table)
+                    .drop_foreign_key(Alias::new("FK-social_link_user_foreign_key"))
+                    .to_owned()
+            )
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(SocialLink::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(UserProfile::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum UserProfile {
+    Table,
+    UserId,
+    DisplayName,
+    Avatar,
+    Bio,
+}
+
+#[derive(DeriveIden)]
+enum SocialLink {
+    Table,
+    Id,
+    UserId,
+    Label,
+    Url,
+}
@@ -0,0 +1,41 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(PostMetadata::Table)
+                    .add_column(ColumnDef::new(PostMetadata::Pinned).boolean().not_null().default(false))
+                    .add_column(ColumnDef::new(PostMetadata::PinOrder).integer().not_null().default(0))
+                    .add_column(ColumnDef::new(PostMetadata::CoverImage).string())
+                    .to_owned()
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(PostMetadata::Table)
+                    .drop_column(PostMetadata::Pinned)
+                    .drop_column(PostMetadata::PinOrder)
+                    .drop_column(PostMetadata::CoverImage)
+                    .to_owned()
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum PostMetadata {
+    Table,
+    Pinned,
+    PinOrder,
+    CoverImage,
+}
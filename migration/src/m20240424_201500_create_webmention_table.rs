@@ -0,0 +1,79 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Webmention::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Webmention::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Webmention::PostId).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK-webmention_post_foreign_key")
+                            .from(Webmention::Table, Webmention::PostId)
+                            .to(PostMetadata::Table, PostMetadata::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade)
+                    )
+                    .col(ColumnDef::new(Webmention::Source).string().not_null())
+                    .col(ColumnDef::new(Webmention::Title).string())
+                    .col(ColumnDef::new(Webmention::Approved).boolean().not_null().default(false))
+                    .col(ColumnDef::new(Webmention::CreatedAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(Webmention::UpdatedAt).timestamp_with_time_zone().not_null())
+                    .index(
+                        Index::create()
+                            .name("IDX-webmention_post_source")
+                            .col(Webmention::PostId)
+                            .col(Webmention::Source)
+                            .unique()
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Webmention::Table)
+                    .drop_foreign_key(Alias::new("FK-webmention_post_foreign_key"))
+                    .to_owned()
+            )
+        .await?;
+
+        manager
+            .drop_table(Table::drop().table(Webmention::Table).to_owned())
+        .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum PostMetadata {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Webmention {
+    Table,
+    Id,
+    PostId,
+    Source,
+    Title,
+    Approved,
+    CreatedAt,
+    UpdatedAt,
+}
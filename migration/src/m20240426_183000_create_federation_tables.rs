@@ -0,0 +1,88 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ActorKey::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ActorKey::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(ActorKey::Actor).string().not_null().unique_key())
+                    .col(ColumnDef::new(ActorKey::PublicKey).text().not_null())
+                    .col(ColumnDef::new(ActorKey::PrivateKey).text().not_null())
+                    .col(ColumnDef::new(ActorKey::CreatedAt).timestamp_with_time_zone().not_null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(Follower::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Follower::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Follower::Actor).string().not_null())
+                    .col(ColumnDef::new(Follower::Follower).string().not_null())
+                    .col(ColumnDef::new(Follower::Inbox).string().not_null())
+                    .col(ColumnDef::new(Follower::SharedInbox).string())
+                    .col(ColumnDef::new(Follower::CreatedAt).timestamp_with_time_zone().not_null())
+                    .index(
+                        Index::create()
+                            .name("IDX-follower_actor_follower")
+                            .col(Follower::Actor)
+                            .col(Follower::Follower)
+                            .unique()
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Follower::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(ActorKey::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ActorKey {
+    Table,
+    Id,
+    Actor,
+    PublicKey,
+    PrivateKey,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Follower {
+    Table,
+    Id,
+    Actor,
+    Follower,
+    Inbox,
+    SharedInbox,
+    CreatedAt,
+}
@@ -0,0 +1,49 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Subscriber::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Subscriber::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Subscriber::Email).string().not_null().unique_key())
+                    .col(ColumnDef::new(Subscriber::Token).string().not_null().unique_key())
+                    .col(ColumnDef::new(Subscriber::Confirmed).boolean().not_null().default(false))
+                    .col(ColumnDef::new(Subscriber::CreatedAt).timestamp_with_time_zone().not_null())
+                    .col(ColumnDef::new(Subscriber::ConfirmedAt).timestamp_with_time_zone())
+                    .col(ColumnDef::new(Subscriber::LastDigestAt).timestamp_with_time_zone())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Subscriber::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Subscriber {
+    Table,
+    Id,
+    Email,
+    Token,
+    Confirmed,
+    CreatedAt,
+    ConfirmedAt,
+    LastDigestAt,
+}
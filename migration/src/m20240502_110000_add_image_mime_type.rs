@@ -0,0 +1,44 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Images uploaded before were always served as PNG, so they keep it
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ImageMetadata::Table)
+                    .add_column(
+                        ColumnDef::new(ImageMetadata::MimeType)
+                            .string()
+                            .not_null()
+                            .default("image/png"),
+                    )
+                    .add_column(ColumnDef::new(ImageMetadata::OriginalFilename).string())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ImageMetadata::Table)
+                    .drop_column(ImageMetadata::MimeType)
+                    .drop_column(ImageMetadata::OriginalFilename)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ImageMetadata {
+    Table,
+    MimeType,
+    OriginalFilename,
+}
@@ -0,0 +1,42 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // The capture date comes from EXIF, which has no time zone
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ImageMetadata::Table)
+                    .add_column(ColumnDef::new(ImageMetadata::Width).integer())
+                    .add_column(ColumnDef::new(ImageMetadata::Height).integer())
+                    .add_column(ColumnDef::new(ImageMetadata::CapturedAt).timestamp())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ImageMetadata::Table)
+                    .drop_column(ImageMetadata::Width)
+                    .drop_column(ImageMetadata::Height)
+                    .drop_column(ImageMetadata::CapturedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ImageMetadata {
+    Table,
+    Width,
+    Height,
+    CapturedAt,
+}
@@ -0,0 +1,56 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Images uploaded before have no hash, and keep the name they were sent with
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ImageMetadata::Table)
+                    .add_column(ColumnDef::new(ImageMetadata::ContentHash).string())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX-image_metadata_content_hash")
+                    .table(ImageMetadata::Table)
+                    .col(ImageMetadata::ContentHash)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("IDX-image_metadata_content_hash")
+                    .table(ImageMetadata::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ImageMetadata::Table)
+                    .drop_column(ImageMetadata::ContentHash)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ImageMetadata {
+    Table,
+    ContentHash,
+}
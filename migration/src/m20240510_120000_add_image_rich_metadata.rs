@@ -0,0 +1,117 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20220101_000001_create_table::User;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Who uploaded the images already stored is unknown, and they count as uploaded now
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ImageMetadata::Table)
+                    .add_column(ColumnDef::new(ImageMetadata::Caption).text())
+                    .add_column(ColumnDef::new(ImageMetadata::Credit).string())
+                    .add_column(ColumnDef::new(ImageMetadata::License).string())
+                    .add_column(ColumnDef::new(ImageMetadata::ByteSize).big_integer())
+                    .add_column(ColumnDef::new(ImageMetadata::UploaderId).integer())
+                    .add_column(
+                        ColumnDef::new(ImageMetadata::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("FK-image_metadata_uploader_foreign_key")
+                            .from_tbl(ImageMetadata::Table)
+                            .from_col(ImageMetadata::UploaderId)
+                            .to_tbl(User::Table)
+                            .to_col(User::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(ImageAltText::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ImageAltText::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(ImageAltText::ImageId).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK-image_alt_text_image_foreign_key")
+                            .from(ImageAltText::Table, ImageAltText::ImageId)
+                            .to(ImageMetadata::Table, ImageMetadata::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .col(ColumnDef::new(ImageAltText::Locale).string().not_null())
+                    .col(ColumnDef::new(ImageAltText::AltText).text().not_null())
+                    .index(
+                        Index::create()
+                            .name("IDX-image_alt_text_image_locale")
+                            .col(ImageAltText::ImageId)
+                            .col(ImageAltText::Locale)
+                            .unique(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ImageAltText::Table).to_owned())
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ImageMetadata::Table)
+                    .drop_foreign_key(Alias::new("FK-image_metadata_uploader_foreign_key"))
+                    .drop_column(ImageMetadata::Caption)
+                    .drop_column(ImageMetadata::Credit)
+                    .drop_column(ImageMetadata::License)
+                    .drop_column(ImageMetadata::ByteSize)
+                    .drop_column(ImageMetadata::UploaderId)
+                    .drop_column(ImageMetadata::CreatedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ImageMetadata {
+    Table,
+    Id,
+    Caption,
+    Credit,
+    License,
+    ByteSize,
+    UploaderId,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum ImageAltText {
+    Table,
+    Id,
+    ImageId,
+    Locale,
+    AltText,
+}
@@ -0,0 +1,133 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Album::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Album::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Album::Title).string().not_null())
+                    .col(ColumnDef::new(Album::Slug).string().not_null().unique_key())
+                    .col(ColumnDef::new(Album::Description).text())
+                    .col(ColumnDef::new(Album::CoverImageId).integer())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK-album_cover_image_foreign_key")
+                            .from(Album::Table, Album::CoverImageId)
+                            .to(ImageMetadata::Table, ImageMetadata::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .col(
+                        ColumnDef::new(Album::Visibility)
+                            .string()
+                            .not_null()
+                            .default("private"),
+                    )
+                    .col(
+                        ColumnDef::new(Album::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Album::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(AlbumImage::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(AlbumImage::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(AlbumImage::AlbumId).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK-album_image_album_foreign_key")
+                            .from(AlbumImage::Table, AlbumImage::AlbumId)
+                            .to(Album::Table, Album::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .col(ColumnDef::new(AlbumImage::ImageId).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK-album_image_image_foreign_key")
+                            .from(AlbumImage::Table, AlbumImage::ImageId)
+                            .to(ImageMetadata::Table, ImageMetadata::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .col(ColumnDef::new(AlbumImage::Position).integer().not_null())
+                    .index(
+                        Index::create()
+                            .name("IDX-album_image_album_image")
+                            .col(AlbumImage::AlbumId)
+                            .col(AlbumImage::ImageId)
+                            .unique(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AlbumImage::Table).to_owned())
+            .await?;
+
+        manager
+            .drop_table(Table::drop().table(Album::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ImageMetadata {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Album {
+    Table,
+    Id,
+    Title,
+    Slug,
+    Description,
+    CoverImageId,
+    Visibility,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum AlbumImage {
+    Table,
+    Id,
+    AlbumId,
+    ImageId,
+    Position,
+}
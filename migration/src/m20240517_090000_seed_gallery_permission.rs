@@ -0,0 +1,65 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20240404_125849_create_role_permissions_table::{Permission, Role, RolePermissions};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let insert = Query::insert()
+            .into_table(Permission::Table)
+            .columns([Permission::Name, Permission::Description])
+            .values_panic([
+                "Galeria".into(),
+                "Essa permissão concede ao usuário a habilidade de enviar e remover imagens da galeria do site, dentro da sua cota de armazenamento.".into(),
+            ])
+            .to_owned();
+        manager.exec_stmt(insert).await?;
+
+        // Administrators keep every permission
+        let grant = Query::insert()
+            .into_table(RolePermissions::Table)
+            .columns([RolePermissions::RoleId, RolePermissions::PermissionId])
+            .select_from(
+                Query::select()
+                    .column((Role::Table, Role::Id))
+                    .column((Permission::Table, Permission::Id))
+                    .from(Role::Table)
+                    .from(Permission::Table)
+                    .and_where(Expr::col((Role::Table, Role::Name)).eq("Administrador"))
+                    .and_where(Expr::col((Permission::Table, Permission::Name)).eq("Galeria"))
+                    .to_owned(),
+            )
+            .map_err(|err| DbErr::Migration(err.to_string()))?
+            .to_owned();
+        manager.exec_stmt(grant).await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let revoke = Query::delete()
+            .from_table(RolePermissions::Table)
+            .and_where(
+                Expr::col(RolePermissions::PermissionId).in_subquery(
+                    Query::select()
+                        .column(Permission::Id)
+                        .from(Permission::Table)
+                        .and_where(Expr::col(Permission::Name).eq("Galeria"))
+                        .to_owned(),
+                ),
+            )
+            .to_owned();
+        manager.exec_stmt(revoke).await?;
+
+        let delete = Query::delete()
+            .from_table(Permission::Table)
+            .and_where(Expr::col(Permission::Name).eq("Galeria"))
+            .to_owned();
+        manager.exec_stmt(delete).await?;
+
+        Ok(())
+    }
+}
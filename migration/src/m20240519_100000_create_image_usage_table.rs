@@ -0,0 +1,76 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ImageUsage::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ImageUsage::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(ImageUsage::ImageId).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK-image_usage_image_foreign_key")
+                            .from(ImageUsage::Table, ImageUsage::ImageId)
+                            .to(ImageMetadata::Table, ImageMetadata::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .col(ColumnDef::new(ImageUsage::PostId).integer().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("FK-image_usage_post_foreign_key")
+                            .from(ImageUsage::Table, ImageUsage::PostId)
+                            .to(PostMetadata::Table, PostMetadata::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .index(
+                        Index::create()
+                            .name("IDX-image_usage_image_post")
+                            .col(ImageUsage::ImageId)
+                            .col(ImageUsage::PostId)
+                            .unique(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ImageUsage::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ImageMetadata {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum PostMetadata {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum ImageUsage {
+    Table,
+    Id,
+    ImageId,
+    PostId,
+}
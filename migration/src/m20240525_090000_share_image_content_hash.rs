@@ -0,0 +1,57 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Every upload gets it's own row, and uploads of the same content share one file
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("IDX-image_metadata_content_hash")
+                    .table(ImageMetadata::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX-image_metadata_content_hash")
+                    .table(ImageMetadata::Table)
+                    .col(ImageMetadata::ContentHash)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("IDX-image_metadata_content_hash")
+                    .table(ImageMetadata::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("IDX-image_metadata_content_hash")
+                    .table(ImageMetadata::Table)
+                    .col(ImageMetadata::ContentHash)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum ImageMetadata {
+    Table,
+    ContentHash,
+}
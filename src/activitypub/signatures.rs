@@ -0,0 +1,175 @@
+//! ## HTTP Signatures
+//! Signs the activities we deliver and verifies the ones posted to our inboxes, the way Mastodon
+//! does it: `rsa-sha256` signatures over `(request-target)`, `host`, `date` and `digest`.
+//! See <https://datatracker.ietf.org/doc/html/draft-cavage-http-signatures-12>.
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use reqwest::Url;
+use rsa::pkcs1v15::{Signature, SigningKey, VerifyingKey};
+use rsa::pkcs8::{DecodePrivateKey, DecodePublicKey, EncodePrivateKey, EncodePublicKey, LineEnding};
+use rsa::sha2::{Digest, Sha256};
+use rsa::signature::{SignatureEncoding, Signer, Verifier};
+use rsa::{RsaPrivateKey, RsaPublicKey};
+use std::time::{Duration, SystemTime};
+
+/// How far the `Date` of a signed request can be from our clock.
+const MAX_CLOCK_SKEW: Duration = Duration::from_secs(12 * 3600);
+
+/// The headers we sign and require on the requests we receive.
+const SIGNED_HEADERS: [&str; 4] = ["(request-target)", "host", "date", "digest"];
+
+/// Generate a new RSA key pair, returning the public and the private key as PEM. This is slow, so
+/// call it from a blocking task.
+pub fn generate_key_pair() -> Result<(String, String), String> {
+    let private_key =
+        RsaPrivateKey::new(&mut rand::thread_rng(), 2048).map_err(|err| err.to_string())?;
+    let public_key = RsaPublicKey::from(&private_key);
+
+    let public_pem = public_key
+        .to_public_key_pem(LineEnding::LF)
+        .map_err(|err| err.to_string())?;
+    let private_pem = private_key
+        .to_pkcs8_pem(LineEnding::LF)
+        .map_err(|err| err.to_string())?;
+
+    Ok((public_pem, private_pem.to_string()))
+}
+
+/// The `Digest` header of a body.
+pub fn digest(body: &[u8]) -> String {
+    format!("SHA-256={}", STANDARD.encode(Sha256::digest(body)))
+}
+
+/// The headers that sign a `POST` of `body` to `url`, including the `Signature` itself.
+pub fn sign_post(
+    private_key_pem: &str,
+    key_id: &str,
+    url: &str,
+    body: &[u8],
+) -> Result<Vec<(String, String)>, String> {
+    let url = Url::parse(url).map_err(|err| err.to_string())?;
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+    let target = match url.query() {
+        Some(query) => format!("post {}?{}", url.path(), query),
+        None => format!("post {}", url.path()),
+    };
+
+    let headers = vec![
+        ("host".to_string(), host),
+        ("date".to_string(), httpdate::fmt_http_date(SystemTime::now())),
+        ("digest".to_string(), digest(body)),
+    ];
+
+    let signing_string = signing_string(&SIGNED_HEADERS, |name| match name {
+        "(request-target)" => Some(target.clone()),
+        name => headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.clone()),
+    })
+    .ok_or_else(|| "Missing header to sign".to_string())?;
+
+    let private_key = RsaPrivateKey::from_pkcs8_pem(private_key_pem).map_err(|err| err.to_string())?;
+    let signature = SigningKey::<Sha256>::new(private_key).sign(signing_string.as_bytes());
+
+    let mut headers = headers;
+    headers.push((
+        "signature".to_string(),
+        format!(
+            "keyId=\"{}\",algorithm=\"rsa-sha256\",headers=\"{}\",signature=\"{}\"",
+            key_id,
+            SIGNED_HEADERS.join(" "),
+            STANDARD.encode(signature.to_bytes())
+        ),
+    ));
+
+    Ok(headers)
+}
+
+/// A parsed `Signature` header.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SignatureHeader {
+    pub key_id: String,
+    pub headers: Vec<String>,
+    pub signature: Vec<u8>,
+}
+
+pub fn parse_signature_header(value: &str) -> Option<SignatureHeader> {
+    let mut key_id = None;
+    let mut headers = vec!["date".to_string()];
+    let mut signature = None;
+
+    for param in value.split(',') {
+        let (name, value) = param.trim().split_once('=')?;
+        let value = value.trim().trim_matches('"');
+        match name.trim() {
+            "keyId" => key_id = Some(value.to_string()),
+            "headers" => headers = value.split_whitespace().map(str::to_lowercase).collect(),
+            "signature" => signature = STANDARD.decode(value).ok(),
+            "algorithm" if value != "rsa-sha256" && value != "hs2019" => return None,
+            _ => (),
+        }
+    }
+
+    Some(SignatureHeader {
+        key_id: key_id?,
+        headers,
+        signature: signature?,
+    })
+}
+
+/// Whether a received signature covers every header we need to trust the request.
+pub fn covers_required_headers(signature: &SignatureHeader) -> bool {
+    SIGNED_HEADERS
+        .iter()
+        .all(|required| signature.headers.iter().any(|header| header == required))
+}
+
+/// Build the string that is signed, taking the value of each header from `header`.
+pub fn signing_string(
+    headers: &[impl AsRef<str>],
+    header: impl Fn(&str) -> Option<String>,
+) -> Option<String> {
+    headers
+        .iter()
+        .map(|name| {
+            let name = name.as_ref();
+            header(name).map(|value| format!("{}: {}", name, value.trim()))
+        })
+        .collect::<Option<Vec<_>>>()
+        .map(|lines| lines.join("\n"))
+}
+
+/// Whether the `Date` of a request is close enough to now.
+pub fn is_recent(date: &str) -> bool {
+    match httpdate::parse_http_date(date) {
+        Ok(date) => {
+            let now = SystemTime::now();
+            let skew = now
+                .duration_since(date)
+                .or_else(|_| date.duration_since(now))
+                .unwrap_or(Duration::MAX);
+            skew <= MAX_CLOCK_SKEW
+        }
+        Err(_) => false,
+    }
+}
+
+pub fn verify(public_key_pem: &str, signing_string: &str, signature: &[u8]) -> bool {
+    let public_key = match RsaPublicKey::from_public_key_pem(public_key_pem) {
+        Ok(public_key) => public_key,
+        Err(_) => return false,
+    };
+
+    let signature = match Signature::try_from(signature) {
+        Ok(signature) => signature,
+        Err(_) => return false,
+    };
+
+    VerifyingKey::<Sha256>::new(public_key)
+        .verify(signing_string.as_bytes(), &signature)
+        .is_ok()
+}
@@ -0,0 +1,502 @@
+use chrono::prelude::*;
+use leptos::*;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[cfg(feature = "ssr")]
+use tokio::sync::Mutex;
+
+#[cfg(feature = "ssr")]
+use super::images::{ImageAltTextModel, ImageMetadataModel};
+use super::images::PublicImageModel;
+use super::users::{user_have_permission, user_logged_in};
+
+/// Who can see an album. Unlisted albums are open to anyone with the link, but aren't listed on
+/// /albums.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub enum AlbumVisibility {
+    Public,
+    Unlisted,
+    #[default]
+    Private,
+}
+
+impl AlbumVisibility {
+    pub const ALL: [AlbumVisibility; 3] = [
+        AlbumVisibility::Public,
+        AlbumVisibility::Unlisted,
+        AlbumVisibility::Private,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AlbumVisibility::Public => "public",
+            AlbumVisibility::Unlisted => "unlisted",
+            AlbumVisibility::Private => "private",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<AlbumVisibility> {
+        Self::ALL
+            .into_iter()
+            .find(|visibility| visibility.name() == name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Default, Deserialize, Serialize)]
+pub struct AlbumModel {
+    pub id: i32,
+    pub title: String,
+    pub slug: String,
+    pub description: Option<String>,
+    /// The image shown on /albums, the first one of the album when unset.
+    pub cover_image_id: Option<i32>,
+    pub visibility: AlbumVisibility,
+    pub created_at: DateTime<FixedOffset>,
+    pub updated_at: DateTime<FixedOffset>,
+    /// The images in order. Listings only fill their ids, `get_album` fills everything.
+    pub images: Vec<PublicImageModel>,
+}
+
+#[cfg(feature = "ssr")]
+impl From<entities::album::Model> for AlbumModel {
+    fn from(value: entities::album::Model) -> Self {
+        Self {
+            id: value.id,
+            title: value.title,
+            slug: value.slug,
+            description: value.description,
+            cover_image_id: value.cover_image_id,
+            visibility: AlbumVisibility::from_name(&value.visibility).unwrap_or_default(),
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+            images: vec![],
+        }
+    }
+}
+
+impl AlbumModel {
+    pub fn cover(&self) -> Option<i32> {
+        self.cover_image_id
+            .or(self.images.first().map(|image| image.id))
+    }
+}
+
+fn is_valid_slug(slug: &str) -> bool {
+    !slug.is_empty()
+        && slug
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+/// Fill the images of `albums`, in order. With `details`, images come with their metadata and
+/// alt texts; otherwise only their ids are set.
+#[cfg(feature = "ssr")]
+async fn with_images(
+    conn: &sea_orm::DatabaseConnection,
+    albums: Vec<entities::album::Model>,
+    details: bool,
+) -> Result<Vec<AlbumModel>, sea_orm::DbErr> {
+    use entities::prelude::{AlbumImage, ImageAltText, ImageMetadata};
+    use entities::{album_image, image_alt_text, image_metadata};
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+
+    let album_images = AlbumImage::find()
+        .filter(album_image::Column::AlbumId.is_in(albums.iter().map(|album| album.id)))
+        .order_by_asc(album_image::Column::Position)
+        .all(conn)
+        .await?;
+
+    let image_ids: Vec<i32> = album_images.iter().map(|image| image.image_id).collect();
+    let (images, alt_texts) = if details && !image_ids.is_empty() {
+        (
+            ImageMetadata::find()
+                .filter(image_metadata::Column::Id.is_in(image_ids.clone()))
+                .all(conn)
+                .await?,
+            ImageAltText::find()
+                .filter(image_alt_text::Column::ImageId.is_in(image_ids))
+                .all(conn)
+                .await?,
+        )
+    } else {
+        (vec![], vec![])
+    };
+
+    Ok(albums
+        .into_iter()
+        .map(|album| {
+            let album_id = album.id;
+            AlbumModel {
+                images: album_images
+                    .iter()
+                    .filter(|album_image| album_image.album_id == album_id)
+                    .map(|album_image| {
+                        match images.iter().find(|image| image.id == album_image.image_id) {
+                            Some(image) => PublicImageModel::from(ImageMetadataModel {
+                                alt_texts: alt_texts
+                                    .iter()
+                                    .filter(|alt_text| alt_text.image_id == image.id)
+                                    .cloned()
+                                    .map(ImageAltTextModel::from)
+                                    .collect(),
+                                ..ImageMetadataModel::from(image.clone())
+                            }),
+                            None => PublicImageModel {
+                                id: album_image.image_id,
+                                ..Default::default()
+                            },
+                        }
+                    })
+                    .collect(),
+                ..AlbumModel::from(album)
+            }
+        })
+        .collect())
+}
+
+// Album Create/Read/Update/Delete
+
+#[server(CreateAlbum, "/api/albums")]
+pub async fn create_album(new_album: AlbumModel) -> Result<AlbumModel, ServerFnError> {
+    if let Some(user) = user_logged_in().await? {
+        if user_have_permission(user, "Escrever".to_string()).await? {
+            return create_album_guard(new_album).await;
+        } else {
+            return Err(ServerFnError::new(
+                "User doesn't have the permission to execute this operation.",
+            ));
+        }
+    } else {
+        return Err(ServerFnError::new("User is not logged in."));
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub async fn create_album_guard(new_album: AlbumModel) -> Result<AlbumModel, ServerFnError> {
+    use crate::AppState;
+
+    use entities::album;
+    use sea_orm::{ActiveModelTrait, Set};
+
+    if !is_valid_slug(&new_album.slug) {
+        return Err(ServerFnError::new(
+            "The slug can only have lowercase letters, numbers and dashes.",
+        ));
+    }
+
+    let state: Arc<Mutex<AppState>> = use_context().unwrap();
+    let state = state.as_ref().lock().await;
+
+    let now = Utc::now().with_timezone(&FixedOffset::west_opt(3 * 3600).expect("Invalid Timezone"));
+    let album = album::ActiveModel {
+        title: Set(new_album.title),
+        slug: Set(new_album.slug),
+        description: Set(new_album.description),
+        cover_image_id: Set(new_album.cover_image_id),
+        visibility: Set(new_album.visibility.name().to_string()),
+        created_at: Set(now),
+        updated_at: Set(now),
+        ..Default::default()
+    };
+
+    match album.insert(&state.conn).await {
+        Ok(album) => Ok(AlbumModel::from(album)),
+        Err(db_err) => Err(ServerFnError::new(format!(
+            "A error occured when inserting a new album to database. DbErr: {}",
+            db_err.to_string()
+        ))),
+    }
+}
+
+/// Read the albums listed on /albums, most recently updated first.
+#[server(ReadPublicAlbums, "/api/albums")]
+pub async fn get_public_albums() -> Result<Vec<AlbumModel>, ServerFnError> {
+    use crate::AppState;
+
+    use entities::album;
+    use entities::prelude::Album;
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+
+    let state: Arc<Mutex<AppState>> = use_context().unwrap();
+    let state = state.as_ref().lock().await;
+
+    let albums = Album::find()
+        .filter(album::Column::Visibility.eq(AlbumVisibility::Public.name()))
+        .order_by_desc(album::Column::UpdatedAt)
+        .all(&state.conn)
+        .await;
+
+    match albums {
+        Ok(albums) => match with_images(&state.conn, albums, false).await {
+            Ok(albums) => Ok(albums),
+            Err(db_err) => Err(ServerFnError::new(format!(
+                "A error happened when requesting the albums, try again later. DbErr: {}",
+                db_err.to_string()
+            ))),
+        },
+        Err(db_err) => Err(ServerFnError::new(format!(
+            "A error happened when requesting the albums, try again later. DbErr: {}",
+            db_err.to_string()
+        ))),
+    }
+}
+
+/// Read every album, for the Gallery page.
+#[server(ReadAlbums, "/api/albums")]
+pub async fn get_all_albums() -> Result<Vec<AlbumModel>, ServerFnError> {
+    if let Some(user) = user_logged_in().await? {
+        if user_have_permission(user, "Escrever".to_string()).await? {
+            return get_all_albums_guard().await;
+        } else {
+            return Err(ServerFnError::new(
+                "User doesn't have the permission to execute this operation.",
+            ));
+        }
+    } else {
+        return Err(ServerFnError::new("User is not logged in."));
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub async fn get_all_albums_guard() -> Result<Vec<AlbumModel>, ServerFnError> {
+    use crate::AppState;
+
+    use entities::album;
+    use entities::prelude::Album;
+    use sea_orm::{EntityTrait, QueryOrder};
+
+    let state: Arc<Mutex<AppState>> = use_context().unwrap();
+    let state = state.as_ref().lock().await;
+
+    let albums = match Album::find()
+        .order_by_desc(album::Column::UpdatedAt)
+        .all(&state.conn)
+        .await
+    {
+        Ok(albums) => albums,
+        Err(db_err) => {
+            return Err(ServerFnError::new(format!(
+                "A error happened when requesting the albums, try again later. DbErr: {}",
+                db_err.to_string()
+            )))
+        }
+    };
+
+    match with_images(&state.conn, albums, false).await {
+        Ok(albums) => Ok(albums),
+        Err(db_err) => Err(ServerFnError::new(format!(
+            "A error happened when requesting the albums, try again later. DbErr: {}",
+            db_err.to_string()
+        ))),
+    }
+}
+
+/// Read an album with it's images. Private albums are only shown to writers.
+#[server(ReadAlbum, "/api/albums")]
+pub async fn get_album(slug: String) -> Result<Option<AlbumModel>, ServerFnError> {
+    use crate::AppState;
+
+    use entities::album;
+    use entities::prelude::Album;
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+
+    let state: Arc<Mutex<AppState>> = use_context().unwrap();
+    let state = state.as_ref().lock().await;
+
+    let album = match Album::find()
+        .filter(album::Column::Slug.eq(slug))
+        .one(&state.conn)
+        .await
+    {
+        Ok(Some(album)) => album,
+        Ok(None) => return Ok(None),
+        Err(db_err) => {
+            return Err(ServerFnError::new(format!(
+                "A error happened when requesting the album, try again later. DbErr: {}",
+                db_err.to_string()
+            )))
+        }
+    };
+
+    let album = match with_images(&state.conn, vec![album], true).await {
+        Ok(mut albums) => albums.remove(0),
+        Err(db_err) => {
+            return Err(ServerFnError::new(format!(
+                "A error happened when requesting the album, try again later. DbErr: {}",
+                db_err.to_string()
+            )))
+        }
+    };
+    std::mem::drop(state);
+
+    if album.visibility == AlbumVisibility::Private {
+        let can_see = match user_logged_in().await? {
+            Some(user) => user_have_permission(user, "Escrever".to_string()).await?,
+            None => false,
+        };
+        if !can_see {
+            return Ok(None);
+        }
+    }
+
+    Ok(Some(album))
+}
+
+/// Update an album based on it's model. The album's images are replaced by the ones on the
+/// model, in that order.
+#[server(UpdateAlbum, "/api/albums")]
+pub async fn update_album(updated_album: AlbumModel) -> Result<AlbumModel, ServerFnError> {
+    if let Some(user) = user_logged_in().await? {
+        if user_have_permission(user, "Escrever".to_string()).await? {
+            return update_album_guard(updated_album).await;
+        } else {
+            return Err(ServerFnError::new(
+                "User doesn't have the permission to execute this operation.",
+            ));
+        }
+    } else {
+        return Err(ServerFnError::new("User is not logged in."));
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub async fn update_album_guard(updated_album: AlbumModel) -> Result<AlbumModel, ServerFnError> {
+    use crate::AppState;
+
+    use entities::prelude::{Album, AlbumImage};
+    use entities::{album, album_image};
+    use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set, TransactionTrait};
+
+    if !is_valid_slug(&updated_album.slug) {
+        return Err(ServerFnError::new(
+            "The slug can only have lowercase letters, numbers and dashes.",
+        ));
+    }
+
+    let state: Arc<Mutex<AppState>> = use_context().unwrap();
+    let state = state.as_ref().lock().await;
+
+    let album = match Album::find_by_id(updated_album.id).one(&state.conn).await {
+        Ok(Some(album)) => album,
+        Ok(None) => return Err(ServerFnError::new("This album doesn't exist.")),
+        Err(db_err) => {
+            return Err(ServerFnError::new(format!(
+                "A error happened when requesting the album, try again later. DbErr: {}",
+                db_err.to_string()
+            )))
+        }
+    };
+
+    let mut album: album::ActiveModel = album.into();
+    album.title = Set(updated_album.title);
+    album.slug = Set(updated_album.slug);
+    album.description = Set(updated_album.description);
+    album.cover_image_id = Set(updated_album.cover_image_id);
+    album.visibility = Set(updated_album.visibility.name().to_string());
+    album.updated_at =
+        Set(Utc::now().with_timezone(&FixedOffset::west_opt(3 * 3600).expect("Invalid Timezone")));
+
+    let transaction = match state.conn.begin().await {
+        Ok(transaction) => transaction,
+        Err(db_err) => {
+            return Err(ServerFnError::new(format!(
+                "A error happened while starting a new transaction over database. DbErr: {}",
+                db_err.to_string()
+            )))
+        }
+    };
+
+    let mut image_ids = updated_album
+        .images
+        .iter()
+        .map(|image| image.id)
+        .collect::<Vec<_>>();
+    // An image is only once on an album, in the first place it was put
+    let mut seen = vec![];
+    image_ids.retain(|id| {
+        let first = !seen.contains(id);
+        seen.push(*id);
+        first
+    });
+
+    let updated = async {
+        let album = album.update(&transaction).await?;
+
+        AlbumImage::delete_many()
+            .filter(album_image::Column::AlbumId.eq(album.id))
+            .exec(&transaction)
+            .await?;
+
+        for (position, image_id) in image_ids.into_iter().enumerate() {
+            album_image::ActiveModel {
+                album_id: Set(album.id),
+                image_id: Set(image_id),
+                position: Set(position as i32),
+                ..Default::default()
+            }
+            .insert(&transaction)
+            .await?;
+        }
+
+        transaction.commit().await?;
+        with_images(&state.conn, vec![album], false).await
+    }
+    .await;
+
+    match updated {
+        Ok(mut albums) => Ok(albums.remove(0)),
+        Err(db_err) => Err(ServerFnError::new(format!(
+            "A error happened when updating the album, try again later. DbErr: {}",
+            db_err.to_string()
+        ))),
+    }
+}
+
+#[server(DeleteAlbum, "/api/albums")]
+pub async fn delete_album(album_id: i32) -> Result<Option<AlbumModel>, ServerFnError> {
+    if let Some(user) = user_logged_in().await? {
+        if user_have_permission(user, "Escrever".to_string()).await? {
+            return delete_album_guard(album_id).await;
+        } else {
+            return Err(ServerFnError::new(
+                "User doesn't have the permission to execute this operation.",
+            ));
+        }
+    } else {
+        return Err(ServerFnError::new("User is not logged in."));
+    }
+}
+
+/// Delete an album. It's images stay on the gallery.
+#[cfg(feature = "ssr")]
+pub async fn delete_album_guard(album_id: i32) -> Result<Option<AlbumModel>, ServerFnError> {
+    use crate::AppState;
+
+    use entities::prelude::Album;
+    use sea_orm::{EntityTrait, ModelTrait};
+
+    let state: Arc<Mutex<AppState>> = use_context().unwrap();
+    let state = state.as_ref().lock().await;
+
+    let album = match Album::find_by_id(album_id).one(&state.conn).await {
+        Ok(Some(album)) => album,
+        Ok(None) => return Ok(None),
+        Err(db_err) => {
+            return Err(ServerFnError::new(format!(
+                "A error happened when requesting the album, try again later. DbErr: {}",
+                db_err.to_string()
+            )))
+        }
+    };
+
+    let deleted_album = AlbumModel::from(album.clone());
+
+    match album.delete(&state.conn).await {
+        Ok(_) => Ok(Some(deleted_album)),
+        Err(db_err) => Err(ServerFnError::new(format!(
+            "A error happened when removing the album, try again later. DbErr: {}",
+            db_err.to_string()
+        ))),
+    }
+}
@@ -1,15 +1,102 @@
+use chrono::prelude::*;
 use leptos::*;
 use serde::{Deserialize, Serialize};
 use server_fn::codec::{GetUrl, MultipartData, MultipartFormData};
 use std::sync::Arc;
 
 #[cfg(feature = "ssr")]
-use tokio::{fs, io::AsyncWriteExt, sync::Mutex};
+use std::collections::HashMap;
+#[cfg(feature = "ssr")]
+use tokio::{fs, sync::Mutex};
+
+use super::users::{user_have_permission, user_logged_in};
+
+/// The locales alt text can be written in, the default one first.
+pub const ALT_TEXT_LOCALES: [&str; 2] = ["en-US", "pt-BR"];
+
+#[derive(Clone, Debug, PartialEq, Default, Deserialize, Serialize)]
+pub struct ImageAltTextModel {
+    pub locale: String,
+    pub alt_text: String,
+}
+
+#[cfg(feature = "ssr")]
+impl From<entities::image_alt_text::Model> for ImageAltTextModel {
+    fn from(value: entities::image_alt_text::Model) -> Self {
+        Self {
+            locale: value.locale,
+            alt_text: value.alt_text,
+        }
+    }
+}
 
 #[derive(Clone, Debug, PartialEq, Default, Deserialize, Serialize)]
 pub struct ImageMetadataModel {
     pub id: i32,
     pub image_path: String,
+    pub mime_type: String,
+    pub original_filename: Option<String>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    /// When the photo was taken, from it's EXIF, in the camera's local time.
+    pub captured_at: Option<NaiveDateTime>,
+    /// One per locale, only filled by `get_image_metadata`.
+    pub alt_texts: Vec<ImageAltTextModel>,
+    pub caption: Option<String>,
+    pub credit: Option<String>,
+    pub license: Option<String>,
+    pub byte_size: Option<i64>,
+    pub uploader_id: Option<i32>,
+    pub created_at: DateTime<FixedOffset>,
+    /// How many articles show the image, only filled by `list_images` and `get_image_metadata`.
+    pub article_count: u64,
+    /// A tiny preview shown while the image loads, see `crate::gallery::placeholder`.
+    pub placeholder: Option<String>,
+}
+
+impl ImageMetadataModel {
+    pub fn alt_text(&self, locale: &str) -> Option<&str> {
+        self.alt_texts
+            .iter()
+            .find(|alt_text| alt_text.locale == locale)
+            .map(|alt_text| alt_text.alt_text.as_str())
+    }
+}
+
+/// What readers get of an image, on albums. Unlike `ImageMetadataModel` it doesn't carry where
+/// the file is, who uploaded it or when the photo was taken.
+#[derive(Clone, Debug, PartialEq, Default, Deserialize, Serialize)]
+pub struct PublicImageModel {
+    pub id: i32,
+    pub alt_texts: Vec<ImageAltTextModel>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub placeholder: Option<String>,
+    pub caption: Option<String>,
+    pub credit: Option<String>,
+}
+
+impl PublicImageModel {
+    pub fn alt_text(&self, locale: &str) -> Option<&str> {
+        self.alt_texts
+            .iter()
+            .find(|alt_text| alt_text.locale == locale)
+            .map(|alt_text| alt_text.alt_text.as_str())
+    }
+}
+
+impl From<ImageMetadataModel> for PublicImageModel {
+    fn from(value: ImageMetadataModel) -> Self {
+        Self {
+            id: value.id,
+            alt_texts: value.alt_texts,
+            width: value.width,
+            height: value.height,
+            placeholder: value.placeholder,
+            caption: value.caption,
+            credit: value.credit,
+        }
+    }
 }
 
 #[cfg(feature = "ssr")]
@@ -18,72 +105,1067 @@ impl From<entities::image_metadata::Model> for ImageMetadataModel {
         Self {
             id: value.id,
             image_path: value.image_path,
+            mime_type: value.mime_type,
+            original_filename: value.original_filename,
+            width: value.width,
+            height: value.height,
+            captured_at: value.captured_at,
+            alt_texts: vec![],
+            caption: value.caption,
+            credit: value.credit,
+            license: value.license,
+            byte_size: value.byte_size,
+            uploader_id: value.uploader_id,
+            created_at: value.created_at,
+            article_count: 0,
+            placeholder: value.placeholder,
+        }
+    }
+}
+
+pub const IMAGES_PER_PAGE: u64 = 24;
+
+/// The filters of `list_images`. Dates are days on the site's time zone, both included.
+#[derive(Clone, Debug, PartialEq, Default, Deserialize, Serialize)]
+pub struct ImageFilter {
+    pub uploader_id: Option<i32>,
+    pub uploaded_from: Option<NaiveDate>,
+    pub uploaded_until: Option<NaiveDate>,
+    pub mime_type: Option<String>,
+    /// Only images no article, cover or avatar shows.
+    pub unused: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub enum ImageSort {
+    #[default]
+    Newest,
+    Oldest,
+    Largest,
+    Smallest,
+}
+
+impl ImageSort {
+    pub const ALL: [ImageSort; 4] = [
+        ImageSort::Newest,
+        ImageSort::Oldest,
+        ImageSort::Largest,
+        ImageSort::Smallest,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ImageSort::Newest => "newest",
+            ImageSort::Oldest => "oldest",
+            ImageSort::Largest => "largest",
+            ImageSort::Smallest => "smallest",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ImageSort> {
+        Self::ALL.into_iter().find(|sort| sort.name() == name)
+    }
+}
+
+/// A page of `list_images`, counting from 0.
+#[derive(Clone, Debug, PartialEq, Default, Deserialize, Serialize)]
+pub struct ImagePageModel {
+    pub images: Vec<ImageMetadataModel>,
+    pub page: u64,
+    pub pages: u64,
+    pub total: u64,
+}
+
+/// The resized variants generated for every raster image, served on `/gallery/:id/:size`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ImageSize {
+    Thumbnail,
+    Medium,
+    Large,
+}
+
+impl ImageSize {
+    pub const ALL: [ImageSize; 3] = [ImageSize::Thumbnail, ImageSize::Medium, ImageSize::Large];
+
+    /// The widest a variant can be. Images are never enlarged, so variants of small images keep
+    /// the original size.
+    pub fn width(&self) -> u32 {
+        match self {
+            ImageSize::Thumbnail => 320,
+            ImageSize::Medium => 800,
+            ImageSize::Large => 1600,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ImageSize::Thumbnail => "thumbnail",
+            ImageSize::Medium => "medium",
+            ImageSize::Large => "large",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ImageSize> {
+        Self::ALL.into_iter().find(|size| size.name() == name)
+    }
+}
+
+/// The URL of an image, or of one of it's variants.
+pub fn image_url(image_id: i32, size: Option<ImageSize>) -> String {
+    match size {
+        Some(size) => format!("/gallery/{}/{}", image_id, size.name()),
+        None => format!("/gallery/{}", image_id),
+    }
+}
+
+/// The `srcset` listing every variant of an image.
+pub fn image_srcset(image_id: i32) -> String {
+    ImageSize::ALL
+        .iter()
+        .map(|size| format!("{} {}w", image_url(image_id, Some(*size)), size.width()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Removes the placeholder of an image once it loads. It's an inline handler, so it also runs for
+/// images that load before the page hydrates and inside the HTML of articles.
+pub const PLACEHOLDER_ONLOAD: &str = "this.style.backgroundImage='none'";
+
+/// What pages need to hold the place of an image before it loads: it's size, so nothing moves
+/// around it, and it's placeholder.
+#[derive(Clone, Debug, PartialEq, Default, Deserialize, Serialize)]
+pub struct ImagePreview {
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub placeholder: Option<String>,
+}
+
+impl ImagePreview {
+    /// The inline style showing the placeholder behind the image.
+    pub fn style(&self) -> Option<String> {
+        self.placeholder.as_ref().map(|placeholder| {
+            format!(
+                "background-image:url({});background-size:cover;background-position:center",
+                placeholder
+            )
+        })
+    }
+}
+
+impl From<&ImageMetadataModel> for ImagePreview {
+    fn from(value: &ImageMetadataModel) -> Self {
+        Self {
+            width: value.width,
+            height: value.height,
+            placeholder: value.placeholder.clone(),
+        }
+    }
+}
+
+impl From<&PublicImageModel> for ImagePreview {
+    fn from(value: &PublicImageModel) -> Self {
+        Self {
+            width: value.width,
+            height: value.height,
+            placeholder: value.placeholder.clone(),
         }
     }
 }
 
 // Image Create/Read/Delete
 
+#[cfg(feature = "ssr")]
+async fn find_image_by_hash(
+    state: &Arc<Mutex<crate::AppState>>,
+    hash: &str,
+) -> Result<Option<entities::image_metadata::Model>, ServerFnError> {
+    use entities::{image_metadata, prelude::ImageMetadata};
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+
+    let state = state.as_ref().lock().await;
+
+    match ImageMetadata::find()
+        .filter(image_metadata::Column::ContentHash.eq(hash))
+        .one(&state.conn)
+        .await
+    {
+        Ok(image) => Ok(image),
+        Err(db_err) => Err(ServerFnError::new(format!(
+            "A error happened when requesting the image, try again later. DbErr: {}",
+            db_err.to_string()
+        ))),
+    }
+}
+
+/// Answer with `status` instead of the 500 every server function error gets.
+#[cfg(feature = "ssr")]
+fn set_status(status: http::StatusCode) {
+    if let Some(response) = use_context::<leptos_axum::ResponseOptions>() {
+        response.set_status(status);
+    }
+}
+
+/// Check that the user is logged in and can send and remove gallery images, answering with a
+/// 401 or a 403 when not. Returns the user id.
+#[cfg(feature = "ssr")]
+async fn require_gallery_permission() -> Result<i32, ServerFnError> {
+    use http::StatusCode;
+
+    match user_logged_in().await? {
+        Some(user) if user_have_permission(user, "Galeria".to_string()).await? => Ok(user),
+        Some(_) => {
+            set_status(StatusCode::FORBIDDEN);
+            Err(ServerFnError::new(
+                "User doesn't have the permission to execute this operation.",
+            ))
+        }
+        None => {
+            set_status(StatusCode::UNAUTHORIZED);
+            Err(ServerFnError::new("User is not logged in."))
+        }
+    }
+}
+
+/// How many bytes the images uploaded by a user take.
+#[cfg(feature = "ssr")]
+async fn uploaded_bytes(
+    state: &Arc<Mutex<crate::AppState>>,
+    uploader_id: i32,
+) -> Result<u64, ServerFnError> {
+    use entities::{image_metadata, prelude::ImageMetadata};
+    use sea_orm::{
+        sea_query::{Alias, Expr, Func, SimpleExpr},
+        ColumnTrait, EntityTrait, QueryFilter, QuerySelect,
+    };
+
+    let state = state.as_ref().lock().await;
+
+    // `SUM` of a `bigint` is a `numeric` on Postgres
+    let used = ImageMetadata::find()
+        .select_only()
+        .column_as(
+            SimpleExpr::from(Func::cast_as(
+                Func::sum(Expr::col(image_metadata::Column::ByteSize)),
+                Alias::new("bigint"),
+            )),
+            "used",
+        )
+        .filter(image_metadata::Column::UploaderId.eq(uploader_id))
+        .into_tuple::<Option<i64>>()
+        .one(&state.conn)
+        .await;
+
+    match used {
+        Ok(used) => Ok(used.flatten().unwrap_or_default().max(0) as u64),
+        Err(db_err) => Err(ServerFnError::new(format!(
+            "A error happened when requesting the storage used, try again later. DbErr: {}",
+            db_err.to_string()
+        ))),
+    }
+}
+
+/// What happened to one of the files of an upload.
+#[derive(Clone, Debug, PartialEq, Default, Deserialize, Serialize)]
+pub struct UploadResultModel {
+    pub original_filename: Option<String>,
+    /// The image saved. Content already on the gallery gets a new image sharing it's file.
+    pub image: Option<ImageMetadataModel>,
+    pub error: Option<String>,
+}
+
+/// Sanitize and save an uploaded image, see `crate::gallery`. Failures come with the status the
+/// request should get when no file of it could be saved.
+#[cfg(feature = "ssr")]
+async fn store_image(
+    state: &Arc<Mutex<crate::AppState>>,
+    uploader_id: i32,
+    user_quota: u64,
+    original_filename: Option<String>,
+    content: Vec<u8>,
+) -> Result<ImageMetadataModel, (http::StatusCode, String)> {
+    use crate::gallery::metadata::sanitize;
+    use crate::gallery::mime::{validate_svg, ImageType};
+    use crate::gallery::placeholder::generate_placeholder;
+    use crate::gallery::storage::{content_hash, remove_image, storage_path, write_image};
+    use crate::gallery::variants::write_variants;
+
+    use entities::image_metadata;
+    use http::StatusCode;
+    use sea_orm::{ActiveModelTrait, Set};
+
+    let internal = |err: ServerFnError| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string());
+
+    // The type comes from the content, whatever the name or the browser says
+    let image_type = match ImageType::sniff(&content) {
+        Some(image_type) => image_type,
+        None => {
+            return Err((
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "This file is not a supported image. Send a PNG, JPEG, GIF, WebP or SVG."
+                    .to_string(),
+            ))
+        }
+    };
+
+    if image_type == ImageType::Svg {
+        if let Err(reason) = validate_svg(&content) {
+            return Err((StatusCode::UNSUPPORTED_MEDIA_TYPE, reason));
+        }
+    }
+
+    // Drop EXIF, XMP and IPTC before anything is written. Images without a placeholder still
+    // load, only without a preview, so failing to make one isn't an error.
+    let sanitized = tokio::task::spawn_blocking(move || {
+        let (content, metadata) = sanitize(content, image_type)?;
+        let placeholder = generate_placeholder(&content, image_type).unwrap_or(None);
+        Ok::<_, String>((content, metadata, placeholder))
+    })
+    .await;
+    let (content, metadata, placeholder) = match sanitized {
+        Ok(Ok(sanitized)) => sanitized,
+        Ok(Err(err)) => return Err((StatusCode::UNPROCESSABLE_ENTITY, err)),
+        Err(join_err) => return Err((StatusCode::INTERNAL_SERVER_ERROR, join_err.to_string())),
+    };
+
+    let hash = content_hash(&content);
+    let (media, gallery_lock) = {
+        let state = state.as_ref().lock().await;
+        (state.media.clone(), state.gallery_lock.clone())
+    };
+
+    // Until the row is inserted, so a delete of the same content can't remove the file we share,
+    // and uploads running together can't all fit on what's left of the quota
+    let _gallery = gallery_lock.lock().await;
+
+    // Every upload is charged to it's uploader, even when the content is on the gallery already
+    let byte_size = content.len() as i64;
+    let used = uploaded_bytes(state, uploader_id).await.map_err(internal)?;
+    if used + byte_size as u64 > user_quota {
+        return Err((
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!(
+                "The image doesn't fit on your storage quota of {} MiB, {:.1} MiB are used already.",
+                user_quota / (1024 * 1024),
+                used as f64 / (1024.0 * 1024.0)
+            ),
+        ));
+    }
+
+    // The same content was uploaded before, so the new row shares it's files
+    let shared = find_image_by_hash(state, &hash).await.map_err(internal)?;
+    let file_name = match &shared {
+        Some(shared) => shared.image_path.clone(),
+        None => storage_path(&hash, image_type),
+    };
+
+    if shared.is_none() {
+        if let Err(file_err) =
+            write_image(media.as_ref(), &file_name, content.clone(), image_type).await
+        {
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!(
+                    "A error occured when saving the image. FileErr: {}",
+                    file_err
+                ),
+            ));
+        }
+
+        // Until the row is in nothing points to the files, so they go away when anything fails
+        if let Err(err) = write_variants(media.as_ref(), &file_name, content, image_type).await {
+            let _ = remove_image(media.as_ref(), &file_name, Some(image_type)).await;
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("A error occured when resizing the image. ImageErr: {}", err),
+            ));
+        }
+    }
+
+    let image_meta = image_metadata::ActiveModel {
+        image_path: Set(file_name.clone()),
+        mime_type: Set(image_type.mime_type().to_string()),
+        original_filename: Set(original_filename),
+        width: Set(metadata.width),
+        height: Set(metadata.height),
+        captured_at: Set(metadata.captured_at),
+        placeholder: Set(placeholder),
+        content_hash: Set(Some(hash)),
+        byte_size: Set(Some(byte_size)),
+        uploader_id: Set(Some(uploader_id)),
+        created_at: Set(
+            Utc::now().with_timezone(&FixedOffset::west_opt(3 * 3600).expect("Invalid Timezone"))
+        ),
+        ..Default::default()
+    };
+
+    let inserted = {
+        let state = state.as_ref().lock().await;
+        image_meta.insert(&state.conn).await
+    };
+
+    match inserted {
+        Ok(image) => Ok(ImageMetadataModel::from(image)),
+        Err(db_err) => {
+            if shared.is_none() {
+                let _ = remove_image(media.as_ref(), &file_name, Some(image_type)).await;
+            }
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!(
+                    "A error happened when saving the image, try again later. DbErr: {}",
+                    db_err.to_string()
+                ),
+            ))
+        }
+    }
+}
+
+/// Upload images to the gallery, every `file_to_upload` field of the form is one, with a result
+/// for each. Users need the "Galeria" permission, and each file and everything a user sent are
+/// limited by `UploadLimits`. A file over the size limit ends the request with a 413 right away,
+/// files before it are kept. When no file could be saved, the request gets the status of the
+/// first failure, like a 413 for going over the quota.
 #[server(UploadImage, "/api/gallery", input = MultipartFormData)]
 pub async fn upload_image_to_gallery(
     data: MultipartData,
-) -> Result<ImageMetadataModel, ServerFnError> {
+) -> Result<Vec<UploadResultModel>, ServerFnError> {
+    use crate::gallery::storage::original_filename;
+    use crate::AppState;
+
+    use http::StatusCode;
+
+    // Checked before reading anything of the request
+    let uploader_id = require_gallery_permission().await?;
+
+    let state: Arc<Mutex<AppState>> = use_context().unwrap();
+    let limits = state.as_ref().lock().await.upload_limits;
     let mut data = data.into_inner().unwrap();
+
+    let mut results = vec![];
+    let mut first_failure = None;
+
+    while let Ok(Some(mut field)) = data.next_field().await {
+        if field.name() != Some("file_to_upload") {
+            continue;
+        }
+        // Only kept as metadata, the file is stored by it's hash
+        let original_filename = field.file_name().and_then(original_filename);
+
+        let mut content = vec![];
+        while let Ok(Some(chunk)) = field.chunk().await {
+            // Answer as soon as the limit is crossed, instead of reading the rest of the request
+            if (content.len() + chunk.len()) as u64 > limits.max_file_size {
+                set_status(StatusCode::PAYLOAD_TOO_LARGE);
+                return Err(ServerFnError::new(format!(
+                    "The image is bigger than the limit of {} MiB.",
+                    limits.max_file_size / (1024 * 1024)
+                )));
+            }
+            content.extend_from_slice(&chunk);
+        }
+
+        let stored = store_image(
+            &state,
+            uploader_id,
+            limits.user_quota,
+            original_filename.clone(),
+            content,
+        )
+        .await;
+
+        results.push(match stored {
+            Ok(image) => UploadResultModel {
+                original_filename,
+                image: Some(image),
+                error: None,
+            },
+            Err((status, error)) => {
+                first_failure.get_or_insert(status);
+                UploadResultModel {
+                    original_filename,
+                    image: None,
+                    error: Some(error),
+                }
+            }
+        });
+    }
+
+    if results.is_empty() {
+        set_status(StatusCode::BAD_REQUEST);
+        return Err(ServerFnError::new("No image was sent."));
+    }
+    if results.iter().all(|result| result.image.is_none()) {
+        if let Some(status) = first_failure {
+            set_status(status);
+        }
+        // A single file gets it's error, like before uploads were batched
+        if let [result] = results.as_slice() {
+            return Err(ServerFnError::new(result.error.clone().unwrap_or_default()));
+        }
+    }
+
+    Ok(results)
+}
+
+/// List the gallery a page of `IMAGES_PER_PAGE` at a time, filtered and sorted.
+#[server(ListImages, "/api/gallery")]
+pub async fn list_images(
+    filter: ImageFilter,
+    sort: ImageSort,
+    page: u64,
+) -> Result<ImagePageModel, ServerFnError> {
+    if let Some(user) = user_logged_in().await? {
+        if user_have_permission(user, "Escrever".to_string()).await? {
+            return list_images_guard(filter, sort, page).await;
+        } else {
+            return Err(ServerFnError::new(
+                "User doesn't have the permission to execute this operation.",
+            ));
+        }
+    } else {
+        return Err(ServerFnError::new("User is not logged in."));
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub async fn list_images_guard(
+    filter: ImageFilter,
+    sort: ImageSort,
+    page: u64,
+) -> Result<ImagePageModel, ServerFnError> {
     use crate::AppState;
 
     use entities::image_metadata;
-    use sea_orm::{ActiveModelTrait, Set};
+    use entities::prelude::ImageMetadata;
+    use sea_orm::{
+        sea_query::NullOrdering, ColumnTrait, EntityTrait, Order, PaginatorTrait, QueryFilter,
+        QueryOrder, QueryTrait,
+    };
 
     let state: Arc<Mutex<AppState>> = use_context().unwrap();
     let state = state.as_ref().lock().await;
 
-    // this will just measure the total number of bytes uploaded
-    while let Ok(Some(mut field)) = data.next_field().await {
-        let name = field.name().unwrap_or_default().to_string();
-        let file_name = format!("data/uploads/{}", field.file_name().unwrap_or("data_file"));
-        if name == "file_to_upload" {
-            let mut file = fs::OpenOptions::new()
-                .create(true)
-                .write(true)
-                .open(&file_name)
+    let timezone = FixedOffset::west_opt(3 * 3600).expect("Invalid Timezone");
+    let mut query = ImageMetadata::find();
+
+    if let Some(uploader_id) = filter.uploader_id {
+        query = query.filter(image_metadata::Column::UploaderId.eq(uploader_id));
+    }
+    if let Some(from) = filter
+        .uploaded_from
+        .and_then(|from| from.and_hms_opt(0, 0, 0))
+        .and_then(|from| from.and_local_timezone(timezone).single())
+    {
+        query = query.filter(image_metadata::Column::CreatedAt.gte(from));
+    }
+    // The whole last day is included
+    if let Some(until) = filter
+        .uploaded_until
+        .and_then(|until| until.succ_opt())
+        .and_then(|until| until.and_hms_opt(0, 0, 0))
+        .and_then(|until| until.and_local_timezone(timezone).single())
+    {
+        query = query.filter(image_metadata::Column::CreatedAt.lt(until));
+    }
+    if let Some(mime_type) = filter.mime_type.filter(|mime_type| !mime_type.is_empty()) {
+        query = query.filter(image_metadata::Column::MimeType.eq(mime_type));
+    }
+    if filter.unused {
+        match used_image_ids(&state.conn).await {
+            Ok(used) => query = query.filter(image_metadata::Column::Id.is_not_in(used)),
+            Err(db_err) => {
+                return Err(ServerFnError::new(format!(
+                    "A error happened when requesting the images, try again later. DbErr: {}",
+                    db_err.to_string()
+                )))
+            }
+        }
+    }
+
+    // Images uploaded before the size was recorded go last either way
+    let (column, order) = match sort {
+        ImageSort::Newest => (image_metadata::Column::CreatedAt, Order::Desc),
+        ImageSort::Oldest => (image_metadata::Column::CreatedAt, Order::Asc),
+        ImageSort::Largest => (image_metadata::Column::ByteSize, Order::Desc),
+        ImageSort::Smallest => (image_metadata::Column::ByteSize, Order::Asc),
+    };
+    QueryTrait::query(&mut query).order_by_with_nulls(column, order, NullOrdering::Last);
+    query = query.order_by_desc(image_metadata::Column::Id);
+
+    let paginator = query.paginate(&state.conn, IMAGES_PER_PAGE);
+    let listed = match paginator.num_items_and_pages().await {
+        Ok(totals) => paginator
+            .fetch_page(page)
+            .await
+            .map(|images| (totals, images)),
+        Err(db_err) => Err(db_err),
+    };
+
+    let listed = match listed {
+        Ok((totals, images)) => {
+            let ids: Vec<i32> = images.iter().map(|image| image.id).collect();
+            article_counts(&state.conn, &ids)
                 .await
-                .unwrap();
+                .map(|counts| (totals, images, counts))
+        }
+        Err(db_err) => Err(db_err),
+    };
+
+    match listed {
+        Ok((totals, images, counts)) => Ok(ImagePageModel {
+            images: images
+                .into_iter()
+                .map(|image| ImageMetadataModel {
+                    article_count: counts.get(&image.id).copied().unwrap_or_default(),
+                    ..ImageMetadataModel::from(image)
+                })
+                .collect(),
+            page,
+            pages: totals.number_of_pages,
+            total: totals.number_of_items,
+        }),
+        Err(db_err) => Err(ServerFnError::new(format!(
+            "A error happened when requesting the images, try again later. DbErr: {}",
+            db_err.to_string()
+        ))),
+    }
+}
+
+/// The id of a gallery image from it's site-relative URL, like `/gallery/12` or
+/// `/gallery/12/medium`. URLs with a host may be of other sites, so they're not taken.
+pub fn image_id_from_url(url: &str) -> Option<i32> {
+    let path = url.strip_prefix("/gallery/")?;
+    let id = &path[..path.find(['/', '?', '#']).unwrap_or(path.len())];
+    if id.is_empty() || !id.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    id.parse().ok()
+}
+
+/// Whether `c` can't be part of a URL written on Markdown or HTML, so it starts or ends one.
+fn bounds_url(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '(' | ')' | '[' | ']' | '<' | '>' | '"' | '\'' | '`')
+}
+
+/// Every gallery image an article shows, in it's content, with the `image` shortcode or by URL,
+/// and as it's cover. Fenced code is skipped, like when rendering.
+pub fn referenced_image_ids(content: &str, cover_image: Option<&str>) -> Vec<i32> {
+    let mut ids = crate::markdown::shortcodes::image_ids(content);
+
+    crate::markdown::shortcodes::outside_fences(content, |line| {
+        for (start, _) in line.match_indices("/gallery/") {
+            if !line[..start].chars().next_back().map_or(true, bounds_url) {
+                continue;
+            }
+            let url = &line[start..];
+            let end = url.find(bounds_url).unwrap_or(url.len());
+            ids.extend(image_id_from_url(&url[..end]));
+        }
+        line.to_string()
+    });
+    ids.extend(cover_image.and_then(image_id_from_url));
+
+    ids.sort_unstable();
+    ids.dedup();
+    ids
+}
+
+/// Replace the images an article uses on `image_usage`. Ids of images that don't exist are
+/// skipped.
+#[cfg(feature = "ssr")]
+pub async fn sync_image_usage<C: sea_orm::ConnectionTrait>(
+    conn: &C,
+    post_id: i32,
+    content: &str,
+    cover_image: Option<&str>,
+) -> Result<(), sea_orm::DbErr> {
+    use entities::prelude::{ImageMetadata, ImageUsage};
+    use entities::{image_metadata, image_usage};
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QuerySelect, Set};
+
+    ImageUsage::delete_many()
+        .filter(image_usage::Column::PostId.eq(post_id))
+        .exec(conn)
+        .await?;
+
+    let ids = referenced_image_ids(content, cover_image);
+    if ids.is_empty() {
+        return Ok(());
+    }
+
+    let existing: Vec<i32> = ImageMetadata::find()
+        .select_only()
+        .column(image_metadata::Column::Id)
+        .filter(image_metadata::Column::Id.is_in(ids))
+        .into_tuple()
+        .all(conn)
+        .await?;
+    if existing.is_empty() {
+        return Ok(());
+    }
 
-            let image_meta = image_metadata::ActiveModel {
-                image_path: Set(file_name.clone()),
+    ImageUsage::insert_many(
+        existing
+            .into_iter()
+            .map(|image_id| image_usage::ActiveModel {
+                image_id: Set(image_id),
+                post_id: Set(post_id),
                 ..Default::default()
+            }),
+    )
+    .exec(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Fill `image_usage` from every article, for the ones saved before it existed.
+#[cfg(feature = "ssr")]
+pub async fn rebuild_image_usage(conn: &sea_orm::DatabaseConnection) -> Result<(), sea_orm::DbErr> {
+    use entities::prelude::PostMetadata;
+    use sea_orm::EntityTrait;
+
+    for article in PostMetadata::find().all(conn).await? {
+        let content = fs::read_to_string(&article.content_path)
+            .await
+            .unwrap_or_default();
+        sync_image_usage(conn, article.id, &content, article.cover_image.as_deref()).await?;
+    }
+
+    Ok(())
+}
+
+/// Generate the placeholder of every raster image that has none, for the ones uploaded before
+/// placeholders existed. Images that can't be read are skipped, and tried again on the next start.
+#[cfg(feature = "ssr")]
+pub async fn backfill_placeholders(
+    conn: &sea_orm::DatabaseConnection,
+    media: &dyn crate::gallery::media::MediaStore,
+) -> Result<(), sea_orm::DbErr> {
+    use crate::gallery::mime::ImageType;
+    use crate::gallery::placeholder::generate_placeholder;
+
+    use entities::{image_metadata, prelude::ImageMetadata};
+    use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set};
+
+    let missing = ImageMetadata::find()
+        .filter(image_metadata::Column::Placeholder.is_null())
+        .filter(image_metadata::Column::MimeType.ne(ImageType::Svg.mime_type()))
+        .all(conn)
+        .await?;
+
+    for image in missing {
+        let Some(image_type) = ImageType::from_mime_type(&image.mime_type) else {
+            continue;
+        };
+        let Ok(Some(content)) = media.get(&image.image_path).await else {
+            continue;
+        };
+        let placeholder =
+            match tokio::task::spawn_blocking(move || generate_placeholder(&content, image_type))
+                .await
+            {
+                Ok(Ok(Some(placeholder))) => placeholder,
+                _ => continue,
             };
 
-            let image = image_meta.insert(&state.conn).await.unwrap();
+        let mut image: image_metadata::ActiveModel = image.into();
+        image.placeholder = Set(Some(placeholder));
+        image.update(conn).await?;
+    }
 
-            while let Ok(Some(chunk)) = field.chunk().await {
-                file.write(&chunk).await.unwrap();
-            }
+    Ok(())
+}
 
-            return Ok(ImageMetadataModel::from(image));
-        }
+/// How many articles use each of the images, by id. Unused images are left out.
+#[cfg(feature = "ssr")]
+async fn article_counts(
+    conn: &sea_orm::DatabaseConnection,
+    ids: &[i32],
+) -> Result<HashMap<i32, u64>, sea_orm::DbErr> {
+    use entities::{image_usage, prelude::ImageUsage};
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+
+    let mut counts = HashMap::new();
+    if ids.is_empty() {
+        return Ok(counts);
     }
 
-    Err(ServerFnError::new(
-        "Something went wrong on uploading this image.",
-    ))
+    for usage in ImageUsage::find()
+        .filter(image_usage::Column::ImageId.is_in(ids.to_vec()))
+        .all(conn)
+        .await?
+    {
+        *counts.entry(usage.image_id).or_insert(0) += 1;
+    }
+
+    Ok(counts)
+}
+
+/// Every image shown somewhere: in an article, as it's cover or as an avatar.
+#[cfg(feature = "ssr")]
+pub async fn used_image_ids(
+    conn: &sea_orm::DatabaseConnection,
+) -> Result<Vec<i32>, sea_orm::DbErr> {
+    use entities::image_usage;
+    use entities::prelude::{ImageUsage, UserProfile};
+    use sea_orm::{EntityTrait, QuerySelect};
+
+    let mut used: Vec<i32> = ImageUsage::find()
+        .select_only()
+        .column(image_usage::Column::ImageId)
+        .distinct()
+        .into_tuple()
+        .all(conn)
+        .await?;
+
+    for profile in UserProfile::find().all(conn).await? {
+        used.extend(profile.avatar.as_deref().and_then(image_id_from_url));
+    }
+
+    used.sort_unstable();
+    used.dedup();
+    Ok(used)
+}
+
+/// Read an image's metadata together with it's alt texts.
+#[server(ReadImageMetadata, "/api/gallery")]
+pub async fn get_image_metadata(
+    image_id: i32,
+) -> Result<Option<ImageMetadataModel>, ServerFnError> {
+    if let Some(user) = user_logged_in().await? {
+        if user_have_permission(user, "Escrever".to_string()).await? {
+            return get_image_metadata_guard(image_id).await;
+        } else {
+            return Err(ServerFnError::new(
+                "User doesn't have the permission to execute this operation.",
+            ));
+        }
+    } else {
+        return Err(ServerFnError::new("User is not logged in."));
+    }
 }
 
-#[server(GetImageLen, "/api/gallery")]
-pub async fn get_image_len() -> Result<usize, ServerFnError> {
+#[cfg(feature = "ssr")]
+pub async fn get_image_metadata_guard(
+    image_id: i32,
+) -> Result<Option<ImageMetadataModel>, ServerFnError> {
     use crate::AppState;
 
-    use entities::image_metadata::Model;
-    use entities::prelude::ImageMetadata;
+    use entities::prelude::{ImageAltText, ImageMetadata};
     use sea_orm::EntityTrait;
 
     let state: Arc<Mutex<AppState>> = use_context().unwrap();
     let state = state.as_ref().lock().await;
 
-    match ImageMetadata::find().all(&state.conn).await {
-        Ok(images) => Ok(images.len()),
-        _ => Ok(0),
+    let found = match ImageMetadata::find_by_id(image_id)
+        .find_with_related(ImageAltText)
+        .all(&state.conn)
+        .await
+    {
+        Ok(images) => article_counts(&state.conn, &[image_id])
+            .await
+            .map(|counts| (images, counts)),
+        Err(db_err) => Err(db_err),
+    };
+
+    match found {
+        Ok((images, counts)) => {
+            Ok(images
+                .into_iter()
+                .next()
+                .map(|(image, alt_texts)| ImageMetadataModel {
+                    alt_texts: alt_texts.into_iter().map(ImageAltTextModel::from).collect(),
+                    article_count: counts.get(&image.id).copied().unwrap_or_default(),
+                    ..ImageMetadataModel::from(image)
+                }))
+        }
+        Err(db_err) => Err(ServerFnError::new(format!(
+            "A error happened when requesting the image, try again later. DbErr: {}",
+            db_err.to_string()
+        ))),
+    }
+}
+
+/// Update the alt texts, caption, credit and license of an image. Empty alt texts are removed.
+#[server(UpdateImageMetadata, "/api/gallery")]
+pub async fn update_image_metadata(
+    updated_image: ImageMetadataModel,
+) -> Result<ImageMetadataModel, ServerFnError> {
+    if let Some(user) = user_logged_in().await? {
+        if user_have_permission(user, "Escrever".to_string()).await? {
+            return update_image_metadata_guard(updated_image).await;
+        } else {
+            return Err(ServerFnError::new(
+                "User doesn't have the permission to execute this operation.",
+            ));
+        }
+    } else {
+        return Err(ServerFnError::new("User is not logged in."));
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub async fn update_image_metadata_guard(
+    updated_image: ImageMetadataModel,
+) -> Result<ImageMetadataModel, ServerFnError> {
+    use crate::AppState;
+
+    use entities::prelude::{ImageAltText, ImageMetadata};
+    use entities::{image_alt_text, image_metadata};
+    use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set, TransactionTrait};
+
+    if let Some(alt_text) = updated_image
+        .alt_texts
+        .iter()
+        .find(|alt_text| !ALT_TEXT_LOCALES.contains(&alt_text.locale.as_str()))
+    {
+        return Err(ServerFnError::new(format!(
+            "There's no locale called {}.",
+            alt_text.locale
+        )));
+    }
+
+    let state: Arc<Mutex<AppState>> = use_context().unwrap();
+    let state = state.as_ref().lock().await;
+
+    let image = match ImageMetadata::find_by_id(updated_image.id)
+        .one(&state.conn)
+        .await
+    {
+        Ok(Some(image)) => image,
+        Ok(None) => return Err(ServerFnError::new("This image doesn't exist.")),
+        Err(db_err) => {
+            return Err(ServerFnError::new(format!(
+                "A error happened when requesting the image, try again later. DbErr: {}",
+                db_err.to_string()
+            )))
+        }
+    };
+
+    let non_empty = |text: Option<String>| {
+        text.map(|text| text.trim().to_string())
+            .filter(|text| !text.is_empty())
+    };
+
+    let mut image: image_metadata::ActiveModel = image.into();
+    image.caption = Set(non_empty(updated_image.caption));
+    image.credit = Set(non_empty(updated_image.credit));
+    image.license = Set(non_empty(updated_image.license));
+
+    let transaction = match state.conn.begin().await {
+        Ok(transaction) => transaction,
+        Err(db_err) => {
+            return Err(ServerFnError::new(format!(
+                "A error happened while starting a new transaction over database. DbErr: {}",
+                db_err.to_string()
+            )))
+        }
+    };
+
+    let updated = async {
+        image.update(&transaction).await?;
+
+        ImageAltText::delete_many()
+            .filter(image_alt_text::Column::ImageId.eq(updated_image.id))
+            .exec(&transaction)
+            .await?;
+
+        for alt_text in updated_image.alt_texts {
+            let text = alt_text.alt_text.trim().to_string();
+            if text.is_empty() {
+                continue;
+            }
+
+            image_alt_text::ActiveModel {
+                image_id: Set(updated_image.id),
+                locale: Set(alt_text.locale),
+                alt_text: Set(text),
+                ..Default::default()
+            }
+            .insert(&transaction)
+            .await?;
+        }
+
+        transaction.commit().await
+    }
+    .await;
+
+    if let Err(db_err) = updated {
+        return Err(ServerFnError::new(format!(
+            "A error happened when updating the image, try again later. DbErr: {}",
+            db_err.to_string()
+        )));
+    }
+
+    drop(state);
+    get_image_metadata_guard(updated_image.id)
+        .await?
+        .ok_or_else(|| ServerFnError::new("This image doesn't exist."))
+}
+
+/// The alt text of every image in `image_ids` that has one, in the first locale of
+/// `ALT_TEXT_LOCALES` it was written in.
+#[cfg(feature = "ssr")]
+pub async fn image_alt_texts(
+    conn: &sea_orm::DatabaseConnection,
+    image_ids: &[i32],
+) -> Result<HashMap<i32, String>, sea_orm::DbErr> {
+    use entities::{image_alt_text, prelude::ImageAltText};
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+
+    if image_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let mut alt_texts = ImageAltText::find()
+        .filter(image_alt_text::Column::ImageId.is_in(image_ids.iter().copied()))
+        .all(conn)
+        .await?;
+    alt_texts.sort_by_key(|alt_text| {
+        ALT_TEXT_LOCALES
+            .iter()
+            .position(|locale| *locale == alt_text.locale)
+            .unwrap_or(ALT_TEXT_LOCALES.len())
+    });
+
+    let mut by_image = HashMap::new();
+    for alt_text in alt_texts {
+        by_image
+            .entry(alt_text.image_id)
+            .or_insert(alt_text.alt_text);
+    }
+
+    Ok(by_image)
+}
+
+/// The size and placeholder of every image in `image_ids` that exists, by id.
+#[cfg(feature = "ssr")]
+pub async fn image_previews(
+    conn: &sea_orm::DatabaseConnection,
+    image_ids: &[i32],
+) -> Result<HashMap<i32, ImagePreview>, sea_orm::DbErr> {
+    use entities::{image_metadata, prelude::ImageMetadata};
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+
+    if image_ids.is_empty() {
+        return Ok(HashMap::new());
     }
+
+    Ok(ImageMetadata::find()
+        .filter(image_metadata::Column::Id.is_in(image_ids.iter().copied()))
+        .all(conn)
+        .await?
+        .into_iter()
+        .map(|image| {
+            let preview = ImagePreview {
+                width: image.width,
+                height: image.height,
+                placeholder: image.placeholder,
+            };
+            (image.id, preview)
+        })
+        .collect())
 }
 
 #[deprecated = "Use URL `/gallery/[image_id]` instead."]
@@ -96,15 +1178,16 @@ pub async fn get_image(image_id: i32) -> Result<Option<Vec<u8>>, ServerFnError>
     use entities::prelude::ImageMetadata;
     use sea_orm::EntityTrait;
 
-    let state: Arc<Mutex<AppState>> = use_context().unwrap();
-    let state = state.as_ref().lock().await;
+    // Reading from an object storage may take a while, so the lock isn't held for it
+    let (conn, media) = {
+        let state: Arc<Mutex<AppState>> = use_context().unwrap();
+        let state = state.as_ref().lock().await;
+        (state.conn.clone(), state.media.clone())
+    };
 
-    match ImageMetadata::find_by_id(image_id).one(&state.conn).await {
+    match ImageMetadata::find_by_id(image_id).one(&conn).await {
         Ok(image_exists) => match image_exists {
-            Some(image) => {
-                let data = fs::read(&image.image_path).await.unwrap();
-                Ok(Some(data))
-            }
+            Some(image) => Ok(media.get(&image.image_path).await.unwrap_or(None)),
             None => return Ok(None),
         },
 
@@ -120,16 +1203,55 @@ pub async fn get_image(image_id: i32) -> Result<Option<Vec<u8>>, ServerFnError>
 #[server(DeleteImage, "/api/gallery")]
 pub async fn delete_image_from_gallery(
     image_id: i32,
+    force: bool,
 ) -> Result<Option<ImageMetadataModel>, ServerFnError> {
-    use crate::AppState;
+    require_gallery_permission().await?;
+    delete_image_from_gallery_guard(image_id, force).await
+}
 
-    use entities::prelude::ImageMetadata;
-    use sea_orm::{EntityTrait, ModelTrait};
+/// Delete an image. Images still used by an article are only deleted with `force`, otherwise the
+/// request gets a 409 listing the articles.
+#[cfg(feature = "ssr")]
+pub async fn delete_image_from_gallery_guard(
+    image_id: i32,
+    force: bool,
+) -> Result<Option<ImageMetadataModel>, ServerFnError> {
+    use crate::AppState;
 
     let state: Arc<Mutex<AppState>> = use_context().unwrap();
-    let state = state.as_ref().lock().await;
+    delete_image(&state, image_id, force).await
+}
+
+/// Delete the row of an image, and it's files when no other row points to them.
+#[cfg(feature = "ssr")]
+async fn delete_image(
+    state: &Arc<Mutex<crate::AppState>>,
+    image_id: i32,
+    force: bool,
+) -> Result<Option<ImageMetadataModel>, ServerFnError> {
+    use crate::gallery::{
+        mime::ImageType,
+        storage::{content_hash, remove_image},
+        transform::remove_cached,
+    };
+
+    use entities::prelude::{ImageMetadata, ImageUsage, PostMetadata};
+    use entities::{image_metadata, image_usage};
+    use sea_orm::{ColumnTrait, EntityTrait, ModelTrait, PaginatorTrait, QueryFilter};
+
+    let (conn, media, gallery_lock) = {
+        let state = state.as_ref().lock().await;
+        (
+            state.conn.clone(),
+            state.media.clone(),
+            state.gallery_lock.clone(),
+        )
+    };
+
+    // Until the file is removed, so an upload of the same content can't count on it meanwhile
+    let _gallery = gallery_lock.lock().await;
 
-    let image = match ImageMetadata::find_by_id(image_id).one(&state.conn).await {
+    let image = match ImageMetadata::find_by_id(image_id).one(&conn).await {
         Ok(image_exists) => match image_exists {
             Some(image) => image,
             None => return Ok(None),
@@ -143,23 +1265,183 @@ pub async fn delete_image_from_gallery(
         }
     };
 
-    let deleted_image = ImageMetadataModel::from(image.clone());
-
-    match image.delete(&state.conn).await {
-        Ok(_) => match fs::remove_file(&deleted_image.image_path).await {
-            Ok(_) => Ok(Some(deleted_image)),
-            Err(file_err) => {
+    if !force {
+        let articles = match ImageUsage::find()
+            .filter(image_usage::Column::ImageId.eq(image_id))
+            .find_also_related(PostMetadata)
+            .all(&conn)
+            .await
+        {
+            Ok(usages) => usages
+                .into_iter()
+                .filter_map(|(_, article)| article)
+                .map(|article| article.title)
+                .collect::<Vec<_>>(),
+            Err(db_err) => {
                 return Err(ServerFnError::new(format!(
-                    "A error occured when removing image from filesystem. FileErr: {}",
-                    file_err.to_string()
+                    "A error happened when requesting the image, try again later. DbErr: {}",
+                    db_err.to_string()
                 )))
             }
-        },
+        };
+
+        if !articles.is_empty() {
+            set_status(http::StatusCode::CONFLICT);
+            return Err(ServerFnError::new(format!(
+                "The image {} is used in {} article(s): {}. Confirm to delete it anyway.",
+                image_id,
+                articles.len(),
+                articles.join(", ")
+            )));
+        }
+    }
+
+    let deleted_image = ImageMetadataModel::from(image.clone());
+    let stored_hash = image.content_hash.clone();
+
+    if let Err(db_err) = image.delete(&conn).await {
+        return Err(ServerFnError::new(format!(
+            "A error happened when removing the image, try again later. DbErr: {}",
+            db_err.to_string()
+        )));
+    }
+
+    // The file is only removed with the last image pointing at it
+    let references = match ImageMetadata::find()
+        .filter(image_metadata::Column::ImagePath.eq(&deleted_image.image_path))
+        .count(&conn)
+        .await
+    {
+        Ok(references) => references,
         Err(db_err) => {
             return Err(ServerFnError::new(format!(
                 "A error happened when removing the image, try again later. DbErr: {}",
                 db_err.to_string()
             )))
         }
+    };
+
+    if references > 0 {
+        return Ok(Some(deleted_image));
+    }
+
+    // Images saved before their hash have it computed from the file, to find their transforms
+    let content_hash = match stored_hash {
+        Some(content_hash) => Some(content_hash),
+        None => media
+            .get(&deleted_image.image_path)
+            .await
+            .ok()
+            .flatten()
+            .map(|content| content_hash(&content)),
+    };
+
+    match remove_image(
+        media.as_ref(),
+        &deleted_image.image_path,
+        ImageType::from_mime_type(&deleted_image.mime_type),
+    )
+    .await
+    {
+        Ok(_) => {
+            if let Some(content_hash) = content_hash {
+                if let Err(file_err) = remove_cached(&content_hash).await {
+                    logging::log!(
+                        "Couldn't remove the transforms of image {}: {}",
+                        image_id,
+                        file_err
+                    );
+                }
+            }
+            Ok(Some(deleted_image))
+        }
+        Err(file_err) => Err(ServerFnError::new(format!(
+            "A error occured when removing image from filesystem. FileErr: {}",
+            file_err
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_ids_from_site_relative_urls() {
+        assert_eq!(image_id_from_url("/gallery/12"), Some(12));
+        assert_eq!(image_id_from_url("/gallery/12/medium"), Some(12));
+        assert_eq!(image_id_from_url("/gallery/12?v=2"), Some(12));
+        assert_eq!(image_id_from_url("https://example.com/gallery/12"), None);
+        assert_eq!(image_id_from_url("//example.com/gallery/12"), None);
+        assert_eq!(image_id_from_url("/blog/gallery/12"), None);
+        assert_eq!(image_id_from_url("/gallery/"), None);
+        assert_eq!(image_id_from_url("/gallery/+12"), None);
+        assert_eq!(image_id_from_url("/gallery/12abc"), None);
+    }
+
+    #[test]
+    fn finds_images_referenced_by_articles() {
+        let content = "{{< image id=3 >}}\n\
+            ![A cat](/gallery/1/medium)\n\
+            <img src=\"/gallery/2\"> and /gallery/4 too\n\
+            ![Elsewhere](https://example.com/gallery/5)\n\
+            [Not ours](//example.com/gallery/6) or /photos/gallery/7\n\
+            ```\n\
+            ![Code](/gallery/8) {{< image id=9 >}}\n\
+            ```\n\
+            `/gallery/2` again\n";
+
+        assert_eq!(
+            referenced_image_ids(content, Some("/gallery/10")),
+            vec![1, 2, 3, 4, 10]
+        );
+        assert_eq!(
+            referenced_image_ids("", Some("https://example.com/gallery/10")),
+            Vec::<i32>::new()
+        );
+    }
+
+    #[cfg(feature = "ssr")]
+    #[tokio::test]
+    async fn shared_files_stay_until_their_last_image_goes() {
+        use crate::gallery::media::{tests::MemoryStore, MediaStore};
+        use crate::testing::{app_state, database, FakeFetcher};
+
+        let mut png = vec![];
+        image::RgbImage::from_pixel(8, 8, image::Rgb([200, 80, 0]))
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+
+        let media = Arc::new(MemoryStore::default());
+        let state = app_state(
+            database().await,
+            Arc::new(FakeFetcher::default()),
+            media.clone(),
+        );
+        let quota = 1024 * 1024;
+
+        let first = store_image(&state, 1, quota, Some("a.png".to_string()), png.clone())
+            .await
+            .unwrap();
+        let second = store_image(&state, 2, quota, Some("b.png".to_string()), png.clone())
+            .await
+            .unwrap();
+        assert_ne!(first.id, second.id);
+        assert_eq!(first.image_path, second.image_path);
+        assert_eq!(second.uploader_id, Some(2));
+
+        // Each uploader is charged for it's own upload
+        let size = first.byte_size.unwrap() as u64;
+        assert_eq!(uploaded_bytes(&state, 1).await.unwrap(), size);
+        assert_eq!(uploaded_bytes(&state, 2).await.unwrap(), size);
+        assert!(store_image(&state, 2, size, None, png.clone())
+            .await
+            .is_err());
+
+        delete_image(&state, first.id, false).await.unwrap();
+        assert!(media.exists(&second.image_path).await.unwrap());
+
+        delete_image(&state, second.id, false).await.unwrap();
+        assert!(!media.exists(&second.image_path).await.unwrap());
     }
 }
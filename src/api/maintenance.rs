@@ -0,0 +1,106 @@
+use leptos::*;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[cfg(feature = "ssr")]
+use tokio::sync::Mutex;
+
+use super::users::{user_have_permission, user_logged_in};
+
+/// What the orphan collector does with the files it finds, see `crate::maintenance`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub enum OrphanAction {
+    /// Only report them.
+    #[default]
+    DryRun,
+    /// Move them to the quarantine directory, from where they can be put back.
+    Quarantine,
+    Delete,
+}
+
+impl OrphanAction {
+    pub const ALL: [OrphanAction; 3] = [
+        OrphanAction::DryRun,
+        OrphanAction::Quarantine,
+        OrphanAction::Delete,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            OrphanAction::DryRun => "dry-run",
+            OrphanAction::Quarantine => "quarantine",
+            OrphanAction::Delete => "delete",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<OrphanAction> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+/// A row of `image_metadata` or `post_metadata` whose file is gone.
+#[derive(Clone, Debug, PartialEq, Default, Deserialize, Serialize)]
+pub struct MissingFileModel {
+    pub id: i32,
+    pub path: String,
+    /// The original filename of an image, or the title of an article.
+    pub name: Option<String>,
+}
+
+/// What the orphan collector found, and did.
+#[derive(Clone, Debug, PartialEq, Default, Deserialize, Serialize)]
+pub struct OrphanReportModel {
+    pub action: OrphanAction,
+    /// Files under the uploads directory that no image has, variants included.
+    pub stray_uploads: Vec<String>,
+    /// Articles on the data directory that no `post_metadata` row has.
+    pub stray_articles: Vec<String>,
+    pub images_without_file: Vec<MissingFileModel>,
+    pub articles_without_file: Vec<MissingFileModel>,
+    /// Where the stray files were moved, when quarantined.
+    pub quarantine_dir: Option<String>,
+    /// The stray files that couldn't be moved or removed, with why.
+    pub failures: Vec<String>,
+}
+
+/// Compare the uploads and articles on disk with the database, and report, quarantine or delete
+/// the stray files.
+#[server(CollectOrphans, "/api/maintenance")]
+pub async fn collect_orphan_files(
+    action: OrphanAction,
+) -> Result<OrphanReportModel, ServerFnError> {
+    if let Some(user) = user_logged_in().await? {
+        if user_have_permission(user, "Moderar".to_string()).await? {
+            return collect_orphan_files_guard(action).await;
+        } else {
+            return Err(ServerFnError::new(
+                "User doesn't have the permission to execute this operation.",
+            ));
+        }
+    } else {
+        return Err(ServerFnError::new("User is not logged in."));
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub async fn collect_orphan_files_guard(
+    action: OrphanAction,
+) -> Result<OrphanReportModel, ServerFnError> {
+    use crate::maintenance::collect_orphans;
+    use crate::AppState;
+
+    let state: Arc<Mutex<AppState>> = use_context().unwrap();
+    // Scanning takes a while, so the lock isn't held for it
+    let (conn, media) = {
+        let state = state.as_ref().lock().await;
+        (state.conn.clone(), state.media.clone())
+    };
+
+    match collect_orphans(&conn, media.as_ref(), action).await {
+        Ok(report) => Ok(report),
+        Err(db_err) => Err(ServerFnError::new(format!(
+            "A error happened when looking for orphan files, try again later. DbErr: {}",
+            db_err.to_string()
+        ))),
+    }
+}
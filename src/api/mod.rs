@@ -7,3 +7,11 @@ pub mod notifications;
 pub mod posts;
 
 pub mod images;
+
+pub mod webmentions;
+
+pub mod newsletter;
+
+pub mod albums;
+
+pub mod maintenance;
@@ -0,0 +1,334 @@
+use chrono::prelude::*;
+use leptos::*;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[cfg(feature = "ssr")]
+use tokio::sync::Mutex;
+
+use super::users::{user_have_permission, user_logged_in};
+
+#[derive(Clone, Debug, PartialEq, Default, Deserialize, Serialize)]
+pub struct SubscriberModel {
+    pub id: i32,
+    pub email: String,
+    pub confirmed: bool,
+    pub created_at: DateTime<FixedOffset>,
+    pub confirmed_at: Option<DateTime<FixedOffset>>,
+    pub last_digest_at: Option<DateTime<FixedOffset>>,
+}
+
+#[cfg(feature = "ssr")]
+impl From<entities::subscriber::Model> for SubscriberModel {
+    fn from(value: entities::subscriber::Model) -> Self {
+        Self {
+            id: value.id,
+            email: value.email,
+            confirmed: value.confirmed,
+            created_at: value.created_at,
+            confirmed_at: value.confirmed_at,
+            last_digest_at: value.last_digest_at,
+        }
+    }
+}
+
+/// Subscribe `email` to the newsletter, sending it a confirmation mail. Subscribing an address
+/// already waiting for confirmation sends a new mail, unless one was sent moments ago, and
+/// confirmed addresses are left as they are, so this never tells who is subscribed.
+#[server(Subscribe, "/api/newsletter")]
+pub async fn subscribe(email: String) -> Result<(), ServerFnError> {
+    use crate::newsletter::{
+        generate_token, is_valid_email, send_confirmation, subscribe_step, SubscribeStep,
+    };
+    use crate::AppState;
+
+    use entities::prelude::Subscriber;
+    use entities::subscriber;
+    use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set};
+
+    let email = email.trim().to_lowercase();
+    if !is_valid_email(&email) {
+        return Err(ServerFnError::new("This e-mail address is not valid."));
+    }
+
+    let (conn, mailer, site_url) = {
+        let state: Arc<Mutex<AppState>> = use_context().unwrap();
+        let state = state.as_ref().lock().await;
+        (state.conn.clone(), state.mailer.clone(), state.site_url.clone())
+    };
+
+    let existing = match Subscriber::find()
+        .filter(subscriber::Column::Email.eq(&email))
+        .one(&conn)
+        .await
+    {
+        Ok(existing) => existing,
+        Err(db_err) => {
+            return Err(ServerFnError::new(format!(
+                "A error happened when requesting the subscriber, try again later. DbErr: {}",
+                db_err.to_string()
+            )))
+        }
+    };
+
+    let now = Utc::now().with_timezone(&FixedOffset::west_opt(3 * 3600).expect("Invalid Timezone"));
+    let result = match (subscribe_step(existing.as_ref(), now), existing) {
+        (SubscribeStep::Confirmed | SubscribeStep::Wait, _) => return Ok(()),
+        (SubscribeStep::Resend, Some(existing)) => {
+            let mut pending: subscriber::ActiveModel = existing.into();
+            pending.token = Set(generate_token());
+            pending.token_sent_at = Set(now);
+            pending.update(&conn).await
+        }
+        _ => {
+            subscriber::ActiveModel {
+                email: Set(email),
+                token: Set(generate_token()),
+                confirmed: Set(false),
+                created_at: Set(now),
+                token_sent_at: Set(now),
+                ..Default::default()
+            }
+            .insert(&conn)
+            .await
+        }
+    };
+
+    let pending = match result {
+        Ok(pending) => pending,
+        Err(db_err) => {
+            return Err(ServerFnError::new(format!(
+                "A error happened when saving the subscriber, try again later. DbErr: {}",
+                db_err.to_string()
+            )))
+        }
+    };
+
+    match send_confirmation(mailer.as_ref(), &site_url, &pending).await {
+        Ok(_) => Ok(()),
+        Err(mail_err) => Err(ServerFnError::new(format!(
+            "A error happened when sending the confirmation mail, try again later. MailErr: {}",
+            mail_err
+        ))),
+    }
+}
+
+/// Confirm the subscription with `token`, returning whether it exists and hasn't expired.
+#[server(ConfirmSubscription, "/api/newsletter")]
+pub async fn confirm_subscription(token: String) -> Result<bool, ServerFnError> {
+    use crate::newsletter::is_token_expired;
+    use crate::AppState;
+
+    use entities::prelude::Subscriber;
+    use entities::subscriber;
+    use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set};
+
+    let state: Arc<Mutex<AppState>> = use_context().unwrap();
+    let state = state.as_ref().lock().await;
+
+    let pending = match Subscriber::find()
+        .filter(subscriber::Column::Token.eq(token))
+        .one(&state.conn)
+        .await
+    {
+        Ok(Some(pending)) => pending,
+        Ok(None) => return Ok(false),
+        Err(db_err) => {
+            return Err(ServerFnError::new(format!(
+                "A error happened when requesting the subscriber, try again later. DbErr: {}",
+                db_err.to_string()
+            )))
+        }
+    };
+
+    if pending.confirmed {
+        return Ok(true);
+    }
+
+    let now = Utc::now().with_timezone(&FixedOffset::west_opt(3 * 3600).expect("Invalid Timezone"));
+    if is_token_expired(&pending, now) {
+        return Ok(false);
+    }
+
+    let mut confirmed: subscriber::ActiveModel = pending.into();
+    confirmed.confirmed = Set(true);
+    confirmed.confirmed_at = Set(Some(now));
+
+    match confirmed.update(&state.conn).await {
+        Ok(_) => Ok(true),
+        Err(db_err) => Err(ServerFnError::new(format!(
+            "A error happened when confirming the subscriber, try again later. DbErr: {}",
+            db_err.to_string()
+        ))),
+    }
+}
+
+/// Cancel the subscription with `token`, returning whether it existed.
+#[server(Unsubscribe, "/api/newsletter")]
+pub async fn unsubscribe(token: String) -> Result<bool, ServerFnError> {
+    use crate::AppState;
+
+    let state: Arc<Mutex<AppState>> = use_context().unwrap();
+    let state = state.as_ref().lock().await;
+
+    match crate::newsletter::unsubscribe(&state.conn, &token).await {
+        Ok(existed) => Ok(existed),
+        Err(db_err) => Err(ServerFnError::new(format!(
+            "A error happened when removing the subscriber, try again later. DbErr: {}",
+            db_err.to_string()
+        ))),
+    }
+}
+
+#[server(ReadSubscribers, "/api/newsletter")]
+pub async fn get_subscribers() -> Result<Vec<SubscriberModel>, ServerFnError> {
+    if let Some(user) = user_logged_in().await? {
+        if user_have_permission(user, "Moderar".to_string()).await? {
+            return get_subscribers_guard().await;
+        } else {
+            return Err(ServerFnError::new(
+                "User doesn't have the permission to execute this operation.",
+            ));
+        }
+    } else {
+        return Err(ServerFnError::new("User is not logged in."));
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub async fn get_subscribers_guard() -> Result<Vec<SubscriberModel>, ServerFnError> {
+    use crate::AppState;
+
+    use entities::prelude::Subscriber;
+    use entities::subscriber;
+    use sea_orm::{EntityTrait, QueryOrder};
+
+    let state: Arc<Mutex<AppState>> = use_context().unwrap();
+    let state = state.as_ref().lock().await;
+
+    match Subscriber::find()
+        .order_by_desc(subscriber::Column::CreatedAt)
+        .all(&state.conn)
+        .await
+    {
+        Ok(subscribers) => Ok(subscribers.into_iter().map(SubscriberModel::from).collect()),
+        Err(db_err) => {
+            return Err(ServerFnError::new(format!(
+                "A error happened when requesting the subscribers, try again later. DbErr: {}",
+                db_err.to_string()
+            )))
+        }
+    }
+}
+
+/// Every subscriber as CSV, with a header line.
+#[server(ExportSubscribers, "/api/newsletter")]
+pub async fn export_subscribers() -> Result<String, ServerFnError> {
+    let subscribers = get_subscribers().await?;
+
+    let mut csv = String::from("email,confirmed,created_at,confirmed_at,last_digest_at\n");
+    for subscriber in subscribers {
+        csv.push_str(&format!(
+            "{},{},{},{},{}\n",
+            csv_field(&subscriber.email),
+            subscriber.confirmed,
+            subscriber.created_at.to_rfc3339(),
+            subscriber
+                .confirmed_at
+                .map(|date| date.to_rfc3339())
+                .unwrap_or_default(),
+            subscriber
+                .last_digest_at
+                .map(|date| date.to_rfc3339())
+                .unwrap_or_default(),
+        ));
+    }
+
+    Ok(csv)
+}
+
+/// Quote a CSV field when needed, and keep spreadsheets from running it as a formula.
+#[cfg(feature = "ssr")]
+fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+#[server(DeleteSubscriber, "/api/newsletter")]
+pub async fn delete_subscriber(subscriber_id: i32) -> Result<(), ServerFnError> {
+    if let Some(user) = user_logged_in().await? {
+        if user_have_permission(user, "Moderar".to_string()).await? {
+            return delete_subscriber_guard(subscriber_id).await;
+        } else {
+            return Err(ServerFnError::new(
+                "User doesn't have the permission to execute this operation.",
+            ));
+        }
+    } else {
+        return Err(ServerFnError::new("User is not logged in."));
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub async fn delete_subscriber_guard(subscriber_id: i32) -> Result<(), ServerFnError> {
+    use crate::AppState;
+
+    use entities::prelude::Subscriber;
+    use sea_orm::EntityTrait;
+
+    let state: Arc<Mutex<AppState>> = use_context().unwrap();
+    let state = state.as_ref().lock().await;
+
+    match Subscriber::delete_by_id(subscriber_id).exec(&state.conn).await {
+        Ok(_) => Ok(()),
+        Err(db_err) => Err(ServerFnError::new(format!(
+            "A error happened when removing the subscriber, try again later. DbErr: {}",
+            db_err.to_string()
+        ))),
+    }
+}
+
+/// Send the digest now instead of waiting for the next one, returning how many were sent.
+#[server(SendDigest, "/api/newsletter")]
+pub async fn send_digest() -> Result<usize, ServerFnError> {
+    if let Some(user) = user_logged_in().await? {
+        if user_have_permission(user, "Moderar".to_string()).await? {
+            return send_digest_guard().await;
+        } else {
+            return Err(ServerFnError::new(
+                "User doesn't have the permission to execute this operation.",
+            ));
+        }
+    } else {
+        return Err(ServerFnError::new("User is not logged in."));
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub async fn send_digest_guard() -> Result<usize, ServerFnError> {
+    use crate::newsletter::send_digests;
+    use crate::AppState;
+
+    let (conn, mailer, site_url) = {
+        let state: Arc<Mutex<AppState>> = use_context().unwrap();
+        let state = state.as_ref().lock().await;
+        (state.conn.clone(), state.mailer.clone(), state.site_url.clone())
+    };
+
+    match send_digests(&conn, mailer.as_ref(), &site_url).await {
+        Ok(sent) => Ok(sent),
+        Err(db_err) => Err(ServerFnError::new(format!(
+            "A error happened when sending the digest, try again later. DbErr: {}",
+            db_err.to_string()
+        ))),
+    }
+}
@@ -6,7 +6,15 @@ use std::sync::Arc;
 #[cfg(feature = "ssr")]
 use tokio::{fs, io::AsyncWriteExt, sync::Mutex};
 
-use super::users::{get_user, user_have_permission, user_logged_in, UserModel};
+use super::users::{get_user, user_have_permission, user_logged_in, PublicProfileModel};
+#[cfg(feature = "ssr")]
+use crate::activitypub::deliver_article;
+#[cfg(feature = "ssr")]
+use crate::api::images::{image_alt_texts, image_previews, referenced_image_ids, sync_image_usage};
+#[cfg(feature = "ssr")]
+use crate::markdown::{render_article_with_images, shortcodes::image_ids};
+#[cfg(feature = "ssr")]
+use crate::webmention::{article_url, changed_links, external_links, send_webmentions};
 
 #[derive(Clone, Debug, PartialEq, Default, Deserialize, Serialize)]
 pub struct PostMetadataModel {
@@ -18,6 +26,9 @@ pub struct PostMetadataModel {
     pub created_at: DateTime<FixedOffset>,
     pub updated_at: DateTime<FixedOffset>,
     pub content_path: String,
+    pub pinned: bool,
+    pub pin_order: i32,
+    pub cover_image: Option<String>,
 }
 
 #[cfg(feature = "ssr")]
@@ -32,6 +43,9 @@ impl From<entities::post_metadata::Model> for PostMetadataModel {
             created_at: value.created_at,
             updated_at: value.updated_at,
             content_path: value.content_path,
+            pinned: value.pinned,
+            pin_order: value.pin_order,
+            cover_image: value.cover_image,
         }
     }
 }
@@ -42,23 +56,93 @@ pub struct PostModel {
     pub title: String,
     pub slug: String,
     pub short_desc: Option<String>,
-    pub writer: UserModel,
+    pub cover_image: Option<String>,
+    pub writer: PublicProfileModel,
     pub created_at: DateTime<FixedOffset>,
     pub updated_at: DateTime<FixedOffset>,
     pub content: String,
+    /// The HTML rendered from `content` by the server. It's ignored when creating or updating.
+    pub rendered_content: String,
+}
+
+/// A group of articles published on the same month, used by the archive pages.
+#[derive(Clone, Debug, PartialEq, Default, Deserialize, Serialize)]
+pub struct ArchiveMonthModel {
+    pub year: i32,
+    pub month: u32,
+    pub articles: Vec<PostMetadataModel>,
 }
 
 // Post Create/Read/Update/Delete
 
+/// Images need alt text on the gallery before they can be used in an article, or as it's cover,
+/// however they are referenced, see `referenced_image_ids`.
+#[cfg(feature = "ssr")]
+async fn require_alt_texts(
+    conn: &sea_orm::DatabaseConnection,
+    content: &str,
+    cover_image: Option<&str>,
+) -> Result<(), ServerFnError> {
+    let ids = referenced_image_ids(content, cover_image);
+    let alt_texts = match image_alt_texts(conn, &ids).await {
+        Ok(alt_texts) => alt_texts,
+        Err(db_err) => {
+            return Err(ServerFnError::new(format!(
+                "A error happened when requesting the images, try again later. DbErr: {}",
+                db_err.to_string()
+            )))
+        }
+    };
+
+    let missing: Vec<String> = ids
+        .into_iter()
+        .filter(|id| !alt_texts.contains_key(id))
+        .map(|id| id.to_string())
+        .collect();
+
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(ServerFnError::new(format!(
+            "Add alt text to the images {} on the gallery before using them in an article.",
+            missing.join(", ")
+        )))
+    }
+}
+
 #[server(CreateArticle, "/api/articles")]
 pub async fn create_article(new_post: PostModel) -> Result<PostMetadataModel, ServerFnError> {
+    if let Some(user) = user_logged_in().await? {
+        if user_have_permission(user, "Escrever".to_string()).await? {
+            return create_article_guard(new_post).await;
+        } else {
+            return Err(ServerFnError::new(
+                "User doesn't have the permission to execute this operation.",
+            ));
+        }
+    } else {
+        return Err(ServerFnError::new("User is not logged in."));
+    }
+}
+
+#[cfg(feature = "ssr")]
+pub async fn create_article_guard(
+    new_post: PostModel,
+) -> Result<PostMetadataModel, ServerFnError> {
     use crate::AppState;
 
     use entities::post_metadata;
-    use sea_orm::{ActiveModelTrait, Set};
+    use sea_orm::{ActiveModelTrait, Set, TransactionTrait};
 
     let state: Arc<Mutex<AppState>> = use_context().unwrap();
 
+    require_alt_texts(
+        &state.as_ref().lock().await.conn,
+        &new_post.content,
+        new_post.cover_image.as_deref(),
+    )
+    .await?;
+
     let data_path = format!("data/{}.md", new_post.slug);
     let mut data_file = fs::OpenOptions::new()
         .create(true)
@@ -73,6 +157,7 @@ pub async fn create_article(new_post: PostModel) -> Result<PostMetadataModel, Se
                 title: Set(new_post.title),
                 slug: Set(new_post.slug),
                 short_desc: Set(new_post.short_desc),
+                cover_image: Set(new_post.cover_image),
                 writer_id: Set(new_post.writer.id),
                 created_at: Set(Utc::now()
                     .with_timezone(&FixedOffset::west_opt(3 * 3600).expect("Invalid Timezone"))),
@@ -83,15 +168,57 @@ pub async fn create_article(new_post: PostModel) -> Result<PostMetadataModel, Se
             };
 
             let state = state.as_ref().lock().await;
-            match article.insert(&state.conn).await {
-                Ok(post_meta) => Ok(PostMetadataModel::from(post_meta)),
+            // The article and the images it uses are saved together
+            let transaction = match state.conn.begin().await {
+                Ok(transaction) => transaction,
+                Err(db_err) => {
+                    return Err(ServerFnError::new(format!(
+                        "A error happened while starting a new transaction over database. DbErr: {}",
+                        db_err.to_string()
+                    )))
+                }
+            };
+            let post_meta = match article.insert(&transaction).await {
+                Ok(post_meta) => post_meta,
                 Err(db_err) => {
                     return Err(ServerFnError::new(format!(
                         "A error occured when inserting a new file to database. DbErr: {}",
                         db_err.to_string()
                     )))
                 }
+            };
+            if let Err(db_err) = sync_image_usage(
+                &transaction,
+                post_meta.id,
+                &new_post.content,
+                post_meta.cover_image.as_deref(),
+            )
+            .await
+            {
+                return Err(ServerFnError::new(format!(
+                    "A error occured when saving the images used by the article. DbErr: {}",
+                    db_err.to_string()
+                )));
             }
+            if let Err(db_err) = transaction.commit().await {
+                return Err(ServerFnError::new(format!(
+                    "A error occured when inserting a new file to database. DbErr: {}",
+                    db_err.to_string()
+                )));
+            }
+
+            tokio::spawn(send_webmentions(
+                state.fetcher.clone(),
+                article_url(&state.site_url, &post_meta.slug),
+                external_links(&new_post.content),
+            ));
+            tokio::spawn(deliver_article(
+                state.conn.clone(),
+                state.fetcher.clone(),
+                state.site_url.clone(),
+                post_meta.clone(),
+            ));
+            Ok(PostMetadataModel::from(post_meta))
         }
 
         Err(file_err) => {
@@ -126,6 +253,145 @@ pub async fn get_all_articles() -> Result<Vec<PostMetadataModel>, ServerFnError>
     Ok(articles)
 }
 
+/// Read the pinned articles in the order they were arranged on the Office.
+#[server(ReadFeaturedArticles, "/api/articles")]
+pub async fn get_featured_articles() -> Result<Vec<PostMetadataModel>, ServerFnError> {
+    use crate::AppState;
+
+    use entities::post_metadata;
+    use entities::prelude::PostMetadata;
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+
+    let state: Arc<Mutex<AppState>> = use_context().unwrap();
+    let state = state.as_ref().lock().await;
+
+    match PostMetadata::find()
+        .filter(post_metadata::Column::Pinned.eq(true))
+        .order_by_asc(post_metadata::Column::PinOrder)
+        .order_by_desc(post_metadata::Column::UpdatedAt)
+        .all(&state.conn)
+        .await
+    {
+        Ok(articles) => Ok(articles
+            .into_iter()
+            .map(PostMetadataModel::from)
+            .collect()),
+        Err(db_err) => Err(ServerFnError::new(format!(
+            "A error happened when requesting the featured articles, try again later. DbErr: {}",
+            db_err.to_string()
+        ))),
+    }
+}
+
+/// Read the articles written by the user, most recently updated first.
+#[server(ReadWriterArticles, "/api/articles")]
+pub async fn get_articles_by_writer(
+    writer_id: i32,
+) -> Result<Vec<PostMetadataModel>, ServerFnError> {
+    use crate::AppState;
+
+    use entities::post_metadata;
+    use entities::prelude::PostMetadata;
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+
+    let state: Arc<Mutex<AppState>> = use_context().unwrap();
+    let state = state.as_ref().lock().await;
+
+    match PostMetadata::find()
+        .filter(post_metadata::Column::WriterId.eq(writer_id))
+        .order_by_desc(post_metadata::Column::UpdatedAt)
+        .all(&state.conn)
+        .await
+    {
+        Ok(articles) => Ok(articles
+            .into_iter()
+            .map(PostMetadataModel::from)
+            .collect()),
+        Err(db_err) => Err(ServerFnError::new(format!(
+            "A error happened when requesting the writer's articles, try again later. DbErr: {}",
+            db_err.to_string()
+        ))),
+    }
+}
+
+/// Read the articles grouped by the year and month they were published, newest first.
+/// `year` and `month` narrow the archive down to a single year or month.
+#[server(ReadArchive, "/api/articles")]
+pub async fn get_archive(
+    year: Option<i32>,
+    month: Option<u32>,
+) -> Result<Vec<ArchiveMonthModel>, ServerFnError> {
+    use crate::AppState;
+
+    use entities::post_metadata;
+    use entities::prelude::PostMetadata;
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+
+    // Articles are grouped by the site's timezone, the same they are shown in
+    let timezone = FixedOffset::west_opt(3 * 3600).expect("Invalid Timezone");
+
+    // The first instant of the period and of the one after it, no year means every article
+    let first_day = |year: i32, month: u32| {
+        timezone
+            .with_ymd_and_hms(year, month, 1, 0, 0, 0)
+            .single()
+    };
+    let period = match (year, month) {
+        (None, _) => None,
+        (Some(year), None) => first_day(year, 1).zip(first_day(year + 1, 1)),
+        (Some(year), Some(12)) => first_day(year, 12).zip(first_day(year + 1, 1)),
+        (Some(year), Some(month)) => first_day(year, month).zip(first_day(year, month + 1)),
+    };
+    if year.is_some() && period.is_none() {
+        return Ok(vec![]);
+    }
+
+    let state: Arc<Mutex<AppState>> = use_context().unwrap();
+    let state = state.as_ref().lock().await;
+
+    let mut query = PostMetadata::find().order_by_desc(post_metadata::Column::CreatedAt);
+    if let Some((start, end)) = period {
+        query = query
+            .filter(post_metadata::Column::CreatedAt.gte(start))
+            .filter(post_metadata::Column::CreatedAt.lt(end));
+    }
+
+    let articles = match query
+        .all(&state.conn)
+        .await
+    {
+        Ok(articles) => articles,
+        Err(db_err) => {
+            return Err(ServerFnError::new(format!(
+                "A error happened when requesting the archive, try again later. DbErr: {}",
+                db_err.to_string()
+            )))
+        }
+    };
+
+    let mut archive: Vec<ArchiveMonthModel> = vec![];
+
+    for article in articles {
+        let mut article = PostMetadataModel::from(article);
+        article.created_at = article.created_at.with_timezone(&timezone);
+        let article_year = article.created_at.year();
+        let article_month = article.created_at.month();
+
+        match archive.last_mut() {
+            Some(group) if group.year == article_year && group.month == article_month => {
+                group.articles.push(article)
+            }
+            _ => archive.push(ArchiveMonthModel {
+                year: article_year,
+                month: article_month,
+                articles: vec![article],
+            }),
+        }
+    }
+
+    Ok(archive)
+}
+
 /// Read the Role specified by it ID together with it's permissions.
 #[server(ReadArticle, "/api/articles")]
 pub async fn get_article(slug: String) -> Result<Option<PostModel>, ServerFnError> {
@@ -155,34 +421,87 @@ pub async fn get_article(slug: String) -> Result<Option<PostModel>, ServerFnErro
     std::mem::drop(state);
 
     let writer = get_user(article_metadata.writer_id).await?;
+    let content = fs::read_to_string(&article_metadata.content_path)
+        .await
+        .unwrap();
+
+    let state: Arc<Mutex<AppState>> = use_context().unwrap();
+    let conn = state.as_ref().lock().await.conn.clone();
+    let ids = image_ids(&content);
+    let alt_texts = image_alt_texts(&conn, &ids).await.unwrap_or_default();
+    let previews = image_previews(&conn, &ids).await.unwrap_or_default();
+
     Ok(Some(PostModel {
         title: article_metadata.title,
         slug: article_metadata.slug,
         short_desc: article_metadata.short_desc,
-        writer: writer.unwrap(),
+        cover_image: article_metadata.cover_image,
+        writer: PublicProfileModel::from(writer.unwrap()),
         created_at: article_metadata.created_at,
         updated_at: article_metadata.updated_at,
-        content: fs::read_to_string(&article_metadata.content_path)
-            .await
-            .unwrap(),
+        rendered_content: render_article_with_images(&content, false, &alt_texts, &previews),
+        content,
         id: article_metadata.id,
     }))
 }
 
-/// Update a Role based on it's model.
+/// Render an article being written, showing the writer warnings hidden from readers.
+#[server(PreviewArticle, "/api/articles")]
+pub async fn preview_article(content: String) -> Result<String, ServerFnError> {
+    use crate::AppState;
+
+    if let Some(_) = user_logged_in().await? {
+        let state: Arc<Mutex<AppState>> = use_context().unwrap();
+        let conn = state.as_ref().lock().await.conn.clone();
+        let ids = image_ids(&content);
+        let alt_texts = image_alt_texts(&conn, &ids).await.unwrap_or_default();
+        let previews = image_previews(&conn, &ids).await.unwrap_or_default();
+        Ok(render_article_with_images(
+            &content, true, &alt_texts, &previews,
+        ))
+    } else {
+        Err(ServerFnError::new("User is not logged in."))
+    }
+}
+
 #[server(UpdateArticle, "/api/articles")]
 pub async fn update_article(
     updated_article: PostModel,
+) -> Result<PostMetadataModel, ServerFnError> {
+    if let Some(user) = user_logged_in().await? {
+        if user_have_permission(user, "Escrever".to_string()).await? {
+            return update_article_guard(updated_article).await;
+        } else {
+            return Err(ServerFnError::new(
+                "User doesn't have the permission to execute this operation.",
+            ));
+        }
+    } else {
+        return Err(ServerFnError::new("User is not logged in."));
+    }
+}
+
+/// Update a Role based on it's model.
+#[cfg(feature = "ssr")]
+pub async fn update_article_guard(
+    updated_article: PostModel,
 ) -> Result<PostMetadataModel, ServerFnError> {
     use crate::AppState;
 
     use entities::post_metadata;
     use entities::prelude::PostMetadata;
-    use sea_orm::{ActiveModelTrait, EntityTrait, Set};
+    use sea_orm::{ActiveModelTrait, EntityTrait, Set, TransactionTrait};
 
     let state: Arc<Mutex<AppState>> = use_context().unwrap();
     let state = state.as_ref().lock().await;
 
+    require_alt_texts(
+        &state.conn,
+        &updated_article.content,
+        updated_article.cover_image.as_deref(),
+    )
+    .await?;
+
     let article_metadata = match PostMetadata::find_by_id(updated_article.id).one(&state.conn).await {
         Ok(article) => article.expect("Unexpected 'None' value when updating article"),
         Err(db_err) => return Err(
@@ -192,6 +511,11 @@ pub async fn update_article(
         )
     };
 
+    let old_slug = article_metadata.slug.clone();
+    let old_content = fs::read_to_string(&article_metadata.content_path)
+        .await
+        .unwrap_or_default();
+
     let mut article_data = fs::OpenOptions::new()
         .write(true)
         .open(&article_metadata.content_path)
@@ -207,19 +531,62 @@ pub async fn update_article(
 
     article.title = Set(updated_article.title);
     article.short_desc = Set(updated_article.short_desc);
+    article.cover_image = Set(updated_article.cover_image);
     article.slug = Set(updated_article.slug);
     article.updated_at =
         Set(Utc::now().with_timezone(&FixedOffset::west_opt(3 * 3600).expect("Invalid Timezone")));
 
-    match article.update(&state.conn).await {
-        Ok(post_meta) => Ok(PostMetadataModel::from(post_meta)),
+    // The article and the images it uses are saved together
+    let transaction = match state.conn.begin().await {
+        Ok(transaction) => transaction,
+        Err(db_err) => {
+            return Err(ServerFnError::new(format!(
+                "A error happened while starting a new transaction over database. DbErr: {}",
+                db_err.to_string()
+            )))
+        }
+    };
+    let post_meta = match article.update(&transaction).await {
+        Ok(post_meta) => post_meta,
         Err(db_err) => {
             return Err(ServerFnError::new(format!(
                 "A error occured when inserting a new file to database. DbErr: {}",
                 db_err.to_string()
             )))
         }
+    };
+    if let Err(db_err) = sync_image_usage(
+        &transaction,
+        post_meta.id,
+        &updated_article.content,
+        post_meta.cover_image.as_deref(),
+    )
+    .await
+    {
+        return Err(ServerFnError::new(format!(
+            "A error occured when saving the images used by the article. DbErr: {}",
+            db_err.to_string()
+        )));
     }
+    if let Err(db_err) = transaction.commit().await {
+        return Err(ServerFnError::new(format!(
+            "A error occured when inserting a new file to database. DbErr: {}",
+            db_err.to_string()
+        )));
+    }
+
+    // Under a new slug every link is new to the sites it points to
+    let targets = if post_meta.slug == old_slug {
+        changed_links(&old_content, &updated_article.content)
+    } else {
+        external_links(&updated_article.content)
+    };
+    tokio::spawn(send_webmentions(
+        state.fetcher.clone(),
+        article_url(&state.site_url, &post_meta.slug),
+        targets,
+    ));
+    Ok(PostMetadataModel::from(post_meta))
 }
 
 #[server(DeleteArticle, "/api/articles")]
@@ -276,3 +643,183 @@ pub async fn delete_article_guard(
         }
     }
 }
+
+#[server(PinArticle, "/api/articles")]
+pub async fn pin_article(article_id: i32, pinned: bool) -> Result<(), ServerFnError> {
+    if let Some(user) = user_logged_in().await? {
+        if user_have_permission(user, "Escrever".to_string()).await? {
+            return pin_article_guard(article_id, pinned).await;
+        } else {
+            return Err(ServerFnError::new(
+                "User doesn't have the permission to execute this operation.",
+            ));
+        }
+    } else {
+        return Err(ServerFnError::new("User is not logged in."));
+    }
+}
+
+/// Pin or unpin an article. Newly pinned articles go to the end of the featured section.
+#[cfg(feature = "ssr")]
+pub async fn pin_article_guard(article_id: i32, pinned: bool) -> Result<(), ServerFnError> {
+    use crate::AppState;
+
+    use entities::post_metadata;
+    use entities::prelude::PostMetadata;
+    use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, QuerySelect, Set};
+
+    let state: Arc<Mutex<AppState>> = use_context().unwrap();
+    let state = state.as_ref().lock().await;
+
+    let article = match PostMetadata::find_by_id(article_id).one(&state.conn).await {
+        Ok(Some(article)) => article,
+        Ok(None) => return Err(ServerFnError::new("This article doesn't exists.")),
+        Err(db_err) => {
+            return Err(ServerFnError::new(format!(
+                "A error happened when requesting the article, try again later. DbErr: {}",
+                db_err.to_string()
+            )))
+        }
+    };
+
+    if article.pinned == pinned {
+        return Ok(());
+    }
+
+    // Newly pinned articles go last, after the highest order, as unpinning leaves gaps
+    let last_order: Option<i32> = match PostMetadata::find()
+        .select_only()
+        .column_as(post_metadata::Column::PinOrder.max(), "last_order")
+        .filter(post_metadata::Column::Pinned.eq(true))
+        .into_tuple()
+        .one(&state.conn)
+        .await
+    {
+        Ok(last_order) => last_order.flatten(),
+        Err(db_err) => {
+            return Err(ServerFnError::new(format!(
+                "A error happened when requesting the pinned articles, try again later. DbErr: {}",
+                db_err.to_string()
+            )))
+        }
+    };
+
+    let mut article: post_metadata::ActiveModel = article.into();
+    article.pinned = Set(pinned);
+    article.pin_order = Set(if pinned {
+        last_order.map_or(0, |order| order + 1)
+    } else {
+        0
+    });
+
+    match article.update(&state.conn).await {
+        Ok(_) => Ok(()),
+        Err(db_err) => Err(ServerFnError::new(format!(
+            "A error happened when pinning the article, try again later. DbErr: {}",
+            db_err.to_string()
+        ))),
+    }
+}
+
+#[server(ReorderPinnedArticles, "/api/articles")]
+pub async fn reorder_pinned_articles(article_ids: Vec<i32>) -> Result<(), ServerFnError> {
+    if let Some(user) = user_logged_in().await? {
+        if user_have_permission(user, "Escrever".to_string()).await? {
+            return reorder_pinned_articles_guard(article_ids).await;
+        } else {
+            return Err(ServerFnError::new(
+                "User doesn't have the permission to execute this operation.",
+            ));
+        }
+    } else {
+        return Err(ServerFnError::new("User is not logged in."));
+    }
+}
+
+/// Arrange the pinned articles in the order of `article_ids`.
+#[cfg(feature = "ssr")]
+pub async fn reorder_pinned_articles_guard(article_ids: Vec<i32>) -> Result<(), ServerFnError> {
+    use crate::AppState;
+
+    use entities::post_metadata;
+    use entities::prelude::PostMetadata;
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, TransactionTrait};
+    use sea_orm::sea_query::Expr;
+
+    let state: Arc<Mutex<AppState>> = use_context().unwrap();
+    let state = state.as_ref().lock().await;
+
+    let transaction = match state.conn.begin().await {
+        Ok(transaction) => transaction,
+        Err(db_err) => {
+            return Err(ServerFnError::new(format!(
+                "A error happened while starting a new transaction over database. DbErr: {}",
+                db_err.to_string()
+            )))
+        }
+    };
+
+    for (order, article_id) in article_ids.into_iter().enumerate() {
+        if let Err(db_err) = PostMetadata::update_many()
+            .col_expr(post_metadata::Column::PinOrder, Expr::value(order as i32))
+            .filter(post_metadata::Column::Id.eq(article_id))
+            .filter(post_metadata::Column::Pinned.eq(true))
+            .exec(&transaction)
+            .await
+        {
+            return Err(ServerFnError::new(format!(
+                "A error happened when reordering the pinned articles, try again later. DbErr: {}",
+                db_err.to_string()
+            )));
+        }
+    }
+
+    match transaction.commit().await {
+        Ok(_) => Ok(()),
+        Err(db_err) => Err(ServerFnError::new(format!(
+            "A error happened when reordering the pinned articles, try again later. DbErr: {}",
+            db_err.to_string()
+        ))),
+    }
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+    use crate::testing::database;
+    use sea_orm::{ActiveModelTrait, Set};
+
+    #[tokio::test]
+    async fn every_referenced_image_needs_alt_text() {
+        let conn = database().await;
+        entities::image_alt_text::ActiveModel {
+            image_id: Set(1),
+            locale: Set("pt-BR".to_string()),
+            alt_text: Set("Um gato".to_string()),
+            ..Default::default()
+        }
+        .insert(&conn)
+        .await
+        .unwrap();
+
+        assert!(require_alt_texts(&conn, "{{< image id=1 >}}", Some("/gallery/1/large"))
+            .await
+            .is_ok());
+
+        // Images linked by URL count as much as the shortcode ones
+        let err = require_alt_texts(
+            &conn,
+            "{{< image id=1 >}} ![Cat](/gallery/2/medium)",
+            Some("/gallery/3"),
+        )
+        .await
+        .unwrap_err()
+        .to_string();
+        assert!(err.contains("images 2, 3 on"), "{}", err);
+
+        // Code isn't rendered, so it needs nothing
+        assert!(require_alt_texts(&conn, "```\n![Cat](/gallery/2)\n```\n", None)
+            .await
+            .is_ok());
+    }
+}
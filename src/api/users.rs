@@ -23,6 +23,9 @@ pub struct UserModel {
     pub password: String,
     pub created_at: DateTime<FixedOffset>,
     pub updated_at: DateTime<FixedOffset>,
+    pub display_name: Option<String>,
+    pub avatar: Option<String>,
+    pub bio: Option<String>,
 }
 
 #[cfg(feature = "ssr")]
@@ -35,6 +38,77 @@ impl From<entities::user::Model> for UserModel {
             password: value.password,
             created_at: value.created_at,
             updated_at: value.updated_at,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl From<(entities::user::Model, Option<entities::user_profile::Model>)> for UserModel {
+    fn from(
+        (user, profile): (entities::user::Model, Option<entities::user_profile::Model>),
+    ) -> Self {
+        let mut user = UserModel::from(user);
+        if let Some(profile) = profile {
+            user.display_name = profile.display_name;
+            user.avatar = profile.avatar;
+            user.bio = profile.bio;
+        }
+        user
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Default, Deserialize, Serialize)]
+pub struct SocialLinkModel {
+    pub id: i32,
+    pub label: String,
+    pub url: String,
+}
+
+#[cfg(feature = "ssr")]
+impl From<entities::social_link::Model> for SocialLinkModel {
+    fn from(value: entities::social_link::Model) -> Self {
+        Self {
+            id: value.id,
+            label: value.label,
+            url: value.url,
+        }
+    }
+}
+
+/// The public side of a user, shown to readers on author pages and bylines. It's built from
+/// `UserModel` but never carries the email or the password hash.
+#[derive(Clone, Debug, PartialEq, Default, Deserialize, Serialize)]
+pub struct PublicProfileModel {
+    pub id: i32,
+    pub username: String,
+    pub display_name: Option<String>,
+    pub avatar: Option<String>,
+    pub bio: Option<String>,
+    pub social_links: Vec<SocialLinkModel>,
+    pub created_at: DateTime<FixedOffset>,
+}
+
+impl PublicProfileModel {
+    /// The name shown to readers, falling back to the username.
+    pub fn name(&self) -> String {
+        self.display_name
+            .clone()
+            .filter(|name| !name.is_empty())
+            .unwrap_or(self.username.clone())
+    }
+}
+
+impl From<UserModel> for PublicProfileModel {
+    fn from(value: UserModel) -> Self {
+        Self {
+            id: value.id,
+            username: value.username,
+            display_name: value.display_name,
+            avatar: value.avatar,
+            bio: value.bio,
+            social_links: vec![],
+            created_at: value.created_at,
         }
     }
 }
@@ -115,18 +189,19 @@ pub async fn create_user_guard(new_user: UserModel) -> Result<UserModel, ServerF
 pub async fn get_all_users() -> Result<Vec<UserModel>, ServerFnError> {
     use crate::AppState;
 
-    use entities::prelude::User;
+    use entities::prelude::{User, UserProfile};
     use sea_orm::EntityTrait;
 
     let state: Arc<Mutex<AppState>> = use_context().unwrap();
     let state = state.as_ref().lock().await;
 
     let mut users: Vec<UserModel> = User::find()
+        .find_also_related(UserProfile)
         .all(&state.conn)
         .await
         .unwrap_or_default()
-        .iter()
-        .map(|model| UserModel::from(model.clone()))
+        .into_iter()
+        .map(UserModel::from)
         .collect();
 
     for user in users.iter_mut() {
@@ -155,7 +230,7 @@ pub async fn get_user(user_id: i32) -> Result<Option<UserModel>, ServerFnError>
 pub async fn get_user_guard(user_id: i32) -> Result<Option<UserModel>, ServerFnError> {
     use crate::AppState;
     println!("Reading user...");
-    use entities::prelude::User;
+    use entities::prelude::{User, UserProfile};
     use entities::user;
     use sea_orm::EntityTrait;
 
@@ -166,6 +241,7 @@ pub async fn get_user_guard(user_id: i32) -> Result<Option<UserModel>, ServerFnE
     let user =
         match User::find()
             .filter(user::Column::Id.eq(user_id))
+            .find_also_related(UserProfile)
             .one(&state.conn)
             .await
         {
@@ -205,8 +281,8 @@ pub async fn update_user(updated_user: UserModel) -> Result<UserModel, ServerFnE
 pub async fn update_user_guard(updated_user: UserModel) -> Result<UserModel, ServerFnError> {
     use crate::AppState;
 
-    use entities::prelude::User;
-    use entities::user;
+    use entities::prelude::{User, UserProfile};
+    use entities::{user, user_profile};
 
     use argon2::{
         password_hash::{
@@ -277,6 +353,51 @@ pub async fn update_user_guard(updated_user: UserModel) -> Result<UserModel, Ser
     let state: Arc<Mutex<AppState>> = use_context().unwrap();
     let state = state.as_ref().lock().await;
 
+    // Only the profile fields that were sent are changed, an empty one clears it
+    let profile_fields = [
+        updated_user.display_name.clone(),
+        updated_user.avatar.clone(),
+        updated_user.bio.clone(),
+    ];
+    if profile_fields.iter().any(Option::is_some) {
+        let mut profile = user_profile::ActiveModel {
+            user_id: Set(user.id),
+            display_name: Set(user.display_name.clone()),
+            avatar: Set(user.avatar.clone()),
+            bio: Set(user.bio.clone()),
+        };
+        let [display_name, avatar, bio] = profile_fields;
+        let cleared = |field: String| Some(field).filter(|field| !field.trim().is_empty());
+        if let Some(display_name) = display_name {
+            profile.display_name = Set(cleared(display_name));
+        }
+        if let Some(avatar) = avatar {
+            profile.avatar = Set(cleared(avatar));
+        }
+        if let Some(bio) = bio {
+            profile.bio = Set(cleared(bio));
+        }
+
+        if let Err(db_err) = UserProfile::insert(profile)
+            .on_conflict(
+                sea_query::OnConflict::column(user_profile::Column::UserId)
+                    .update_columns([
+                        user_profile::Column::DisplayName,
+                        user_profile::Column::Avatar,
+                        user_profile::Column::Bio,
+                    ])
+                    .to_owned(),
+            )
+            .exec(&state.conn)
+            .await
+        {
+            return Err(ServerFnError::new(format!(
+                "A error happened when saving the user's profile, try again later. DbErr: {}",
+                db_err.to_string()
+            )));
+        }
+    }
+
     if !updated_user.password.is_empty() {
         let secret = state.secret_key.clone().into_bytes();
 
@@ -303,7 +424,13 @@ pub async fn update_user_guard(updated_user: UserModel) -> Result<UserModel, Ser
         Set(Utc::now().with_timezone(&FixedOffset::west_opt(3 * 3600).expect("Invalid Timezone")));
 
     match user_model.update(&state.conn).await {
-        Ok(model) => Ok(UserModel::from(model)),
+        Ok(model) => {
+            let profile = UserProfile::find_by_id(model.id)
+                .one(&state.conn)
+                .await
+                .unwrap_or_default();
+            Ok(UserModel::from((model, profile)))
+        }
         Err(db_err) => {
             return Err(ServerFnError::new(format!(
                 "A error happened while trying to make this request, try again later. DbErr: {}",
@@ -524,3 +651,204 @@ pub async fn user_have_permission(
 
     Ok(false)
 }
+
+// Public profiles
+
+/// Read the public profile of a user by it's username, together with it's social links.
+#[server(ReadAuthor, "/api/authors")]
+pub async fn get_author(username: String) -> Result<Option<PublicProfileModel>, ServerFnError> {
+    use crate::AppState;
+
+    use entities::prelude::{User, UserProfile};
+    use entities::user;
+
+    let state: Arc<Mutex<AppState>> = use_context().unwrap();
+    let state = state.as_ref().lock().await;
+
+    let user = match User::find()
+        .filter(user::Column::Username.eq(username))
+        .find_also_related(UserProfile)
+        .one(&state.conn)
+        .await
+    {
+        Ok(user_exists) => match user_exists {
+            Some(user) => user,
+            None => return Ok(None),
+        },
+        Err(db_err) => {
+            return Err(ServerFnError::new(format!(
+                "A error happened when requesting the author, try again later. DbErr: {}",
+                db_err.to_string()
+            )))
+        }
+    };
+
+    drop(state);
+
+    let social_links = get_social_links(user.0.id).await?;
+    let mut author = PublicProfileModel::from(UserModel::from(user));
+    author.social_links = social_links;
+
+    Ok(Some(author))
+}
+
+/// Read the public profiles of every user that have written at least one article.
+#[server(ReadAuthors, "/api/authors")]
+pub async fn get_all_authors() -> Result<Vec<PublicProfileModel>, ServerFnError> {
+    use crate::AppState;
+
+    use entities::post_metadata;
+    use entities::prelude::{PostMetadata, User, UserProfile};
+    use entities::user;
+
+    let state: Arc<Mutex<AppState>> = use_context().unwrap();
+    let state = state.as_ref().lock().await;
+
+    let writers = PostMetadata::find()
+        .select_only()
+        .column(post_metadata::Column::WriterId)
+        .into_query();
+
+    let authors: Vec<PublicProfileModel> = match User::find()
+        .filter(user::Column::Id.in_subquery(writers))
+        .find_also_related(UserProfile)
+        .all(&state.conn)
+        .await
+    {
+        Ok(users) => users
+            .into_iter()
+            .map(|model| PublicProfileModel::from(UserModel::from(model)))
+            .collect(),
+        Err(db_err) => {
+            return Err(ServerFnError::new(format!(
+                "A error happened when requesting the authors, try again later. DbErr: {}",
+                db_err.to_string()
+            )))
+        }
+    };
+
+    Ok(authors)
+}
+
+/// Get the social links shown on the user's public profile.
+#[server(GetSocialLinks, "/api/users")]
+pub async fn get_social_links(user_id: i32) -> Result<Vec<SocialLinkModel>, ServerFnError> {
+    use crate::AppState;
+
+    use entities::prelude::SocialLink;
+    use entities::social_link;
+
+    let state: Arc<Mutex<AppState>> = use_context().unwrap();
+    let state = state.as_ref().lock().await;
+
+    match SocialLink::find()
+        .filter(social_link::Column::UserId.eq(user_id))
+        .order_by_asc(social_link::Column::Id)
+        .all(&state.conn)
+        .await
+    {
+        Ok(links) => Ok(links.into_iter().map(SocialLinkModel::from).collect()),
+        Err(db_err) => Err(ServerFnError::new(format!(
+            "A error happened when requesting the user's social links, try again later. DbErr: {}",
+            db_err.to_string()
+        ))),
+    }
+}
+
+#[server(UpdateSocialLinks, "/api/users")]
+pub async fn update_social_links(
+    user_id: i32,
+    links: Vec<SocialLinkModel>,
+) -> Result<Vec<SocialLinkModel>, ServerFnError> {
+    if let Some(user) = user_logged_in().await? {
+        if user == user_id || user_have_permission(user, "Moderar".to_string()).await? {
+            return update_social_links_guard(user_id, links).await;
+        } else {
+            return Err(ServerFnError::new(
+                "User doesn't have the permission to execute this operation.",
+            ));
+        }
+    } else {
+        return Err(ServerFnError::new("User is not logged in."));
+    }
+}
+
+/// Replace every social link of the user by `links`.
+#[cfg(feature = "ssr")]
+pub async fn update_social_links_guard(
+    user_id: i32,
+    links: Vec<SocialLinkModel>,
+) -> Result<Vec<SocialLinkModel>, ServerFnError> {
+    use crate::AppState;
+
+    use entities::prelude::SocialLink;
+    use entities::social_link;
+
+    let state: Arc<Mutex<AppState>> = use_context().unwrap();
+    let state = state.as_ref().lock().await;
+
+    let transaction = match state.conn.begin().await {
+        Ok(transaction) => transaction,
+        Err(db_err) => {
+            return Err(ServerFnError::new(format!(
+                "A error happened while starting a new transaction over database. DbErr: {}",
+                db_err.to_string()
+            )))
+        }
+    };
+
+    if let Err(db_err) = SocialLink::delete_many()
+        .filter(social_link::Column::UserId.eq(user_id))
+        .exec(&transaction)
+        .await
+    {
+        return Err(ServerFnError::new(format!(
+            "A error happened when removing the user's social links, try again later. DbErr: {}",
+            db_err.to_string()
+        )));
+    }
+
+    let mut saved_links = vec![];
+
+    for link in links {
+        if link.label.trim().is_empty() || link.url.trim().is_empty() {
+            continue;
+        }
+
+        let url = link.url.trim();
+        if !["https://", "http://", "mailto:"]
+            .iter()
+            .any(|scheme| url.starts_with(scheme))
+        {
+            return Err(ServerFnError::new(format!(
+                "The social link \"{}\" must be a http(s) or mailto URL.",
+                link.label
+            )));
+        }
+
+        let new_link = social_link::ActiveModel {
+            user_id: Set(user_id),
+            label: Set(link.label.trim().to_string()),
+            url: Set(url.to_string()),
+            ..Default::default()
+        };
+
+        match new_link.insert(&transaction).await {
+            Ok(link) => saved_links.push(SocialLinkModel::from(link)),
+            Err(db_err) => {
+                return Err(ServerFnError::new(format!(
+                    "A error happened when saving the user's social links, try again later. DbErr: {}",
+                    db_err.to_string()
+                )))
+            }
+        }
+    }
+
+    match transaction.commit().await {
+        Ok(_) => Ok(saved_links),
+        Err(db_err) => Err(ServerFnError::new(format!(
+            "A error happened when updating the user's social links, try again later. DbErr: {}",
+            db_err.to_string()
+        ))),
+    }
+}
@@ -0,0 +1,164 @@
+use chrono::prelude::*;
+use leptos::*;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+#[cfg(feature = "ssr")]
+use tokio::sync::Mutex;
+
+use super::users::{user_have_permission, user_logged_in};
+
+#[derive(Clone, Debug, PartialEq, Default, Deserialize, Serialize)]
+pub struct WebmentionModel {
+    pub id: i32,
+    pub post_id: i32,
+    pub source: String,
+    pub title: Option<String>,
+    pub approved: bool,
+    pub created_at: DateTime<FixedOffset>,
+    pub updated_at: DateTime<FixedOffset>,
+}
+
+#[cfg(feature = "ssr")]
+impl From<entities::webmention::Model> for WebmentionModel {
+    fn from(value: entities::webmention::Model) -> Self {
+        Self {
+            id: value.id,
+            post_id: value.post_id,
+            source: value.source,
+            title: value.title,
+            approved: value.approved,
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+        }
+    }
+}
+
+/// The approved webmentions of an article, from the oldest to the newest.
+#[server(ReadWebmentions, "/api/webmentions")]
+pub async fn get_webmentions(post_id: i32) -> Result<Vec<WebmentionModel>, ServerFnError> {
+    use crate::AppState;
+
+    use entities::prelude::Webmention;
+    use entities::webmention;
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+
+    let state: Arc<Mutex<AppState>> = use_context().unwrap();
+    let state = state.as_ref().lock().await;
+
+    match Webmention::find()
+        .filter(webmention::Column::PostId.eq(post_id))
+        .filter(webmention::Column::Approved.eq(true))
+        .order_by_asc(webmention::Column::CreatedAt)
+        .all(&state.conn)
+        .await
+    {
+        Ok(mentions) => Ok(mentions.into_iter().map(WebmentionModel::from).collect()),
+        Err(db_err) => {
+            return Err(ServerFnError::new(format!(
+                "A error happened when requesting the webmentions, try again later. DbErr: {}",
+                db_err.to_string()
+            )))
+        }
+    }
+}
+
+#[server(ReadPendingWebmentions, "/api/webmentions")]
+pub async fn get_pending_webmentions() -> Result<Vec<WebmentionModel>, ServerFnError> {
+    if let Some(user) = user_logged_in().await? {
+        if user_have_permission(user, "Moderar".to_string()).await? {
+            return get_pending_webmentions_guard().await;
+        } else {
+            return Err(ServerFnError::new(
+                "User doesn't have the permission to execute this operation.",
+            ));
+        }
+    } else {
+        return Err(ServerFnError::new("User is not logged in."));
+    }
+}
+
+/// The webmentions waiting for moderation, from the newest to the oldest.
+#[cfg(feature = "ssr")]
+pub async fn get_pending_webmentions_guard() -> Result<Vec<WebmentionModel>, ServerFnError> {
+    use crate::AppState;
+
+    use entities::prelude::Webmention;
+    use entities::webmention;
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder};
+
+    let state: Arc<Mutex<AppState>> = use_context().unwrap();
+    let state = state.as_ref().lock().await;
+
+    match Webmention::find()
+        .filter(webmention::Column::Approved.eq(false))
+        .order_by_desc(webmention::Column::CreatedAt)
+        .all(&state.conn)
+        .await
+    {
+        Ok(mentions) => Ok(mentions.into_iter().map(WebmentionModel::from).collect()),
+        Err(db_err) => {
+            return Err(ServerFnError::new(format!(
+                "A error happened when requesting the webmentions, try again later. DbErr: {}",
+                db_err.to_string()
+            )))
+        }
+    }
+}
+
+#[server(ModerateWebmention, "/api/webmentions")]
+pub async fn moderate_webmention(mention_id: i32, approve: bool) -> Result<(), ServerFnError> {
+    if let Some(user) = user_logged_in().await? {
+        if user_have_permission(user, "Moderar".to_string()).await? {
+            return moderate_webmention_guard(mention_id, approve).await;
+        } else {
+            return Err(ServerFnError::new(
+                "User doesn't have the permission to execute this operation.",
+            ));
+        }
+    } else {
+        return Err(ServerFnError::new("User is not logged in."));
+    }
+}
+
+/// Approve a webmention, showing it under the article, or reject it, deleting it.
+#[cfg(feature = "ssr")]
+pub async fn moderate_webmention_guard(mention_id: i32, approve: bool) -> Result<(), ServerFnError> {
+    use crate::AppState;
+
+    use entities::prelude::Webmention;
+    use entities::webmention;
+    use sea_orm::{ActiveModelTrait, EntityTrait, ModelTrait, Set};
+
+    let state: Arc<Mutex<AppState>> = use_context().unwrap();
+    let state = state.as_ref().lock().await;
+
+    let mention = match Webmention::find_by_id(mention_id).one(&state.conn).await {
+        Ok(Some(mention)) => mention,
+        Ok(None) => return Err(ServerFnError::new("This webmention doesn't exists.")),
+        Err(db_err) => {
+            return Err(ServerFnError::new(format!(
+                "A error happened when requesting the webmention, try again later. DbErr: {}",
+                db_err.to_string()
+            )))
+        }
+    };
+
+    let result = if approve {
+        let mut mention: webmention::ActiveModel = mention.into();
+        mention.approved = Set(true);
+        mention.update(&state.conn).await.map(|_| ())
+    } else {
+        mention.delete(&state.conn).await.map(|_| ())
+    };
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(db_err) => {
+            return Err(ServerFnError::new(format!(
+                "A error happened when moderating the webmention, try again later. DbErr: {}",
+                db_err.to_string()
+            )))
+        }
+    }
+}
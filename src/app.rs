@@ -4,9 +4,16 @@ use crate::error_template::{AppError, ErrorTemplate};
 use crate::pages::admin::dashboard::Dashboard;
 use crate::pages::admin::gallery::Gallery;
 use crate::pages::admin::identity::Identity;
+use crate::pages::admin::maintenance::Maintenance;
+use crate::pages::admin::mentions::Mentions;
 use crate::pages::admin::office::Office;
+use crate::pages::admin::subscribers::Subscribers;
+use crate::pages::albums::{AlbumViewer, Albums};
+use crate::pages::archive::Archive;
 use crate::pages::articles::handler::ArticleLoader;
 use crate::pages::articles::Articles;
+use crate::pages::authors::AuthorPage;
+use crate::pages::newsletter::{NewsletterConfirm, NewsletterUnsubscribe};
 use leptos::*;
 use leptos_meta::*;
 use leptos_router::*;
@@ -32,6 +39,9 @@ pub fn App() -> impl IntoView {
         // sets the document title
         <Title formatter/>
 
+        // lets other sites notify us when they link to an article
+        <Link rel="webmention" href="/webmention"/>
+
         // content for this welcome page
         <Router fallback=|| {
             let mut outside_errors = Errors::default();
@@ -51,7 +61,16 @@ pub fn App() -> impl IntoView {
                     }>
                         <Route path="/" view=Index/>
                         <Route path="/articles" view=Articles/>
-                        <Route path="/articles/:slug" view=ArticleLoader/>
+                        // Rendered whole before answering, so the article can set it's `Last-Modified`
+                        <Route path="/articles/:slug" view=ArticleLoader ssr=SsrMode::Async/>
+                        <Route path="/archive" view=Archive/>
+                        <Route path="/archive/:year" view=Archive/>
+                        <Route path="/archive/:year/:month" view=Archive/>
+                        <Route path="/albums" view=Albums/>
+                        <Route path="/albums/:slug" view=AlbumViewer/>
+                        <Route path="/authors/:username" view=AuthorPage/>
+                        <Route path="/newsletter/confirm/:token" view=NewsletterConfirm/>
+                        <Route path="/newsletter/unsubscribe/:token" view=NewsletterUnsubscribe/>
                     </Route>
                     <Route path="/admin" view=move || {
                         view! {
@@ -68,6 +87,9 @@ pub fn App() -> impl IntoView {
                             <Route path="/office" view=Office/>
                             <Route path="/gallery" view=Gallery/>
                             <Route path="/identity" view=Identity/>
+                            <Route path="/mentions" view=Mentions/>
+                            <Route path="/subscribers" view=Subscribers/>
+                            <Route path="/maintenance" view=Maintenance/>
                         </Route>
                     </Route>
                 </Routes>
@@ -0,0 +1,309 @@
+//! ## Caching
+//! Conditional GET support. Responses carry a strong `ETag`, derived from a SHA-256 of their
+//! content, and a `Last-Modified` when we know it, so browsers and proxies can revalidate with
+//! `If-None-Match` or `If-Modified-Since` and get a `304 Not Modified` back.
+//!
+//! Files streamed from disk can't be hashed without reading them whole, `crate::streaming` gives
+//! them their own `ETag`.
+//!
+//! Pages go through [`conditional_get`], which buffers them to hash the rendered HTML. An article
+//! page shows more than its post, like approved webmentions, the author's profile and image alt
+//! texts, so its `updated_at` alone isn't a good enough validator.
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+use std::time::SystemTime;
+
+/// Images don't change once uploaded, but are revalidated once a day anyway.
+pub const IMAGE_CACHE_CONTROL: &str = "public, max-age=86400";
+
+/// Anonymous pages may be kept for a short while by shared caches, as they are the same for every
+/// reader. `Vary: Cookie` keeps them apart from the pages of logged in users.
+pub const PAGE_CACHE_CONTROL: &str = "public, max-age=60, must-revalidate";
+
+/// Pages of logged in users are always revalidated and never stored by shared caches.
+pub const PRIVATE_CACHE_CONTROL: &str = "private, no-cache";
+
+/// The biggest page that is buffered for hashing.
+const MAX_PAGE_SIZE: usize = 8 * 1024 * 1024;
+
+/// The cookie `tower-sessions` uses for the session id.
+const SESSION_COOKIE: &str = "id";
+
+/// A strong `ETag` for `content`.
+pub fn strong_etag(content: &[u8]) -> String {
+    let hash = Sha256::digest(content);
+    let hex: String = hash.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("\"{}\"", hex)
+}
+
+pub fn http_date(time: SystemTime) -> String {
+    httpdate::fmt_http_date(time)
+}
+
+/// Whether the client's cached copy, described by the request `headers`, is still fresh.
+/// `If-None-Match` takes precedence over `If-Modified-Since`, as RFC 9110 asks. Without an
+/// `etag`, only `If-None-Match: *` matches.
+pub fn is_not_modified(
+    headers: &HeaderMap,
+    etag: Option<&str>,
+    last_modified: Option<SystemTime>,
+) -> bool {
+    if let Some(if_none_match) = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+    {
+        // GET uses the weak comparison, so a `W/` prefix still matches.
+        return if_none_match.split(',').map(str::trim).any(|candidate| {
+            candidate == "*"
+                || etag.is_some_and(|etag| {
+                    candidate.trim_start_matches("W/") == etag.trim_start_matches("W/")
+                })
+        });
+    }
+
+    match (
+        last_modified,
+        headers
+            .get(header::IF_MODIFIED_SINCE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| httpdate::parse_http_date(value).ok()),
+    ) {
+        // HTTP dates have a precision of seconds, so compare them formatted.
+        (Some(last_modified), Some(since)) => {
+            let last_modified = httpdate::parse_http_date(&http_date(last_modified))
+                .unwrap_or(last_modified);
+            last_modified <= since
+        }
+        _ => false,
+    }
+}
+
+/// A `304 Not Modified` carrying the validators and cache policy of the full response.
+pub fn not_modified(
+    etag: &str,
+    last_modified: Option<SystemTime>,
+    cache_control: &'static str,
+) -> Response {
+    let mut response = StatusCode::NOT_MODIFIED.into_response();
+    set_validators(response.headers_mut(), etag, last_modified, cache_control);
+    response
+}
+
+pub fn set_validators(
+    headers: &mut HeaderMap,
+    etag: &str,
+    last_modified: Option<SystemTime>,
+    cache_control: &'static str,
+) {
+    if let Ok(etag) = HeaderValue::from_str(etag) {
+        headers.insert(header::ETAG, etag);
+    }
+    if let Some(last_modified) = last_modified {
+        if let Ok(last_modified) = HeaderValue::from_str(&http_date(last_modified)) {
+            headers.insert(header::LAST_MODIFIED, last_modified);
+        }
+    }
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static(cache_control));
+}
+
+fn has_session(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|cookies| cookies.split(';'))
+        .any(|cookie| cookie.trim().starts_with(&format!("{}=", SESSION_COOKIE)))
+}
+
+/// Middleware adding validators and a cache policy to rendered pages, answering with a
+/// `304 Not Modified` when the client already has them.
+pub async fn conditional_get(request: Request, next: Next) -> Response {
+    let method = request.method().clone();
+    let request_headers = request.headers().clone();
+    let response = next.run(request).await;
+
+    let is_page = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("text/html"));
+
+    if !(method == Method::GET || method == Method::HEAD)
+        || response.status() != StatusCode::OK
+        || !is_page
+    {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+
+    let cache_control = if has_session(&request_headers)
+        || parts.headers.contains_key(header::SET_COOKIE)
+    {
+        PRIVATE_CACHE_CONTROL
+    } else {
+        PAGE_CACHE_CONTROL
+    };
+
+    // Pages that already know their `ETag` don't need to be read whole to be hashed
+    if let Some(etag) = parts
+        .headers
+        .get(header::ETAG)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+    {
+        if is_not_modified(&request_headers, Some(&etag), None) {
+            let mut response = not_modified(&etag, None, cache_control);
+            set_page_policy(response.headers_mut(), cache_control);
+            return response;
+        }
+
+        set_page_policy(&mut parts.headers, cache_control);
+        return Response::from_parts(parts, body);
+    }
+
+    let body = match to_bytes(body, MAX_PAGE_SIZE).await {
+        Ok(body) => body,
+        Err(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+
+    let etag = strong_etag(&body);
+    if is_not_modified(&request_headers, Some(&etag), None) {
+        let mut response = not_modified(&etag, None, cache_control);
+        set_page_policy(response.headers_mut(), cache_control);
+        return response;
+    }
+
+    set_validators(&mut parts.headers, &etag, None, cache_control);
+    set_page_policy(&mut parts.headers, cache_control);
+
+    Response::from_parts(parts, Body::from(body))
+}
+
+fn set_page_policy(headers: &mut HeaderMap, cache_control: &'static str) {
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static(cache_control));
+    headers.insert(header::VARY, HeaderValue::from_static("Cookie"));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{middleware, routing::get, Router};
+    use tower::ServiceExt;
+
+    const ARTICLE_ETAG: &str = "\"article\"";
+
+    fn app() -> Router {
+        Router::new()
+            .route(
+                "/",
+                get(|| async { ([(header::CONTENT_TYPE, "text/html")], "<p>Home</p>") }),
+            )
+            .route(
+                "/article",
+                get(|| async {
+                    (
+                        [
+                            (header::CONTENT_TYPE, "text/html"),
+                            (header::ETAG, ARTICLE_ETAG),
+                        ],
+                        vec![b'a'; MAX_PAGE_SIZE + 1],
+                    )
+                }),
+            )
+            .layer(middleware::from_fn(conditional_get))
+    }
+
+    async fn request(uri: &str, headers: &[(header::HeaderName, &str)]) -> Response {
+        let mut request = Request::builder().uri(uri);
+        for (name, value) in headers {
+            request = request.header(name, *value);
+        }
+        app()
+            .oneshot(request.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn pages_are_hashed_and_revalidated() {
+        let response = request("/", &[]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::VARY], "Cookie");
+        let etag = response.headers()[header::ETAG]
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert_eq!(etag, strong_etag(b"<p>Home</p>"));
+
+        let response = request("/", &[(header::IF_NONE_MATCH, &etag)]).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(
+            response.headers()[header::CACHE_CONTROL],
+            PAGE_CACHE_CONTROL
+        );
+    }
+
+    #[tokio::test]
+    async fn pages_with_their_own_etag_are_not_buffered() {
+        let response = request("/article", &[]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::ETAG], ARTICLE_ETAG);
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body.len(), MAX_PAGE_SIZE + 1);
+
+        let response = request("/article", &[(header::IF_NONE_MATCH, "\"abc\"")]).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = request(
+            "/article",
+            &[(header::COOKIE, "id=session"), (header::IF_NONE_MATCH, ARTICLE_ETAG)],
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::ETAG], ARTICLE_ETAG);
+        assert_eq!(
+            response.headers()[header::CACHE_CONTROL],
+            PRIVATE_CACHE_CONTROL
+        );
+    }
+
+    #[tokio::test]
+    async fn a_last_modified_alone_is_not_trusted() {
+        let app = Router::new()
+            .route(
+                "/",
+                get(|| async {
+                    (
+                        [
+                            (header::CONTENT_TYPE, "text/html"),
+                            (header::LAST_MODIFIED, "Thu, 23 May 2024 12:00:00 GMT"),
+                        ],
+                        "<p>Article</p>",
+                    )
+                }),
+            )
+            .layer(middleware::from_fn(conditional_get));
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/")
+                    .header(header::IF_MODIFIED_SINCE, "Fri, 24 May 2024 12:00:00 GMT")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::ETAG],
+            strong_etag(b"<p>Article</p>")
+        );
+    }
+}
@@ -0,0 +1,35 @@
+use leptos::*;
+
+use crate::api::images::{image_srcset, image_url, ImagePreview, ImageSize, PLACEHOLDER_ONLOAD};
+
+/// A gallery image with every resized variant in it's `srcset`, so the browser downloads the
+/// smallest one that fits `sizes`. With a `preview`, it holds it's size and shows a placeholder
+/// until it loads.
+#[component]
+pub fn ResponsiveImage(
+    id: i32,
+    #[prop(optional, into)] alt: String,
+    #[prop(optional)] class: Option<&'static str>,
+    #[prop(default = "100vw")] sizes: &'static str,
+    #[prop(optional)] preview: Option<ImagePreview>,
+) -> impl IntoView {
+    let preview = preview.unwrap_or_default();
+    let style = preview.style();
+    let onload = style.as_ref().map(|_| PLACEHOLDER_ONLOAD);
+
+    view! {
+        <img
+            class=class.unwrap_or_default()
+            src=image_url(id, Some(ImageSize::Medium))
+            srcset=image_srcset(id)
+            sizes=sizes
+            alt=alt
+            width=preview.width
+            height=preview.height
+            style=style
+            onload=onload
+            loading="lazy"
+            decoding="async"
+        />
+    }
+}
@@ -3,3 +3,7 @@ pub mod navbar;
 pub mod background;
 
 pub mod container;
+
+pub mod newsletter;
+
+pub mod image;
@@ -39,6 +39,8 @@ pub fn Navbar() -> impl IntoView {
                     <ul class="inline">
                         <NavButton href="/">"Home"</NavButton>
                         <NavButton href="/articles">"Posts"</NavButton>
+                        <NavButton href="/archive">"Archive"</NavButton>
+                        <NavButton href="/albums">"Albums"</NavButton>
                         <NavButtonUnavailable href="/projects">"Projects"</NavButtonUnavailable>
                         <NavButtonUnavailable href="/about-me">"About me"</NavButtonUnavailable>
                     </ul>
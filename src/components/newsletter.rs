@@ -0,0 +1,42 @@
+use leptos::*;
+
+use crate::{api::newsletter::subscribe, components::container::RetroContainer};
+
+use crate::i18n::*;
+
+/// A form for subscribing to the newsletter by e-mail
+#[component]
+pub fn NewsletterForm() -> impl IntoView {
+    let i18n = use_i18n();
+
+    let email = create_rw_signal(String::new());
+    let subscribe_action = create_action(|email: &String| subscribe(email.clone()));
+
+    view! {
+        <RetroContainer class="min-w-60 mt-4">
+            <p class="font-extrabold text-2xl">{t!(i18n, home.newsletter)}</p>
+            <p class="mb-2">{t!(i18n, home.newsletter_description)}</p>
+            {move || match subscribe_action.value().get() {
+                Some(Ok(_)) => view! {
+                    <p class="font-bold">{t!(i18n, home.newsletter_sent)}</p>
+                }.into_view(),
+                result => view! {
+                    <form on:submit=move |ev| {
+                        ev.prevent_default();
+                        subscribe_action.dispatch(email.get());
+                    }>
+                        <input type="email" required on:input=move |ev| {
+                            email.set(event_target_value(&ev));
+                        } prop:value=email placeholder="email@example.com" class="bg-orange-400 border-4 border-t-orange-900 border-l-orange-800 border-r-orange-300 border-b-orange-300 w-full p-2 focus:outline-none"/>
+                        <button type="submit" class="bg-orange-400 mt-2 border-4 border-b-orange-900 active:border-t-orange-900 border-r-orange-800 active:border-l-orange-900 border-l-orange-300 active:border-r-orange-300 border-t-orange-300 active:border-b-orange-300 w-full cursor-pointer py-2">
+                            {t!(i18n, home.newsletter_subscribe)}
+                        </button>
+                        {result.and_then(|result| result.err()).map(|err| view! {
+                            <p class="text-[#630000] mt-2">{err.to_string()}</p>
+                        })}
+                    </form>
+                }.into_view()
+            }}
+        </RetroContainer>
+    }
+}
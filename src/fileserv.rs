@@ -1,15 +1,26 @@
-use crate::{app::App, AppState};
+use crate::{
+    app::App,
+    caching::IMAGE_CACHE_CONTROL,
+    gallery::{
+        media::{Download, MediaStore},
+        transform::{Transform, TransformQuery},
+    },
+    streaming::{serve_bytes, serve_file},
+    AppState,
+};
 use axum::response::Response as AxumResponse;
 use axum::{
     body::Body,
-    extract::{Path, State},
-    http::{Request, Response, StatusCode, Uri},
+    extract::{Path, Query, State},
+    http::{HeaderMap, HeaderValue, Request, Response, StatusCode, Uri},
     response::IntoResponse,
 };
 use http::header;
 use leptos::*;
+use sea_orm::DatabaseConnection;
 use std::sync::Arc;
-use tokio::{fs, sync::Mutex};
+use std::time::SystemTime;
+use tokio::sync::Mutex;
 use tower::ServiceExt;
 use tower_http::services::ServeDir;
 
@@ -38,22 +49,17 @@ pub async fn file_and_error_handler(
 
 async fn get_image_with_extern_state(
     image_id: i32,
-    state_ref: Arc<Mutex<AppState>>,
-) -> Result<Option<Vec<u8>>, ServerFnError> {
+    conn: &DatabaseConnection,
+) -> Result<Option<entities::image_metadata::Model>, ServerFnError> {
+    use crate::gallery::storage::is_plain_upload_path;
     use entities::prelude::ImageMetadata;
     use sea_orm::EntityTrait;
 
-    let state = state_ref.as_ref().lock().await;
-
-    match ImageMetadata::find_by_id(image_id).one(&state.conn).await {
-        Ok(image_exists) => match image_exists {
-            Some(image) => {
-                let data = fs::read(&image.image_path).await.unwrap();
-                Ok(Some(data))
-            }
-            None => return Ok(None),
-        },
-
+    match ImageMetadata::find_by_id(image_id).one(conn).await {
+        // Images whose path leads outside of the uploads are treated as missing. Symlinks are
+        // checked by the store.
+        Ok(Some(image)) if is_plain_upload_path(&image.image_path) => Ok(Some(image)),
+        Ok(_) => Ok(None),
         Err(db_err) => {
             return Err(ServerFnError::new(format!(
                 "A error happened when requesting the image, try again later. DbErr: {}",
@@ -63,16 +69,220 @@ async fn get_image_with_extern_state(
     }
 }
 
+/// Serve an image, transformed when the query asks for it, see `crate::gallery::transform`.
 pub async fn get_image_by_id_handler(
     Path(id): Path<i32>,
+    Query(query): Query<TransformQuery>,
+    headers: HeaderMap,
     state: Arc<Mutex<AppState>>,
-) -> impl IntoResponse {
-    let image = match get_image_with_extern_state(id, state).await {
-        Ok(Some(data)) => data,
-        _ => vec![],
+) -> AxumResponse {
+    use crate::gallery::{mime::ImageType, transform};
+
+    let (conn, media) = media_state(&state).await;
+    let image = match get_image_with_extern_state(id, &conn).await {
+        Ok(Some(image)) => image,
+        Ok(None) => return StatusCode::NOT_FOUND.into_response(),
+        Err(err) => return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+    };
+
+    let image_type = ImageType::from_mime_type(&image.mime_type);
+    let transform = match image_type.map(|image_type| Transform::from_query(&query, image_type)) {
+        Some(Ok(transform)) => transform,
+        Some(Err(reason)) => return (StatusCode::BAD_REQUEST, reason).into_response(),
+        None => None,
+    };
+
+    match (transform, image_type) {
+        (Some(transform), Some(image_type)) => {
+            match transform::transformed_file(
+                media.as_ref(),
+                &image.image_path,
+                image.content_hash,
+                image_type,
+                transform,
+            )
+            .await
+            {
+                // Transforms are cached on this host, whatever the store
+                Ok(path) => {
+                    let mut response = serve_file(
+                        &headers,
+                        &path,
+                        None,
+                        Some(SystemTime::from(image.created_at)),
+                        IMAGE_CACHE_CONTROL,
+                    )
+                    .await;
+                    if response.status().is_success() {
+                        set_image_headers(response.headers_mut(), transform.format.mime_type());
+                    }
+                    response
+                }
+                Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err).into_response(),
+            }
+        }
+        _ => {
+            serve_image(
+                media.as_ref(),
+                &headers,
+                &image.image_path,
+                image.content_hash.as_deref(),
+                SystemTime::from(image.created_at),
+                &image.mime_type,
+            )
+            .await
+        }
+    }
+}
+
+/// Serve a resized variant of an image, see `crate::gallery::variants`.
+pub async fn get_image_variant_handler(
+    Path((id, size)): Path<(i32, String)>,
+    headers: HeaderMap,
+    state: Arc<Mutex<AppState>>,
+) -> AxumResponse {
+    use crate::api::images::ImageSize;
+    use crate::gallery::{mime::ImageType, variants};
+
+    let size = match ImageSize::from_name(&size) {
+        Some(size) => size,
+        None => return StatusCode::NOT_FOUND.into_response(),
+    };
+
+    let (conn, media) = media_state(&state).await;
+    let image = match get_image_with_extern_state(id, &conn).await {
+        Ok(Some(image)) => image,
+        Ok(None) => return StatusCode::NOT_FOUND.into_response(),
+        Err(err) => return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+    };
+
+    let image_type = match ImageType::from_mime_type(&image.mime_type) {
+        Some(image_type) => image_type,
+        None => return StatusCode::NOT_FOUND.into_response(),
+    };
+
+    let (path, variant_type) =
+        match variants::variant_file(media.as_ref(), &image.image_path, image_type, size).await {
+            Ok(variant) => variant,
+            Err(err) => return (StatusCode::INTERNAL_SERVER_ERROR, err).into_response(),
+        };
+    // SVGs have no variants, and are served with their own hash.
+    let content_hash = match path == image.image_path {
+        true => image.content_hash.as_deref(),
+        false => None,
     };
 
-    ([(header::CONTENT_TYPE, "image/png")], image)
+    serve_image(
+        media.as_ref(),
+        &headers,
+        &path,
+        content_hash,
+        SystemTime::from(image.created_at),
+        variant_type.mime_type(),
+    )
+    .await
+}
+
+/// The connection and the media store. The connection is a pool, so the lock is only held to
+/// clone them, and never while the database or the store are answering.
+async fn media_state(state: &Arc<Mutex<AppState>>) -> (DatabaseConnection, Arc<dyn MediaStore>) {
+    let state = state.as_ref().lock().await;
+    (state.conn.clone(), state.media.clone())
+}
+
+/// Send an image from the media store, see `crate::streaming`, with the headers of it's type.
+/// Images don't change once uploaded, so they were last modified when their row was created.
+/// Stores that presign their URLs get the browser redirected to them, but for SVGs, that always
+/// go through the site to get the headers of [`set_image_headers`].
+async fn serve_image(
+    media: &dyn MediaStore,
+    headers: &HeaderMap,
+    path: &str,
+    content_hash: Option<&str>,
+    uploaded_at: SystemTime,
+    mime_type: &str,
+) -> AxumResponse {
+    use crate::gallery::mime::ImageType;
+
+    let download = match media.download(path).await {
+        Ok(Some(Download::Redirect(_))) if mime_type == ImageType::Svg.mime_type() => media
+            .get(path)
+            .await
+            .map(|content| content.map(Download::Bytes)),
+        download => download,
+    };
+    let mut response = match download {
+        Ok(Some(Download::File(path))) => {
+            serve_file(
+                headers,
+                path,
+                content_hash,
+                Some(uploaded_at),
+                IMAGE_CACHE_CONTROL,
+            )
+            .await
+        }
+        Ok(Some(Download::Bytes(content))) => serve_bytes(
+            headers,
+            content,
+            content_hash,
+            Some(uploaded_at),
+            IMAGE_CACHE_CONTROL,
+        ),
+        // Presigned URLs expire, so browsers come back for a new one
+        Ok(Some(Download::Redirect(url))) => {
+            return match HeaderValue::from_str(&url) {
+                Ok(location) => (
+                    StatusCode::TEMPORARY_REDIRECT,
+                    [
+                        (header::LOCATION, location),
+                        (
+                            header::CACHE_CONTROL,
+                            HeaderValue::from_static("private, no-cache"),
+                        ),
+                    ],
+                )
+                    .into_response(),
+                Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+            }
+        }
+        Ok(None) => return StatusCode::NOT_FOUND.into_response(),
+        Err(err) => return (StatusCode::INTERNAL_SERVER_ERROR, err).into_response(),
+    };
+    if response.status().is_success() {
+        set_image_headers(response.headers_mut(), mime_type);
+    }
+    response
+}
+
+/// Serve images with the type sniffed on upload, or the one they were converted to, and keep
+/// browsers from guessing another one. SVGs opened directly get a sandbox, so even a script that got through can't run.
+fn set_image_headers(headers: &mut HeaderMap, mime_type: &str) {
+    use crate::gallery::{mime::ImageType, transform::OutputFormat};
+
+    let image_type = ImageType::from_mime_type(mime_type);
+    let content_type = image_type
+        .map(|image_type| image_type.mime_type())
+        .or_else(|| {
+            OutputFormat::ALL
+                .into_iter()
+                .map(|format| format.mime_type())
+                .find(|format_mime_type| *format_mime_type == mime_type)
+        })
+        .unwrap_or("application/octet-stream");
+
+    headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+    headers.insert(
+        header::X_CONTENT_TYPE_OPTIONS,
+        HeaderValue::from_static("nosniff"),
+    );
+
+    if image_type == Some(ImageType::Svg) {
+        headers.insert(
+            header::CONTENT_SECURITY_POLICY,
+            HeaderValue::from_static("default-src 'none'; style-src 'unsafe-inline'; sandbox"),
+        );
+    }
 }
 
 async fn get_static_file(uri: Uri, root: &str) -> Result<Response<Body>, (StatusCode, String)> {
@@ -90,3 +300,44 @@ async fn get_static_file(uri: Uri, root: &str) -> Result<Response<Body>, (Status
         )),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gallery::media::tests::MemoryStore;
+
+    #[tokio::test]
+    async fn proxies_presigned_svgs() {
+        let media = MemoryStore {
+            presigned: true,
+            ..MemoryStore::with_files(&["data/uploads/ab/x.png", "data/uploads/cd/y.svg"])
+        };
+
+        let response = serve_image(
+            &media,
+            &HeaderMap::new(),
+            "data/uploads/ab/x.png",
+            None,
+            SystemTime::UNIX_EPOCH,
+            "image/png",
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+
+        let response = serve_image(
+            &media,
+            &HeaderMap::new(),
+            "data/uploads/cd/y.svg",
+            None,
+            SystemTime::UNIX_EPOCH,
+            "image/svg+xml",
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "image/svg+xml");
+        assert!(response.headers()[header::CONTENT_SECURITY_POLICY]
+            .to_str()
+            .unwrap()
+            .contains("sandbox"));
+    }
+}
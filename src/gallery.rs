@@ -0,0 +1,11 @@
+//! ## Gallery
+//! Server side handling of the images uploaded to the gallery and served on `/gallery/:id` and
+//! `/gallery/:id/:size`.
+
+pub mod media;
+pub mod metadata;
+pub mod mime;
+pub mod placeholder;
+pub mod storage;
+pub mod transform;
+pub mod variants;
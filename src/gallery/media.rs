@@ -0,0 +1,648 @@
+//! ## Media
+//! Where the files of the gallery are kept, behind [`MediaStore`]. Files are named by their path
+//! under [`UPLOADS_DIR`], the same saved on `image_metadata`, whatever the store.
+//!
+//! The store is picked with `MEDIA_STORE`:
+//! - `local`, the default, is [`LocalStore`], the uploads directory on this host;
+//! - `s3` is [`S3Store`], any S3 compatible API, like AWS, R2 or a local MinIO. It's set with
+//!   `S3_BUCKET`, `S3_REGION`, `S3_ENDPOINT` (like `http://localhost:9000` for MinIO),
+//!   `S3_ACCESS_KEY` and `S3_SECRET_KEY`. Files are proxied through the site by default, or
+//!   redirected to a presigned URL that lasts `S3_PRESIGN_SECONDS` with `S3_DOWNLOADS=presigned`.
+//!
+//! Starting the site with `--copy-media-to-s3` copies every local file to the S3 store with
+//! [`copy_media`], and exits. Files already there are skipped, so it can be run again after a
+//! failure, and the site switched with `MEDIA_STORE=s3` once it's done.
+
+use super::{
+    mime::ImageType,
+    storage::{is_plain_upload_path, normalize_path, resolve_upload_path, UPLOADS_DIR},
+};
+use crate::maintenance::QUARANTINE_DIR;
+use async_trait::async_trait;
+use chrono::DateTime;
+use s3::{creds::Credentials, Bucket, Region};
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use tokio::fs;
+
+/// A file on a store.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MediaObject {
+    pub path: String,
+    pub modified: Option<SystemTime>,
+}
+
+/// How a file is sent to the browser.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Download {
+    /// Streamed from disk.
+    File(PathBuf),
+    /// Proxied, after being fetched from the store.
+    Bytes(Vec<u8>),
+    /// Fetched by the browser from the store itself.
+    Redirect(String),
+}
+
+#[async_trait]
+pub trait MediaStore: Send + Sync + std::fmt::Debug {
+    /// Save a file, replacing the one on `path`, if any.
+    async fn put(&self, path: &str, content: Vec<u8>, content_type: &str) -> Result<(), String>;
+
+    /// The content of a file, `None` when it doesn't exist.
+    async fn get(&self, path: &str) -> Result<Option<Vec<u8>>, String>;
+
+    async fn exists(&self, path: &str) -> Result<bool, String>;
+
+    async fn delete(&self, path: &str) -> Result<(), String>;
+
+    /// Every file of the store, quarantined ones left out.
+    async fn list(&self) -> Result<Vec<MediaObject>, String>;
+
+    /// Move a file out of the way, to the quarantine named `run`, see `crate::maintenance`.
+    async fn quarantine(&self, path: &str, run: &str) -> Result<(), String>;
+
+    /// How the file on `path` is sent, `None` when it doesn't exist.
+    async fn download(&self, path: &str) -> Result<Option<Download>, String>;
+
+    /// The name of the file on `path`, the same for every path leading to it, so paths can be
+    /// compared.
+    async fn normalize(&self, path: &str) -> String {
+        normalize_path(path)
+    }
+}
+
+fn outside_uploads() -> String {
+    "The path is outside of the uploads directory.".to_string()
+}
+
+/// The files on [`UPLOADS_DIR`].
+#[derive(Clone, Debug, Default)]
+pub struct LocalStore;
+
+#[async_trait]
+impl MediaStore for LocalStore {
+    async fn put(&self, path: &str, content: Vec<u8>, _content_type: &str) -> Result<(), String> {
+        if !is_plain_upload_path(path) {
+            return Err(outside_uploads());
+        }
+        if let Some(parent) = Path::new(path).parent() {
+            fs::create_dir_all(parent)
+                .await
+                .map_err(|err| err.to_string())?;
+        }
+
+        // Written aside and renamed, so a concurrent request never streams half a file.
+        let path = resolve_upload_path(path).await?;
+        let partial_path = format!("{}.{:016x}.partial", path.display(), rand::random::<u64>());
+        fs::write(&partial_path, content)
+            .await
+            .map_err(|err| err.to_string())?;
+        fs::rename(&partial_path, &path)
+            .await
+            .map_err(|err| err.to_string())
+    }
+
+    async fn get(&self, path: &str) -> Result<Option<Vec<u8>>, String> {
+        let path = resolve_upload_path(path).await?;
+        match fs::read(path).await {
+            Ok(content) => Ok(Some(content)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+
+    async fn exists(&self, path: &str) -> Result<bool, String> {
+        let path = resolve_upload_path(path).await?;
+        fs::try_exists(path).await.map_err(|err| err.to_string())
+    }
+
+    async fn delete(&self, path: &str) -> Result<(), String> {
+        let path = resolve_upload_path(path).await?;
+        fs::remove_file(path).await.map_err(|err| err.to_string())
+    }
+
+    async fn list(&self) -> Result<Vec<MediaObject>, String> {
+        let mut objects = vec![];
+        let mut pending = vec![PathBuf::from(UPLOADS_DIR)];
+
+        while let Some(dir) = pending.pop() {
+            let mut entries = fs::read_dir(&dir).await.map_err(|err| err.to_string())?;
+            while let Ok(Some(entry)) = entries.next_entry().await {
+                let Ok(file_type) = entry.file_type().await else {
+                    continue;
+                };
+                // Symlinks are listed, not followed, and links to directories aren't files
+                if file_type.is_dir() {
+                    pending.push(entry.path());
+                    continue;
+                }
+                if file_type.is_symlink()
+                    && fs::metadata(entry.path())
+                        .await
+                        .is_ok_and(|metadata| metadata.is_dir())
+                {
+                    continue;
+                }
+                let modified = fs::symlink_metadata(entry.path())
+                    .await
+                    .ok()
+                    .and_then(|metadata| metadata.modified().ok());
+                objects.push(MediaObject {
+                    path: entry.path().to_string_lossy().into_owned(),
+                    modified,
+                });
+            }
+        }
+
+        objects.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(objects)
+    }
+
+    async fn quarantine(&self, path: &str, run: &str) -> Result<(), String> {
+        if !is_plain_upload_path(path) {
+            return Err(outside_uploads());
+        }
+        // Keeping the path inside `data/`, like `data/quarantine/<run>/uploads/ab/<hash>.png`
+        let relative = Path::new(path)
+            .strip_prefix("data")
+            .map_err(|err| err.to_string())?;
+        let target = Path::new(QUARANTINE_DIR).join(run).join(relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .map_err(|err| err.to_string())?;
+        }
+        fs::rename(path, target)
+            .await
+            .map_err(|err| err.to_string())
+    }
+
+    async fn download(&self, path: &str) -> Result<Option<Download>, String> {
+        match resolve_upload_path(path).await {
+            Ok(path) => Ok(Some(Download::File(path))),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Where `path` leads once symlinks are followed.
+    async fn normalize(&self, path: &str) -> String {
+        match resolve_upload_path(path).await {
+            Ok(resolved) => resolved.to_string_lossy().into_owned(),
+            Err(_) => normalize_path(path),
+        }
+    }
+}
+
+/// How files on a [`S3Store`] reach browsers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum S3Downloads {
+    /// Through the site, with it's caching headers.
+    #[default]
+    Proxied,
+    /// Redirected to a presigned URL, so the bytes never go through the site.
+    Presigned { expiry_secs: u32 },
+}
+
+/// The files on a bucket of a S3 compatible API. Keys are the paths without [`UPLOADS_DIR`], like
+/// `ab/<hash>.png`, and quarantined files go under `quarantine/<run>/`.
+#[derive(Clone, Debug)]
+pub struct S3Store {
+    bucket: Box<Bucket>,
+    downloads: S3Downloads,
+}
+
+/// Where quarantined files go on a bucket.
+const S3_QUARANTINE_PREFIX: &str = "quarantine/";
+
+impl S3Store {
+    /// With path style URLs when there is a custom endpoint, as MinIO expects.
+    pub fn new(
+        bucket: &str,
+        region: &str,
+        endpoint: Option<String>,
+        access_key: &str,
+        secret_key: &str,
+        downloads: S3Downloads,
+    ) -> Result<Self, String> {
+        let credentials = Credentials::new(Some(access_key), Some(secret_key), None, None, None)
+            .map_err(|err| err.to_string())?;
+        let region = match &endpoint {
+            Some(endpoint) => Region::Custom {
+                region: region.to_string(),
+                endpoint: endpoint.clone(),
+            },
+            None => region.parse::<Region>().map_err(|err| err.to_string())?,
+        };
+
+        let mut bucket = Bucket::new(bucket, region, credentials).map_err(|err| err.to_string())?;
+        if endpoint.is_some() {
+            bucket = bucket.with_path_style();
+        }
+
+        Ok(Self {
+            bucket: Box::new(bucket),
+            downloads,
+        })
+    }
+
+    /// Set from `S3_BUCKET`, `S3_REGION`, `S3_ENDPOINT`, `S3_ACCESS_KEY`, `S3_SECRET_KEY`,
+    /// `S3_DOWNLOADS` and `S3_PRESIGN_SECONDS`.
+    pub fn from_env() -> Result<Self, String> {
+        let var = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+        let required = |name: &str| var(name).ok_or_else(|| format!("{} isn't set.", name));
+
+        let downloads = match var("S3_DOWNLOADS").as_deref() {
+            None | Some("proxied") => S3Downloads::Proxied,
+            Some("presigned") => S3Downloads::Presigned {
+                expiry_secs: var("S3_PRESIGN_SECONDS")
+                    .and_then(|seconds| seconds.parse().ok())
+                    .unwrap_or(60 * 60),
+            },
+            Some(other) => {
+                return Err(format!(
+                    "S3_DOWNLOADS must be proxied or presigned, not {}.",
+                    other
+                ))
+            }
+        };
+
+        Self::new(
+            &required("S3_BUCKET")?,
+            &var("S3_REGION").unwrap_or("us-east-1".to_string()),
+            var("S3_ENDPOINT"),
+            &required("S3_ACCESS_KEY")?,
+            &required("S3_SECRET_KEY")?,
+            downloads,
+        )
+    }
+
+    fn key(path: &str) -> Result<String, String> {
+        if !is_plain_upload_path(path) {
+            return Err(outside_uploads());
+        }
+        Path::new(path)
+            .strip_prefix(UPLOADS_DIR)
+            .map(|key| key.to_string_lossy().replace('\\', "/"))
+            .map_err(|err| err.to_string())
+    }
+
+    fn path(key: &str) -> String {
+        format!("{}/{}", UPLOADS_DIR, key)
+    }
+}
+
+fn failed(action: &str, status: u16) -> String {
+    format!("The object storage answered {} when {}.", status, action)
+}
+
+#[async_trait]
+impl MediaStore for S3Store {
+    async fn put(&self, path: &str, content: Vec<u8>, content_type: &str) -> Result<(), String> {
+        let response = self
+            .bucket
+            .put_object_with_content_type(Self::key(path)?, &content, content_type)
+            .await
+            .map_err(|err| err.to_string())?;
+        match response.status_code() {
+            200..=299 => Ok(()),
+            status => Err(failed("saving a file", status)),
+        }
+    }
+
+    async fn get(&self, path: &str) -> Result<Option<Vec<u8>>, String> {
+        let response = self
+            .bucket
+            .get_object(Self::key(path)?)
+            .await
+            .map_err(|err| err.to_string())?;
+        match response.status_code() {
+            200..=299 => Ok(Some(response.bytes().to_vec())),
+            404 => Ok(None),
+            status => Err(failed("reading a file", status)),
+        }
+    }
+
+    async fn exists(&self, path: &str) -> Result<bool, String> {
+        let (_, status) = self
+            .bucket
+            .head_object(Self::key(path)?)
+            .await
+            .map_err(|err| err.to_string())?;
+        match status {
+            200..=299 => Ok(true),
+            404 => Ok(false),
+            status => Err(failed("looking for a file", status)),
+        }
+    }
+
+    async fn delete(&self, path: &str) -> Result<(), String> {
+        let response = self
+            .bucket
+            .delete_object(Self::key(path)?)
+            .await
+            .map_err(|err| err.to_string())?;
+        match response.status_code() {
+            200..=299 => Ok(()),
+            status => Err(failed("removing a file", status)),
+        }
+    }
+
+    async fn list(&self) -> Result<Vec<MediaObject>, String> {
+        let pages = self
+            .bucket
+            .list(String::new(), None)
+            .await
+            .map_err(|err| err.to_string())?;
+
+        let mut objects = pages
+            .into_iter()
+            .flat_map(|page| page.contents)
+            .filter(|object| !object.key.starts_with(S3_QUARANTINE_PREFIX))
+            .map(|object| MediaObject {
+                path: Self::path(&object.key),
+                modified: DateTime::parse_from_rfc3339(&object.last_modified)
+                    .ok()
+                    .map(SystemTime::from),
+            })
+            .collect::<Vec<_>>();
+        objects.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Ok(objects)
+    }
+
+    async fn quarantine(&self, path: &str, run: &str) -> Result<(), String> {
+        let key = Self::key(path)?;
+        let content = self
+            .get(path)
+            .await?
+            .ok_or_else(|| "The file doesn't exist.".to_string())?;
+        let content_type = ImageType::sniff(&content)
+            .map(|image_type| image_type.mime_type())
+            .unwrap_or("application/octet-stream");
+
+        let response = self
+            .bucket
+            .put_object_with_content_type(
+                format!("{}{}/{}", S3_QUARANTINE_PREFIX, run, key),
+                &content,
+                content_type,
+            )
+            .await
+            .map_err(|err| err.to_string())?;
+        match response.status_code() {
+            200..=299 => self.delete(path).await,
+            status => Err(failed("quarantining a file", status)),
+        }
+    }
+
+    async fn download(&self, path: &str) -> Result<Option<Download>, String> {
+        match self.downloads {
+            S3Downloads::Proxied => Ok(self.get(path).await?.map(Download::Bytes)),
+            S3Downloads::Presigned { expiry_secs } => self
+                .bucket
+                .presign_get(Self::key(path)?, expiry_secs, None)
+                .await
+                .map(|url| Some(Download::Redirect(url)))
+                .map_err(|err| err.to_string()),
+        }
+    }
+}
+
+/// The store set with `MEDIA_STORE`.
+pub fn media_store_from_env() -> Result<std::sync::Arc<dyn MediaStore>, String> {
+    match std::env::var("MEDIA_STORE").ok().as_deref() {
+        None | Some("") | Some("local") => Ok(std::sync::Arc::new(LocalStore)),
+        Some("s3") => Ok(std::sync::Arc::new(S3Store::from_env()?)),
+        Some(other) => Err(format!("MEDIA_STORE must be local or s3, not {}.", other)),
+    }
+}
+
+/// What [`copy_media`] did.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CopyReport {
+    pub copied: usize,
+    /// Files that were on the destination already.
+    pub skipped: usize,
+    /// The files that couldn't be copied, with why.
+    pub failures: Vec<String>,
+}
+
+/// Copy every file of `from` missing on `to`.
+pub async fn copy_media(from: &dyn MediaStore, to: &dyn MediaStore) -> Result<CopyReport, String> {
+    let mut report = CopyReport::default();
+
+    for object in from.list().await? {
+        // Half written files are left behind
+        if object.path.ends_with(".partial") {
+            continue;
+        }
+        match to.exists(&object.path).await {
+            Ok(true) => {
+                report.skipped += 1;
+                continue;
+            }
+            Ok(false) => (),
+            Err(err) => {
+                report.failures.push(format!("{}: {}", object.path, err));
+                continue;
+            }
+        }
+
+        let copied = match from.get(&object.path).await {
+            Ok(Some(content)) => {
+                let content_type = ImageType::sniff(&content)
+                    .map(|image_type| image_type.mime_type())
+                    .unwrap_or("application/octet-stream");
+                to.put(&object.path, content, content_type).await
+            }
+            Ok(None) => Err("The file disappeared.".to_string()),
+            Err(err) => Err(err),
+        };
+        match copied {
+            Ok(()) => report.copied += 1,
+            Err(err) => report.failures.push(format!("{}: {}", object.path, err)),
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::{
+        collections::{BTreeMap, HashSet},
+        sync::Mutex,
+        time::UNIX_EPOCH,
+    };
+
+    /// A store kept in memory. Files are old unless put with [`MemoryStore::put_recent`], and
+    /// paths in `failing` can't be written.
+    #[derive(Debug, Default)]
+    pub(crate) struct MemoryStore {
+        pub files: Mutex<BTreeMap<String, (Vec<u8>, SystemTime)>>,
+        pub failing: HashSet<String>,
+        /// Answer downloads with a redirect, like a presigned [`S3Store`].
+        pub presigned: bool,
+    }
+
+    impl MemoryStore {
+        pub fn with_files(paths: &[&str]) -> Self {
+            let store = Self::default();
+            for path in paths {
+                store.insert(path, path.as_bytes().to_vec(), UNIX_EPOCH);
+            }
+            store
+        }
+
+        pub fn insert(&self, path: &str, content: Vec<u8>, modified: SystemTime) {
+            self.files
+                .lock()
+                .unwrap()
+                .insert(path.to_string(), (content, modified));
+        }
+
+        pub fn put_recent(&self, path: &str) {
+            self.insert(path, path.as_bytes().to_vec(), SystemTime::now());
+        }
+
+        pub fn paths(&self) -> Vec<String> {
+            self.files.lock().unwrap().keys().cloned().collect()
+        }
+    }
+
+    #[async_trait]
+    impl MediaStore for MemoryStore {
+        async fn put(&self, path: &str, content: Vec<u8>, _: &str) -> Result<(), String> {
+            if self.failing.contains(path) {
+                return Err("The store is full.".to_string());
+            }
+            self.insert(path, content, UNIX_EPOCH);
+            Ok(())
+        }
+
+        async fn get(&self, path: &str) -> Result<Option<Vec<u8>>, String> {
+            let files = self.files.lock().unwrap();
+            Ok(files.get(path).map(|(content, _)| content.clone()))
+        }
+
+        async fn exists(&self, path: &str) -> Result<bool, String> {
+            Ok(self.files.lock().unwrap().contains_key(path))
+        }
+
+        async fn delete(&self, path: &str) -> Result<(), String> {
+            match self.files.lock().unwrap().remove(path) {
+                Some(_) => Ok(()),
+                None => Err("The file doesn't exist.".to_string()),
+            }
+        }
+
+        async fn list(&self) -> Result<Vec<MediaObject>, String> {
+            let files = self.files.lock().unwrap();
+            Ok(files
+                .iter()
+                .filter(|(path, _)| !path.starts_with(S3_QUARANTINE_PREFIX))
+                .map(|(path, (_, modified))| MediaObject {
+                    path: path.clone(),
+                    modified: Some(*modified),
+                })
+                .collect())
+        }
+
+        async fn quarantine(&self, path: &str, run: &str) -> Result<(), String> {
+            let mut files = self.files.lock().unwrap();
+            let file = files
+                .remove(path)
+                .ok_or_else(|| "The file doesn't exist.".to_string())?;
+            files.insert(format!("{}{}/{}", S3_QUARANTINE_PREFIX, run, path), file);
+            Ok(())
+        }
+
+        async fn download(&self, path: &str) -> Result<Option<Download>, String> {
+            if !self.exists(path).await? {
+                return Ok(None);
+            }
+            match self.presigned {
+                true => Ok(Some(Download::Redirect(format!(
+                    "https://bucket.example/{}",
+                    path
+                )))),
+                false => Ok(self.get(path).await?.map(Download::Bytes)),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn copies_missing_files() {
+        let from = MemoryStore::with_files(&["data/uploads/ab/a.png", "data/uploads/cd/b.png"]);
+        let to = MemoryStore::default();
+
+        let report = copy_media(&from, &to).await.unwrap();
+        assert_eq!(
+            report,
+            CopyReport {
+                copied: 2,
+                ..Default::default()
+            }
+        );
+        assert_eq!(to.paths(), from.paths());
+        assert_eq!(
+            to.get("data/uploads/ab/a.png").await,
+            Ok(Some(b"data/uploads/ab/a.png".to_vec()))
+        );
+    }
+
+    #[tokio::test]
+    async fn skips_files_already_copied() {
+        let from = MemoryStore::with_files(&[
+            "data/uploads/ab/a.png",
+            "data/uploads/cd/b.png",
+            "data/uploads/cd/c.png.0123456789abcdef.partial",
+        ]);
+        let to = MemoryStore::default();
+        to.insert("data/uploads/ab/a.png", b"there".to_vec(), UNIX_EPOCH);
+
+        let report = copy_media(&from, &to).await.unwrap();
+        assert_eq!(
+            report,
+            CopyReport {
+                copied: 1,
+                skipped: 1,
+                ..Default::default()
+            }
+        );
+        // Skipped files are kept as they were, and half written ones aren't copied
+        assert_eq!(
+            to.get("data/uploads/ab/a.png").await,
+            Ok(Some(b"there".to_vec()))
+        );
+        assert_eq!(
+            to.paths(),
+            vec!["data/uploads/ab/a.png", "data/uploads/cd/b.png"]
+        );
+
+        // Running again copies nothing
+        let report = copy_media(&from, &to).await.unwrap();
+        assert_eq!((report.copied, report.skipped), (0, 2));
+    }
+
+    #[tokio::test]
+    async fn reports_files_that_cant_be_copied() {
+        let from = MemoryStore::with_files(&["data/uploads/ab/a.png", "data/uploads/cd/b.png"]);
+        let to = MemoryStore {
+            failing: HashSet::from(["data/uploads/ab/a.png".to_string()]),
+            ..Default::default()
+        };
+
+        let report = copy_media(&from, &to).await.unwrap();
+        assert_eq!(report.copied, 1);
+        assert_eq!(
+            report.failures,
+            vec!["data/uploads/ab/a.png: The store is full."]
+        );
+        assert_eq!(to.paths(), vec!["data/uploads/cd/b.png"]);
+    }
+}
@@ -0,0 +1,781 @@
+//! ## Metadata
+//! Photos carry EXIF, XMP and IPTC metadata, with GPS coordinates, camera serials and the like,
+//! that must not reach visitors. Uploads are cleaned before they are saved: metadata segments and
+//! chunks are dropped from the file, leaving the pixels untouched. Photos that are rotated with
+//! the EXIF orientation get it applied to their pixels instead, and are encoded again in their own
+//! format, JPEGs with about the quality they had. Animated images and lossy WebPs can't be encoded
+//! again without losing frames or quality, so they keep their pixels and lose the orientation.
+//!
+//! Only a safe subset is kept, in [`SafeMetadata`], to be saved on `image_metadata`. SVGs are
+//! drawings, not photos, and are saved as they are.
+
+use super::mime::ImageType;
+use chrono::NaiveDateTime;
+use exif::{In, Reader, Tag, Value};
+use image::{
+    codecs::{gif::GifEncoder, jpeg::JpegEncoder, png::PngEncoder, webp::WebPEncoder},
+    metadata::Orientation,
+    DynamicImage, Frame, ImageEncoder, ImageReader,
+};
+use std::io::Cursor;
+
+/// The quality photos are saved with when they have to be encoded again, and their own can't be
+/// told.
+const JPEG_QUALITY: u8 = 90;
+
+/// The luminance quantization table of the JPEG standard, the one encoders scale by quality.
+const JPEG_LUMINANCE: [u16; 64] = [
+    16, 11, 10, 16, 24, 40, 51, 61, 12, 12, 14, 19, 26, 58, 60, 55, 14, 13, 16, 24, 40, 57, 69, 56,
+    14, 17, 22, 29, 51, 87, 80, 62, 18, 22, 37, 56, 68, 109, 103, 77, 24, 35, 55, 64, 81, 104, 113,
+    92, 49, 64, 78, 87, 103, 121, 120, 101, 72, 92, 95, 98, 112, 100, 103, 99,
+];
+
+/// The metadata kept from an upload.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SafeMetadata {
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub captured_at: Option<NaiveDateTime>,
+}
+
+/// Remove the metadata of an image and apply it's orientation. It's slow, so call it from a
+/// blocking task.
+pub fn sanitize(
+    content: Vec<u8>,
+    image_type: ImageType,
+) -> Result<(Vec<u8>, SafeMetadata), String> {
+    if image_type == ImageType::Svg {
+        return Ok((content, SafeMetadata::default()));
+    }
+
+    let exif = Reader::new()
+        .read_from_container(&mut Cursor::new(&content))
+        .ok();
+    let captured_at = exif.as_ref().and_then(capture_date);
+    let orientation = exif
+        .as_ref()
+        .and_then(|exif| exif.get_field(Tag::Orientation, In::PRIMARY))
+        .and_then(|field| field.value.get_uint(0))
+        .and_then(|value| Orientation::from_exif(value as u8))
+        .filter(|orientation| *orientation != Orientation::NoTransforms);
+
+    let content = match orientation {
+        // Encoding again leaves every metadata behind.
+        Some(orientation) if can_encode_again(&content, image_type) => {
+            let quality = jpeg_quality(&content).unwrap_or(JPEG_QUALITY);
+            let mut image = super::variants::decode(&content, image_type)?;
+            image.apply_orientation(orientation);
+            encode(&image, image_type, quality)?
+        }
+        _ => match image_type {
+            ImageType::Jpeg => strip_jpeg(&content)?,
+            ImageType::Png => strip_png(&content)?,
+            ImageType::Gif => strip_gif(&content)?,
+            ImageType::Webp => strip_webp(&content)?,
+            ImageType::Svg => content,
+        },
+    };
+
+    let (width, height) = ImageReader::new(Cursor::new(&content))
+        .with_guessed_format()
+        .map_err(|err| err.to_string())?
+        .into_dimensions()
+        .map_err(|err| err.to_string())?;
+
+    Ok((
+        content,
+        SafeMetadata {
+            width: i32::try_from(width).ok(),
+            height: i32::try_from(height).ok(),
+            captured_at,
+        },
+    ))
+}
+
+fn capture_date(exif: &exif::Exif) -> Option<NaiveDateTime> {
+    [Tag::DateTimeOriginal, Tag::DateTimeDigitized, Tag::DateTime]
+        .into_iter()
+        .filter_map(|tag| exif.get_field(tag, In::PRIMARY))
+        .find_map(|field| match field.value {
+            Value::Ascii(ref values) => values.first().and_then(|value| {
+                let date = exif::DateTime::from_ascii(value).ok()?;
+                chrono::NaiveDate::from_ymd_opt(
+                    date.year as i32,
+                    date.month as u32,
+                    date.day as u32,
+                )?
+                .and_hms_opt(
+                    date.hour as u32,
+                    date.minute as u32,
+                    date.second as u32,
+                )
+            }),
+            _ => None,
+        })
+}
+
+/// Whether the image can be encoded again by [`encode`] without losing frames or quality.
+fn can_encode_again(content: &[u8], image_type: ImageType) -> bool {
+    match image_type {
+        ImageType::Jpeg | ImageType::Png => true,
+        ImageType::Gif => gif_frames(content).is_ok_and(|frames| frames <= 1),
+        // Only lossless WebPs can be encoded
+        ImageType::Webp => webp_chunks(content)
+            .is_ok_and(|chunks| chunks.contains(b"VP8L") && !chunks.contains(b"ANIM")),
+        ImageType::Svg => false,
+    }
+}
+
+/// Estimate the quality a JPEG was saved with, comparing it's luminance table to the standard
+/// one, the way libjpeg scales it.
+fn jpeg_quality(content: &[u8]) -> Option<u8> {
+    let mut position = 2;
+    while *content.get(position)? == 0xFF {
+        let marker = *content.get(position + 1)?;
+        let length =
+            u16::from_be_bytes([*content.get(position + 2)?, *content.get(position + 3)?]) as usize;
+        // The scan comes after every table
+        if marker == 0xDA || length < 2 {
+            return None;
+        }
+        let mut table = content.get(position + 4..position + 2 + length)?;
+        position += 2 + length;
+
+        if marker != 0xDB {
+            continue;
+        }
+        while let Some((&info, rest)) = table.split_first() {
+            let wide = info >> 4 == 1;
+            let size = if wide { 128 } else { 64 };
+            let values = rest.get(..size)?;
+            table = &rest[size..];
+            if info & 0x0F != 0 {
+                continue;
+            }
+
+            let sum: u32 = if wide {
+                values
+                    .chunks(2)
+                    .map(|value| u16::from_be_bytes([value[0], value[1]]) as u32)
+                    .sum()
+            } else {
+                values.iter().map(|value| *value as u32).sum()
+            };
+            let scale = sum * 100
+                / JPEG_LUMINANCE
+                    .iter()
+                    .map(|value| *value as u32)
+                    .sum::<u32>();
+            let quality = match scale {
+                0 => 100,
+                1..=100 => (200 - scale) / 2,
+                _ => 5000 / scale,
+            };
+            return Some(quality.clamp(1, 100) as u8);
+        }
+    }
+    None
+}
+
+fn encode(
+    image: &DynamicImage,
+    image_type: ImageType,
+    jpeg_quality: u8,
+) -> Result<Vec<u8>, String> {
+    let mut encoded = Cursor::new(vec![]);
+
+    match image_type {
+        ImageType::Jpeg => {
+            let image = image.to_rgb8();
+            JpegEncoder::new_with_quality(&mut encoded, jpeg_quality).write_image(
+                &image,
+                image.width(),
+                image.height(),
+                image::ExtendedColorType::Rgb8,
+            )
+        }
+        ImageType::Webp => {
+            let image = image.to_rgba8();
+            WebPEncoder::new_lossless(&mut encoded).write_image(
+                &image,
+                image.width(),
+                image.height(),
+                image::ExtendedColorType::Rgba8,
+            )
+        }
+        ImageType::Gif => GifEncoder::new(&mut encoded).encode_frame(Frame::new(image.to_rgba8())),
+        _ => {
+            let image = image.to_rgba8();
+            PngEncoder::new(&mut encoded).write_image(
+                &image,
+                image.width(),
+                image.height(),
+                image::ExtendedColorType::Rgba8,
+            )
+        }
+    }
+    .map_err(|err| err.to_string())?;
+
+    Ok(encoded.into_inner())
+}
+
+fn truncated() -> String {
+    "The image is truncated.".to_string()
+}
+
+/// Keep only the segments needed to show a JPEG: JFIF, the ICC color profile and Adobe's color
+/// transform. EXIF and XMP live in APP1, IPTC in APP13, and comments may hold anything. Whatever
+/// comes after the end of the image, like the extra pictures of MPF, is dropped too.
+fn strip_jpeg(content: &[u8]) -> Result<Vec<u8>, String> {
+    if !content.starts_with(&[0xFF, 0xD8]) {
+        return Err("This is not a JPEG image.".to_string());
+    }
+
+    let mut stripped = vec![0xFF, 0xD8];
+    let mut position = 2;
+
+    loop {
+        if content.get(position) != Some(&0xFF) {
+            return Err("The JPEG image is corrupted.".to_string());
+        }
+        // Markers may be padded with any number of 0xFF.
+        while content.get(position) == Some(&0xFF) {
+            position += 1;
+        }
+        let marker = *content.get(position).ok_or_else(truncated)?;
+        position += 1;
+
+        match marker {
+            0xD9 => {
+                stripped.extend_from_slice(&[0xFF, 0xD9]);
+                return Ok(stripped);
+            }
+            0x01 | 0xD0..=0xD7 => {
+                stripped.extend_from_slice(&[0xFF, marker]);
+                continue;
+            }
+            _ => {}
+        }
+
+        let length = content
+            .get(position..position + 2)
+            .map(|length| u16::from_be_bytes([length[0], length[1]]) as usize)
+            .ok_or_else(truncated)?;
+        if length < 2 {
+            return Err("The JPEG image is corrupted.".to_string());
+        }
+        let segment = content
+            .get(position..position + length)
+            .ok_or_else(truncated)?;
+        position += length;
+
+        let keep = match marker {
+            0xE0 | 0xEE => true,
+            0xE2 => segment[2..].starts_with(b"ICC_PROFILE\0"),
+            0xE1 | 0xE3..=0xED | 0xEF | 0xFE => false,
+            _ => true,
+        };
+        if keep {
+            stripped.extend_from_slice(&[0xFF, marker]);
+            stripped.extend_from_slice(segment);
+        }
+
+        // The scan is followed by entropy coded data, that only has 0xFF before a zero byte or a
+        // restart marker, until the next marker.
+        if marker == 0xDA {
+            let start = position;
+            while position + 1 < content.len() {
+                if content[position] == 0xFF
+                    && content[position + 1] != 0x00
+                    && !(0xD0..=0xD7).contains(&content[position + 1])
+                {
+                    break;
+                }
+                position += 1;
+            }
+            if position + 1 >= content.len() {
+                return Err(truncated());
+            }
+            stripped.extend_from_slice(&content[start..position]);
+        }
+    }
+}
+
+/// Drop the text, time and EXIF chunks of a PNG. XMP is stored in an `iTXt` chunk.
+fn strip_png(content: &[u8]) -> Result<Vec<u8>, String> {
+    const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+    if !content.starts_with(SIGNATURE) {
+        return Err("This is not a PNG image.".to_string());
+    }
+
+    let mut stripped = SIGNATURE.to_vec();
+    let mut position = SIGNATURE.len();
+
+    loop {
+        let header = content.get(position..position + 8).ok_or_else(truncated)?;
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let chunk_type = &header[4..8];
+        // Length, type, data and CRC.
+        let chunk = content
+            .get(position..position + 12 + length)
+            .ok_or_else(truncated)?;
+        position += chunk.len();
+
+        if !matches!(chunk_type, b"tEXt" | b"zTXt" | b"iTXt" | b"eXIf" | b"tIME") {
+            stripped.extend_from_slice(chunk);
+        }
+
+        if chunk_type == b"IEND" {
+            return Ok(stripped);
+        }
+    }
+}
+
+/// Drop the `EXIF` and `XMP ` chunks of a WebP, and their flags on the extended header.
+fn strip_webp(content: &[u8]) -> Result<Vec<u8>, String> {
+    if content.len() < 12 || &content[..4] != b"RIFF" || &content[8..12] != b"WEBP" {
+        return Err("This is not a WebP image.".to_string());
+    }
+
+    // Anything after the RIFF container isn't part of the image.
+    let riff_size = u32::from_le_bytes([content[4], content[5], content[6], content[7]]) as usize;
+    let content = content.get(..8 + riff_size).ok_or_else(truncated)?;
+
+    let mut chunks = b"WEBP".to_vec();
+    let mut position = 12;
+
+    while position < content.len() {
+        let header = content.get(position..position + 8).ok_or_else(truncated)?;
+        let fourcc = &header[..4];
+        let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        // Chunks are padded to an even size.
+        let padded_size = size + size % 2;
+        let chunk = content
+            .get(position..(position + 8 + padded_size).min(content.len()))
+            .ok_or_else(truncated)?;
+        if chunk.len() < 8 + size {
+            return Err(truncated());
+        }
+        position += 8 + padded_size;
+
+        match fourcc {
+            b"EXIF" | b"XMP " => {}
+            b"VP8X" => {
+                let mut chunk = chunk.to_vec();
+                if let Some(flags) = chunk.get_mut(8) {
+                    *flags &= !(0x08 | 0x04);
+                }
+                chunks.extend_from_slice(&chunk);
+            }
+            _ => chunks.extend_from_slice(chunk),
+        }
+    }
+
+    let mut stripped = b"RIFF".to_vec();
+    stripped.extend_from_slice(&(chunks.len() as u32).to_le_bytes());
+    stripped.extend_from_slice(&chunks);
+    Ok(stripped)
+}
+
+/// Drop the comment and application extensions of a GIF, which is where XMP goes, keeping the
+/// ones that make animations loop.
+fn strip_gif(content: &[u8]) -> Result<Vec<u8>, String> {
+    // The header and the logical screen descriptor, followed by the global color table.
+    let screen = content.get(..13).ok_or_else(truncated)?;
+    let mut position = 13 + color_table_size(screen[10]);
+    let mut stripped = content.get(..position).ok_or_else(truncated)?.to_vec();
+
+    loop {
+        match *content.get(position).ok_or_else(truncated)? {
+            0x3B => {
+                stripped.push(0x3B);
+                return Ok(stripped);
+            }
+            0x21 => {
+                let label = *content.get(position + 1).ok_or_else(truncated)?;
+                let end = sub_blocks_end(content, position + 2)?;
+                let keep = match label {
+                    0xFE => false,
+                    0xFF => {
+                        let identifier = content
+                            .get(position + 3..position + 14)
+                            .ok_or_else(truncated)?;
+                        identifier == b"NETSCAPE2.0" || identifier == b"ANIMEXTS1.0"
+                    }
+                    _ => true,
+                };
+                if keep {
+                    stripped.extend_from_slice(&content[position..end]);
+                }
+                position = end;
+            }
+            0x2C => {
+                let descriptor = content.get(position..position + 10).ok_or_else(truncated)?;
+                // The descriptor, the local color table and the LZW code size.
+                let data_start = position + 10 + color_table_size(descriptor[9]) + 1;
+                let end = sub_blocks_end(content, data_start)?;
+                stripped.extend_from_slice(&content[position..end]);
+                position = end;
+            }
+            _ => return Err("The GIF image is corrupted.".to_string()),
+        }
+    }
+}
+
+/// How many frames a GIF has.
+fn gif_frames(content: &[u8]) -> Result<usize, String> {
+    let screen = content.get(..13).ok_or_else(truncated)?;
+    let mut position = 13 + color_table_size(screen[10]);
+    let mut frames = 0;
+
+    loop {
+        match *content.get(position).ok_or_else(truncated)? {
+            0x3B => return Ok(frames),
+            0x21 => position = sub_blocks_end(content, position + 2)?,
+            0x2C => {
+                let descriptor = content.get(position..position + 10).ok_or_else(truncated)?;
+                let data_start = position + 10 + color_table_size(descriptor[9]) + 1;
+                position = sub_blocks_end(content, data_start)?;
+                frames += 1;
+            }
+            _ => return Err("The GIF image is corrupted.".to_string()),
+        }
+    }
+}
+
+/// The FourCC of every chunk of a WebP.
+fn webp_chunks(content: &[u8]) -> Result<Vec<[u8; 4]>, String> {
+    if content.len() < 12 || &content[..4] != b"RIFF" || &content[8..12] != b"WEBP" {
+        return Err("This is not a WebP image.".to_string());
+    }
+
+    let mut chunks = vec![];
+    let mut position = 12;
+    while let Some(header) = content.get(position..position + 8) {
+        chunks.push([header[0], header[1], header[2], header[3]]);
+        let size = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        position += 8 + size + size % 2;
+    }
+
+    Ok(chunks)
+}
+
+fn color_table_size(packed: u8) -> usize {
+    if packed & 0x80 == 0 {
+        0
+    } else {
+        3 * (1 << ((packed & 0x07) + 1))
+    }
+}
+
+/// Where the sub-blocks starting at `position` end, after their zero sized terminator.
+fn sub_blocks_end(content: &[u8], mut position: usize) -> Result<usize, String> {
+    loop {
+        let size = *content.get(position).ok_or_else(truncated)? as usize;
+        position += 1 + size;
+        if size == 0 {
+            return Ok(position);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbaImage;
+
+    const SECRET: &[u8] = b"GPS 12.3456 SECRET";
+
+    fn picture() -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(6, 4, |x, y| {
+            image::Rgba([(x * 40) as u8, (y * 60) as u8, 128, 255])
+        }))
+    }
+
+    fn contains(content: &[u8], needle: &[u8]) -> bool {
+        content.windows(needle.len()).any(|window| window == needle)
+    }
+
+    /// The image decodes to the same size, so nothing needed to show it was dropped.
+    fn assert_round_trip(stripped: &[u8]) {
+        let image = image::load_from_memory(stripped).unwrap();
+        assert_eq!((image.width(), image.height()), (6, 4));
+        assert!(!contains(stripped, SECRET));
+    }
+
+    /// Every cut of a valid image is refused without panicking.
+    fn assert_refuses_truncated(content: &[u8], strip: fn(&[u8]) -> Result<Vec<u8>, String>) {
+        for end in 0..content.len() {
+            assert!(
+                strip(&content[..end]).is_err(),
+                "cut at {} was accepted",
+                end
+            );
+        }
+    }
+
+    fn jpeg() -> Vec<u8> {
+        let encoded = encode(&picture(), ImageType::Jpeg, JPEG_QUALITY).unwrap();
+
+        let mut exif = b"Exif\0\0".to_vec();
+        exif.extend_from_slice(SECRET);
+        let mut content = vec![0xFF, 0xD8];
+        for (marker, payload) in [(0xE1, exif.as_slice()), (0xFE, SECRET), (0xED, SECRET)] {
+            content.extend_from_slice(&[0xFF, marker]);
+            content.extend_from_slice(&((payload.len() + 2) as u16).to_be_bytes());
+            content.extend_from_slice(payload);
+        }
+        content.extend_from_slice(&encoded[2..]);
+        // Like the extra pictures of MPF
+        content.extend_from_slice(SECRET);
+        content
+    }
+
+    fn png() -> Vec<u8> {
+        let encoded = encode(&picture(), ImageType::Png, JPEG_QUALITY).unwrap();
+        // Before `IEND`, the last 12 bytes
+        let (image, end) = encoded.split_at(encoded.len() - 12);
+
+        let mut content = image.to_vec();
+        for chunk_type in [b"tEXt", b"iTXt", b"eXIf"] {
+            content.extend_from_slice(&(SECRET.len() as u32).to_be_bytes());
+            content.extend_from_slice(chunk_type);
+            content.extend_from_slice(SECRET);
+            content.extend_from_slice(&[0; 4]);
+        }
+        content.extend_from_slice(end);
+        content
+    }
+
+    fn webp() -> Vec<u8> {
+        webp_with_exif(SECRET)
+    }
+
+    fn webp_with_exif(exif: &[u8]) -> Vec<u8> {
+        let encoded = encode(&picture(), ImageType::Webp, JPEG_QUALITY).unwrap();
+
+        // The extended header, with the EXIF and XMP flags, and the canvas size minus one
+        let mut chunks = b"WEBPVP8X".to_vec();
+        chunks.extend_from_slice(&10u32.to_le_bytes());
+        chunks.extend_from_slice(&[0x08 | 0x04, 0, 0, 0, 5, 0, 0, 3, 0, 0]);
+        chunks.extend_from_slice(&encoded[12..]);
+        for (fourcc, payload) in [(b"EXIF", exif), (b"XMP ", SECRET)] {
+            chunks.extend_from_slice(fourcc);
+            chunks.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+            chunks.extend_from_slice(payload);
+        }
+
+        let mut content = b"RIFF".to_vec();
+        content.extend_from_slice(&(chunks.len() as u32).to_le_bytes());
+        content.extend_from_slice(&chunks);
+        content
+    }
+
+    fn gif() -> Vec<u8> {
+        let mut encoded = vec![];
+        GifEncoder::new(&mut encoded)
+            .encode_frame(Frame::new(picture().to_rgba8()))
+            .unwrap();
+        let screen_end = 13 + color_table_size(encoded[10]);
+
+        let mut content = encoded[..screen_end].to_vec();
+        content.extend_from_slice(&[0x21, 0xFE, SECRET.len() as u8]);
+        content.extend_from_slice(SECRET);
+        content.push(0);
+        content.extend_from_slice(b"\x21\xFF\x0BXMP DataXMP");
+        content.push(SECRET.len() as u8);
+        content.extend_from_slice(SECRET);
+        content.push(0);
+        content.extend_from_slice(&encoded[screen_end..]);
+        content
+    }
+
+    #[test]
+    fn strips_jpeg_metadata() {
+        let content = jpeg();
+        let stripped = strip_jpeg(&content).unwrap();
+        assert_round_trip(&stripped);
+        assert!(stripped.ends_with(&[0xFF, 0xD9]));
+        assert_refuses_truncated(&stripped, strip_jpeg);
+    }
+
+    #[test]
+    fn strips_png_metadata() {
+        let content = png();
+        assert!(contains(&content, SECRET));
+        let stripped = strip_png(&content).unwrap();
+        assert_round_trip(&stripped);
+        assert_refuses_truncated(&stripped, strip_png);
+    }
+
+    #[test]
+    fn strips_webp_metadata() {
+        let content = webp();
+        assert!(image::load_from_memory(&content).is_ok());
+        let stripped = strip_webp(&content).unwrap();
+        assert_round_trip(&stripped);
+        assert_eq!(stripped[20] & (0x08 | 0x04), 0);
+        assert_eq!(
+            u32::from_le_bytes([stripped[4], stripped[5], stripped[6], stripped[7]]) as usize,
+            stripped.len() - 8
+        );
+        assert_refuses_truncated(&stripped, strip_webp);
+    }
+
+    #[test]
+    fn strips_gif_metadata() {
+        let content = gif();
+        assert!(image::load_from_memory(&content).is_ok());
+        let stripped = strip_gif(&content).unwrap();
+        assert_round_trip(&stripped);
+        assert_refuses_truncated(&stripped, strip_gif);
+    }
+
+    #[test]
+    fn refuses_lengths_past_the_end() {
+        let mut content = strip_jpeg(&jpeg()).unwrap();
+        // The first segment after SOI claims the whole length range
+        content[4..6].copy_from_slice(&[0xFF, 0xFF]);
+        assert!(strip_jpeg(&content).is_err());
+        content[4..6].copy_from_slice(&[0x00, 0x01]);
+        assert!(strip_jpeg(&content).is_err());
+
+        let mut content = strip_png(&png()).unwrap();
+        content[8..12].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(strip_png(&content).is_err());
+
+        let mut content = strip_webp(&webp()).unwrap();
+        content[16..20].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(strip_webp(&content).is_err());
+        let mut content = strip_webp(&webp()).unwrap();
+        content[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(strip_webp(&content).is_err());
+
+        let mut content = gif();
+        let screen_end = 13 + color_table_size(content[10]);
+        content[screen_end + 2] = 0xFF;
+        assert!(strip_gif(&content).is_err());
+    }
+
+    #[test]
+    fn refuses_other_formats() {
+        let content = png();
+        assert!(strip_jpeg(&content).is_err());
+        assert!(strip_webp(&content).is_err());
+        assert!(strip_gif(&content).is_err());
+        assert!(strip_png(&jpeg()).is_err());
+    }
+
+    /// A TIFF header with a single entry, the orientation, as EXIF is stored.
+    fn orientation_exif(orientation: u16) -> Vec<u8> {
+        let mut tiff = b"MM\0\x2A\0\0\0\x08\0\x01".to_vec();
+        tiff.extend_from_slice(&[0x01, 0x12, 0, 3, 0, 0, 0, 1]);
+        tiff.extend_from_slice(&orientation.to_be_bytes());
+        tiff.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+        tiff
+    }
+
+    fn oriented_jpeg(quality: u8) -> Vec<u8> {
+        let encoded = encode(&picture(), ImageType::Jpeg, quality).unwrap();
+        let mut exif = b"Exif\0\0".to_vec();
+        exif.extend_from_slice(&orientation_exif(6));
+        exif.extend_from_slice(SECRET);
+
+        let mut content = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        content.extend_from_slice(&((exif.len() + 2) as u16).to_be_bytes());
+        content.extend_from_slice(&exif);
+        content.extend_from_slice(&encoded[2..]);
+        content
+    }
+
+    fn with_webp_chunk(content: &[u8], fourcc: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut chunks = content[8..].to_vec();
+        chunks.extend_from_slice(fourcc);
+        chunks.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        chunks.extend_from_slice(payload);
+        if payload.len() % 2 == 1 {
+            chunks.push(0);
+        }
+
+        let mut content = b"RIFF".to_vec();
+        content.extend_from_slice(&(chunks.len() as u32).to_le_bytes());
+        content.extend_from_slice(&chunks);
+        content
+    }
+
+    fn animated_gif() -> Vec<u8> {
+        let mut encoded = vec![];
+        {
+            let mut encoder = GifEncoder::new(&mut encoded);
+            encoder
+                .encode_frame(Frame::new(picture().to_rgba8()))
+                .unwrap();
+            encoder
+                .encode_frame(Frame::new(picture().fliph().to_rgba8()))
+                .unwrap();
+        }
+        encoded
+    }
+
+    #[test]
+    fn oriented_jpegs_keep_their_format_and_quality() {
+        for quality in [60, 95] {
+            let content = oriented_jpeg(quality);
+            assert_eq!(jpeg_quality(&content), Some(quality));
+
+            let (sanitized, metadata) = sanitize(content, ImageType::Jpeg).unwrap();
+            assert_eq!((metadata.width, metadata.height), (Some(4), Some(6)));
+            assert!(sanitized.starts_with(&[0xFF, 0xD8]));
+            assert!(!contains(&sanitized, SECRET));
+            assert!(!contains(&sanitized, b"Exif"));
+            assert!(
+                jpeg_quality(&sanitized).is_some_and(|estimated| estimated.abs_diff(quality) <= 1)
+            );
+        }
+    }
+
+    #[test]
+    fn oriented_lossless_webps_are_rotated() {
+        let mut exif = orientation_exif(6);
+        exif.extend_from_slice(SECRET);
+        let content = webp_with_exif(&exif);
+
+        let (sanitized, metadata) = sanitize(content, ImageType::Webp).unwrap();
+        assert_eq!((metadata.width, metadata.height), (Some(4), Some(6)));
+        assert_eq!(&sanitized[8..12], b"WEBP");
+        assert!(!contains(&sanitized, SECRET));
+    }
+
+    #[test]
+    fn animated_and_lossy_images_are_not_encoded_again() {
+        let gif = animated_gif();
+        assert_eq!(gif_frames(&gif), Ok(2));
+        assert!(!can_encode_again(&gif, ImageType::Gif));
+        assert!(can_encode_again(&self::gif(), ImageType::Gif));
+
+        let lossless = webp();
+        assert!(can_encode_again(&lossless, ImageType::Webp));
+        assert!(!can_encode_again(
+            &with_webp_chunk(&lossless, b"ANIM", &[0; 6]),
+            ImageType::Webp
+        ));
+        let mut lossy = lossless.clone();
+        let vp8l = lossy
+            .windows(4)
+            .position(|window| window == b"VP8L")
+            .unwrap();
+        lossy[vp8l..vp8l + 4].copy_from_slice(b"VP8 ");
+        assert!(!can_encode_again(&lossy, ImageType::Webp));
+
+        // Their frames are kept as they are
+        let (sanitized, _) = sanitize(gif.clone(), ImageType::Gif).unwrap();
+        assert_eq!(sanitized, strip_gif(&gif).unwrap());
+        assert_eq!(gif_frames(&sanitized), Ok(2));
+    }
+
+    #[test]
+    fn oriented_gifs_stay_gifs() {
+        let encoded = encode(&picture(), ImageType::Gif, JPEG_QUALITY).unwrap();
+        assert!(encoded.starts_with(b"GIF8"));
+    }
+}
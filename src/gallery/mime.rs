@@ -0,0 +1,486 @@
+//! ## Image types
+//! The type of an upload is taken from it's content, never from the name or the `Content-Type`
+//! the browser sent. Only the types below are accepted.
+
+/// The image types accepted by the gallery.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageType {
+    Png,
+    Jpeg,
+    Gif,
+    Webp,
+    Svg,
+}
+
+impl ImageType {
+    pub const ALL: [ImageType; 5] = [
+        ImageType::Png,
+        ImageType::Jpeg,
+        ImageType::Gif,
+        ImageType::Webp,
+        ImageType::Svg,
+    ];
+
+    /// Find the type of an image from it's magic bytes.
+    pub fn sniff(content: &[u8]) -> Option<ImageType> {
+        if content.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(ImageType::Png)
+        } else if content.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some(ImageType::Jpeg)
+        } else if content.starts_with(b"GIF87a") || content.starts_with(b"GIF89a") {
+            Some(ImageType::Gif)
+        } else if content.len() >= 12 && &content[..4] == b"RIFF" && &content[8..12] == b"WEBP" {
+            Some(ImageType::Webp)
+        } else if is_svg(content) {
+            Some(ImageType::Svg)
+        } else {
+            None
+        }
+    }
+
+    pub fn from_mime_type(mime_type: &str) -> Option<ImageType> {
+        Self::ALL
+            .into_iter()
+            .find(|image_type| image_type.mime_type() == mime_type)
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ImageType::Png => "image/png",
+            ImageType::Jpeg => "image/jpeg",
+            ImageType::Gif => "image/gif",
+            ImageType::Webp => "image/webp",
+            ImageType::Svg => "image/svg+xml",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageType::Png => "png",
+            ImageType::Jpeg => "jpg",
+            ImageType::Gif => "gif",
+            ImageType::Webp => "webp",
+            ImageType::Svg => "svg",
+        }
+    }
+}
+
+/// SVGs are text, so skip a byte order mark, the XML declaration, comments and the doctype
+/// looking for the `<svg` root.
+fn is_svg(content: &[u8]) -> bool {
+    let text = match std::str::from_utf8(&content[..content.len().min(4096)]) {
+        Ok(text) => text,
+        // The cut may have split a character in half.
+        Err(err) => match std::str::from_utf8(&content[..err.valid_up_to()]) {
+            Ok(text) => text,
+            Err(_) => return false,
+        },
+    };
+
+    let mut rest = text.trim_start_matches('\u{FEFF}').trim_start();
+    loop {
+        if rest.starts_with("<?") {
+            rest = match rest.find("?>") {
+                Some(end) => rest[end + 2..].trim_start(),
+                None => return false,
+            };
+        } else if rest.starts_with("<!--") {
+            rest = match rest.find("-->") {
+                Some(end) => rest[end + 3..].trim_start(),
+                None => return false,
+            };
+        } else if rest.starts_with("<!") {
+            rest = match rest.find('>') {
+                Some(end) => rest[end + 1..].trim_start(),
+                None => return false,
+            };
+        } else {
+            return rest.starts_with("<svg")
+                && rest[4..].starts_with(|c: char| c.is_whitespace() || c == '>');
+        }
+    }
+}
+
+const SVG_NAMESPACE: &[u8] = b"http://www.w3.org/2000/svg";
+const XLINK_NAMESPACE: &[u8] = b"http://www.w3.org/1999/xlink";
+const XML_NAMESPACE: &[u8] = b"http://www.w3.org/XML/1998/namespace";
+
+/// The SVG elements kept, shapes, text, paint servers, filters and animations. Anything else in
+/// the SVG namespace, like `script` or `foreignObject`, is refused.
+const SVG_ELEMENTS: &[&str] = &[
+    "a",
+    "animate",
+    "animateMotion",
+    "animateTransform",
+    "circle",
+    "clipPath",
+    "defs",
+    "desc",
+    "ellipse",
+    "feBlend",
+    "feColorMatrix",
+    "feComponentTransfer",
+    "feComposite",
+    "feConvolveMatrix",
+    "feDiffuseLighting",
+    "feDisplacementMap",
+    "feDistantLight",
+    "feDropShadow",
+    "feFlood",
+    "feFuncA",
+    "feFuncB",
+    "feFuncG",
+    "feFuncR",
+    "feGaussianBlur",
+    "feImage",
+    "feMerge",
+    "feMergeNode",
+    "feMorphology",
+    "feOffset",
+    "fePointLight",
+    "feSpecularLighting",
+    "feSpotLight",
+    "feTile",
+    "feTurbulence",
+    "filter",
+    "g",
+    "image",
+    "line",
+    "linearGradient",
+    "marker",
+    "mask",
+    "metadata",
+    "mpath",
+    "path",
+    "pattern",
+    "polygon",
+    "polyline",
+    "radialGradient",
+    "rect",
+    "set",
+    "stop",
+    "style",
+    "svg",
+    "switch",
+    "symbol",
+    "text",
+    "textPath",
+    "title",
+    "tspan",
+    "use",
+    "view",
+];
+
+/// Reject SVGs that could run code or load other resources. The SVG is parsed, so entities and
+/// odd spacing can't hide anything: elements in the SVG namespace must be in [`SVG_ELEMENTS`],
+/// HTML and MathML can't be embedded, event handlers are refused on any element, and links and
+/// `url()`s may only point inside the image, except for `<a>` links to other pages. Elements of
+/// other namespaces, like the metadata editors leave, are kept, browsers ignore them.
+///
+/// They are also served with a sandboxing `Content-Security-Policy`, in case anything gets
+/// through.
+pub fn validate_svg(content: &[u8]) -> Result<(), String> {
+    use quick_xml::events::Event;
+    use quick_xml::name::ResolveResult;
+    use quick_xml::NsReader;
+
+    let text = std::str::from_utf8(content).map_err(|_| "SVG images must be UTF-8.".to_string())?;
+    let unreadable = |err: quick_xml::Error| format!("This SVG image could not be read: {}", err);
+
+    let mut reader = NsReader::from_str(text);
+    reader.check_end_names(true);
+
+    // How deep inside `<style>` elements we are, their text is CSS.
+    let mut style_depth = 0usize;
+    let mut depth = 0usize;
+    let mut has_root = false;
+    loop {
+        let (namespace, event) = reader.read_resolved_event().map_err(unreadable)?;
+        match event {
+            Event::Start(ref element) | Event::Empty(ref element) => {
+                let is_svg = match namespace {
+                    ResolveResult::Bound(namespace) if namespace.as_ref() == SVG_NAMESPACE => true,
+                    ResolveResult::Bound(namespace) if is_markup_namespace(namespace.as_ref()) => {
+                        return Err("SVG images can't embed HTML or MathML.".to_string())
+                    }
+                    ResolveResult::Unknown(_) => {
+                        return Err("This SVG image uses an undeclared prefix.".to_string())
+                    }
+                    _ => false,
+                };
+                if !is_xml_name(element.name().as_ref()) {
+                    return Err("This SVG image has an invalid element name.".to_string());
+                }
+                let local_name = element.local_name();
+                let local_name = std::str::from_utf8(local_name.as_ref()).unwrap_or_default();
+                if !has_root && !(is_svg && local_name == "svg") {
+                    return Err("SVG images must start with a <svg> element.".to_string());
+                }
+                has_root = true;
+
+                if is_svg && !SVG_ELEMENTS.contains(&local_name) {
+                    return Err(format!("SVG images can't have <{}> elements.", local_name));
+                }
+                validate_svg_attributes(&reader, element, is_svg.then_some(local_name))?;
+
+                if let Event::Start(_) = event {
+                    depth += 1;
+                    if is_svg && local_name == "style" {
+                        style_depth += 1;
+                    }
+                }
+            }
+            Event::End(ref element) => {
+                depth -= 1;
+                let is_svg = matches!(
+                    namespace,
+                    ResolveResult::Bound(namespace) if namespace.as_ref() == SVG_NAMESPACE
+                );
+                if is_svg && style_depth > 0 && element.local_name().as_ref() == b"style" {
+                    style_depth -= 1;
+                }
+            }
+            Event::Text(text) => {
+                let text = text.unescape().map_err(unreadable)?;
+                if style_depth > 0 {
+                    validate_css(&text)?;
+                }
+            }
+            Event::CData(text) if style_depth > 0 => {
+                validate_css(&String::from_utf8_lossy(&text.into_inner()))?;
+            }
+            // Entities can only be declared on the doctype
+            Event::DocType(doctype) if doctype.contains(&b'[') => {
+                return Err("SVG images can't declare entities.".to_string())
+            }
+            // Like `<?xml-stylesheet?>`, which loads a stylesheet
+            Event::PI(_) => {
+                return Err("SVG images can't have processing instructions.".to_string())
+            }
+            Event::Eof if depth > 0 => {
+                return Err("This SVG image is missing closing tags.".to_string())
+            }
+            Event::Eof => return Ok(()),
+            _ => {}
+        }
+    }
+}
+
+/// A loose check, ASCII names with the punctuation XML allows, which is enough for SVGs.
+fn is_xml_name(name: &[u8]) -> bool {
+    !name.is_empty()
+        && name
+            .iter()
+            .all(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'_' | b'-' | b'.' | b':'))
+}
+
+fn is_markup_namespace(namespace: &[u8]) -> bool {
+    namespace == b"http://www.w3.org/1999/xhtml"
+        || namespace == b"http://www.w3.org/1998/Math/MathML"
+}
+
+/// Check the attributes of an element, `svg_element` being it's name when it's in the SVG
+/// namespace.
+fn validate_svg_attributes(
+    reader: &quick_xml::NsReader<&[u8]>,
+    element: &quick_xml::events::BytesStart,
+    svg_element: Option<&str>,
+) -> Result<(), String> {
+    use quick_xml::name::ResolveResult;
+
+    for attribute in element.attributes() {
+        let attribute =
+            attribute.map_err(|err| format!("This SVG image could not be read: {}", err))?;
+        if !is_xml_name(attribute.key.as_ref()) {
+            return Err("This SVG image has an invalid attribute name.".to_string());
+        }
+        if attribute.key.as_namespace_binding().is_some() {
+            continue;
+        }
+
+        let (namespace, local_name) = reader.resolve_attribute(attribute.key);
+        let local_name = String::from_utf8_lossy(local_name.as_ref()).to_ascii_lowercase();
+        let value = attribute
+            .unescape_value()
+            .map_err(|err| format!("This SVG image could not be read: {}", err))?;
+        // Browsers ignore spaces and control characters inside URLs and schemes
+        let compact: String = value
+            .chars()
+            .filter(|c| !c.is_whitespace() && !c.is_control())
+            .collect::<String>()
+            .to_ascii_lowercase();
+
+        let namespace = match namespace {
+            ResolveResult::Bound(namespace) => Some(namespace.into_inner()),
+            ResolveResult::Unbound => None,
+            ResolveResult::Unknown(_) => {
+                return Err("This SVG image uses an undeclared prefix.".to_string())
+            }
+        };
+
+        if local_name.starts_with("on") {
+            return Err("SVG images can't have event handlers.".to_string());
+        }
+        if namespace == Some(XML_NAMESPACE) && local_name == "base" {
+            return Err("SVG images can't change their base URL.".to_string());
+        }
+        if local_name == "href" && (namespace.is_none() || namespace == Some(XLINK_NAMESPACE)) {
+            let is_link = svg_element == Some("a");
+            if !(compact.starts_with('#')
+                || (is_link
+                    && ["http:", "https:", "mailto:"]
+                        .iter()
+                        .any(|scheme| compact.starts_with(scheme))))
+            {
+                return Err("SVG images can only link inside themselves.".to_string());
+            }
+        }
+        // Animations could set a link or a handler after the checks above
+        if local_name == "attributename"
+            && matches!(svg_element, Some("animate" | "set"))
+            && (compact.ends_with("href") || compact.starts_with("on"))
+        {
+            return Err("SVG images can't animate links or event handlers.".to_string());
+        }
+        if local_name == "style" {
+            validate_css(&value)?;
+        } else if compact.contains("url(") {
+            validate_css(&compact)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Stylesheets can't import others, and `url()`s may only point inside the image.
+fn validate_css(css: &str) -> Result<(), String> {
+    let css: String = css
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_ascii_lowercase();
+
+    // CSS escapes could spell `url` or `@import` in other ways
+    if css.contains('\\') {
+        return Err("SVG styles can't have escapes.".to_string());
+    }
+    if css.contains("@import") {
+        return Err("SVG styles can't import other stylesheets.".to_string());
+    }
+    for (index, _) in css.match_indices("url(") {
+        let target = css[index + 4..].trim_start_matches(['"', '\'']);
+        if !target.starts_with('#') {
+            return Err("SVG images can't load other resources.".to_string());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn svg(body: &str) -> String {
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" xmlns:xlink=\"http://www.w3.org/1999/xlink\">{}</svg>",
+            body
+        )
+    }
+
+    fn refused(content: &str) -> bool {
+        validate_svg(content.as_bytes()).is_err()
+    }
+
+    #[test]
+    fn accepts_plain_drawings() {
+        for content in [
+            svg("<rect width=\"10\" height=\"10\" fill=\"url(#gradient)\"/>"),
+            svg("<defs><linearGradient id=\"gradient\"><stop offset=\"0\"/></linearGradient></defs>"),
+            svg("<style>rect { fill: url('#gradient'); }</style><use xlink:href=\"#shape\"/>"),
+            svg("<a href=\"https://example.com\"><text>Link &amp; text</text></a>"),
+            svg("<animate attributeName=\"opacity\" values=\"0;1\" dur=\"1s\"/>"),
+            // Editors leave their own metadata, which browsers ignore
+            "<?xml version=\"1.0\"?>\n<!DOCTYPE svg>\n<svg xmlns=\"http://www.w3.org/2000/svg\" \
+             xmlns:inkscape=\"http://www.inkscape.org/namespaces/inkscape\" inkscape:version=\"1.3\">\
+             <inkscape:grid/></svg>"
+                .to_string(),
+        ] {
+            assert_eq!(validate_svg(content.as_bytes()), Ok(()), "{}", content);
+        }
+    }
+
+    #[test]
+    fn refuses_scripts_and_handlers() {
+        assert!(refused(&svg("<script>alert(1)</script>")));
+        assert!(refused(&svg("<SCRIPT>alert(1)</SCRIPT>")));
+        assert!(refused("<svg/onload=alert(1)>"));
+        assert!(refused(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\"/onload=\"alert(1)\">"
+        ));
+        assert!(refused(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" onload=\"alert(1)\"/>"
+        ));
+        assert!(refused(&svg("<g\nONCLICK='alert(1)'/>")));
+        assert!(refused(&svg("<foreignObject><div/></foreignObject>")));
+        assert!(refused(&svg(
+            "<h:script xmlns:h=\"http://www.w3.org/1999/xhtml\">alert(1)</h:script>"
+        )));
+        assert!(refused(&svg(
+            "<x:svg xmlns:x=\"http://www.w3.org/2000/svg\"><x:script/></x:svg>"
+        )));
+        assert!(refused(&svg(
+            "<set attributeName=\"href\" to=\"javascript:alert(1)\"/>"
+        )));
+    }
+
+    #[test]
+    fn refuses_hidden_javascript_links() {
+        assert!(refused(&svg(
+            "<a href=\"javascript:alert(1)\"><text>x</text></a>"
+        )));
+        assert!(refused(&svg(
+            "<a href=\"&#106;avascript:alert(1)\"><text>x</text></a>"
+        )));
+        assert!(refused(&svg(
+            "<a href=\"java&#x09;script:alert(1)\"><text>x</text></a>"
+        )));
+        assert!(refused(&svg(
+            "<a xlink:href=\" JAVASCRIPT:alert(1)\"><text>x</text></a>"
+        )));
+    }
+
+    #[test]
+    fn refuses_outside_resources() {
+        assert!(refused(&svg(
+            "<image href=\"https://example.com/track.png\"/>"
+        )));
+        assert!(refused(&svg("<use xlink:href=\"other.svg#shape\"/>")));
+        assert!(refused(&svg(
+            "<rect fill=\"url(https://example.com/#x)\"/>"
+        )));
+        assert!(refused(&svg(
+            "<rect style=\"fill: u\\72l(https://example.com/)\"/>"
+        )));
+        assert!(refused(&svg(
+            "<style>@import 'https://example.com/x.css';</style>"
+        )));
+        assert!(refused(&svg(
+            "<style><![CDATA[rect { fill: url(//example.com) }]]></style>"
+        )));
+        assert!(refused(
+            "<?xml-stylesheet href=\"https://example.com/x.css\"?><svg xmlns=\"http://www.w3.org/2000/svg\"/>"
+        ));
+    }
+
+    #[test]
+    fn refuses_entities_and_broken_documents() {
+        assert!(refused(
+            "<!DOCTYPE svg [<!ENTITY x \"&#60;script&#62;\">]><svg xmlns=\"http://www.w3.org/2000/svg\">&x;</svg>"
+        ));
+        assert!(refused(&svg("<text>&unknown;</text>")));
+        assert!(refused(&svg("<g></rect>")));
+        assert!(refused(&svg("<undeclared:g/>")));
+        assert!(validate_svg(b"<svg xmlns=\"http://www.w3.org/2000/svg\">\xff</svg>").is_err());
+    }
+}
@@ -0,0 +1,40 @@
+//! ## Placeholder
+//! Every raster image gets a tiny preview, at most [`PLACEHOLDER_SIZE`] pixels wide or tall, saved
+//! on `image_metadata` as a `data:` URL of a few hundred bytes. Pages show it stretched as the
+//! background of the image, which browsers smooth into a blur, until the image itself loads.
+//! SVGs are small and drawn right away, so they have none.
+
+use super::{
+    mime::ImageType,
+    variants::{decode, encode},
+};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use image::DynamicImage;
+
+/// The biggest side of a placeholder, in pixels.
+pub const PLACEHOLDER_SIZE: u32 = 16;
+
+/// Generate the placeholder of an image, `None` for SVGs. It's slow, so call it from a blocking
+/// task.
+pub fn generate_placeholder(
+    content: &[u8],
+    image_type: ImageType,
+) -> Result<Option<String>, String> {
+    if image_type == ImageType::Svg {
+        return Ok(None);
+    }
+
+    let image = decode(content, image_type)?;
+    let preview = image.thumbnail(PLACEHOLDER_SIZE, PLACEHOLDER_SIZE);
+    // Transparency is kept, so the placeholder of a logo isn't a black square
+    let preview = match preview.color().has_alpha() {
+        true => DynamicImage::ImageRgba8(preview.to_rgba8()),
+        false => DynamicImage::ImageRgb8(preview.to_rgb8()),
+    };
+    let encoded = encode(&preview, ImageType::Png)?;
+
+    Ok(Some(format!(
+        "data:image/png;base64,{}",
+        STANDARD.encode(encoded)
+    )))
+}
@@ -0,0 +1,376 @@
+//! ## Storage
+//! Uploads are stored by their content, under `data/uploads/<first 2 hex>/<SHA-256>.<extension>`.
+//! The same image is never stored twice, and two uploads sharing a name don't overwrite each
+//! other. A file may be referenced by more than one `image_metadata` row, as each upload gets
+//! it's own even when the content is stored already, and rows uploaded before hashing shared
+//! files when they shared a name, so a file is only removed with the last of them.
+//!
+//! Names sent by clients are never part of a path, they're only kept, cleaned by
+//! [`original_filename`], as metadata. Paths are still checked with [`resolve_upload_path`]
+//! before any file is written, read or removed, as rows from before hashing were named by the
+//! client, and a `..` or a symlink could lead outside of [`UPLOADS_DIR`].
+//!
+//! The paths name files on the [`MediaStore`] of `AppState`, which may be the uploads directory
+//! or an object storage, see `crate::gallery::media`.
+
+use super::{media::MediaStore, mime::ImageType, variants::variant_path};
+use crate::api::images::ImageSize;
+use sha2::{Digest, Sha256};
+use std::path::{Component, Path, PathBuf};
+use tokio::fs;
+
+pub const UPLOADS_DIR: &str = "data/uploads";
+
+/// How much a single upload, and everything a user uploaded, can take. Set with
+/// `GALLERY_MAX_FILE_MB` and `GALLERY_USER_QUOTA_MB`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UploadLimits {
+    pub max_file_size: u64,
+    pub user_quota: u64,
+}
+
+impl Default for UploadLimits {
+    fn default() -> Self {
+        Self {
+            max_file_size: 20 * 1024 * 1024,
+            user_quota: 1024 * 1024 * 1024,
+        }
+    }
+}
+
+impl UploadLimits {
+    pub fn from_env() -> Self {
+        let megabytes = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .and_then(|megabytes| megabytes.parse::<u64>().ok())
+                .map(|megabytes| megabytes * 1024 * 1024)
+        };
+        let default = Self::default();
+
+        Self {
+            max_file_size: megabytes("GALLERY_MAX_FILE_MB").unwrap_or(default.max_file_size),
+            user_quota: megabytes("GALLERY_USER_QUOTA_MB").unwrap_or(default.user_quota),
+        }
+    }
+}
+
+/// The hex SHA-256 of an image.
+pub fn content_hash(content: &[u8]) -> String {
+    Sha256::digest(content)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Where an image with `hash` is stored. The first two characters make a directory, so none of
+/// them grows too big.
+pub fn storage_path(hash: &str, image_type: ImageType) -> String {
+    format!(
+        "{}/{}/{}.{}",
+        UPLOADS_DIR,
+        &hash[..2],
+        hash,
+        image_type.extension()
+    )
+}
+
+/// The longest original filename kept, in characters.
+const MAX_FILENAME_LENGTH: usize = 255;
+
+/// Clean the name a client sent with an upload, to keep as metadata: only the last component of
+/// the path is kept, without control characters or the ones that reorder text, like U+202E.
+pub fn original_filename(name: &str) -> Option<String> {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let name: String = name
+        .chars()
+        .filter(|c| {
+            !c.is_control()
+                && !matches!(c, '\u{200E}' | '\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}')
+        })
+        .take(MAX_FILENAME_LENGTH)
+        .collect();
+    let name = name.trim();
+
+    match name {
+        "" | "." | ".." => None,
+        name => Some(name.to_string()),
+    }
+}
+
+fn outside_uploads() -> String {
+    "The path is outside of the uploads directory.".to_string()
+}
+
+/// Resolve `path`, following `..` and symlinks, and check that it stays inside [`UPLOADS_DIR`].
+/// Files that don't exist yet are resolved through their directory, which must exist.
+pub async fn resolve_upload_path(path: &str) -> Result<PathBuf, String> {
+    resolve_inside(Path::new(UPLOADS_DIR), Path::new(path)).await
+}
+
+/// Resolve `path` like [`resolve_upload_path`], checking it stays inside `root`.
+async fn resolve_inside(root: &Path, path: &Path) -> Result<PathBuf, String> {
+    let root = fs::canonicalize(root)
+        .await
+        .map_err(|err| err.to_string())?;
+
+    let resolved = match fs::canonicalize(path).await {
+        Ok(resolved) => resolved,
+        Err(_) => {
+            let file_name = match path.components().last() {
+                Some(Component::Normal(file_name)) => file_name,
+                _ => return Err(outside_uploads()),
+            };
+            let parent = path.parent().ok_or_else(outside_uploads)?;
+            fs::canonicalize(parent)
+                .await
+                .map_err(|err| err.to_string())?
+                .join(file_name)
+        }
+    };
+
+    if resolved.starts_with(&root) && resolved != root {
+        Ok(resolved)
+    } else {
+        Err(outside_uploads())
+    }
+}
+
+/// Whether `path` is under [`UPLOADS_DIR`] only looking at it, with no `..` or root on the way.
+/// Checked before creating directories, that can't wait for [`resolve_upload_path`], and for
+/// stores without a filesystem.
+pub fn is_plain_upload_path(path: &str) -> bool {
+    Path::new(path)
+        .strip_prefix(UPLOADS_DIR)
+        .is_ok_and(|relative| {
+            relative
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)))
+        })
+}
+
+/// `path` with the `.` left out and each `..` taking the directory before it, so paths spelled
+/// differently compare equal, like `data/uploads/./ab/x.png` and `data/uploads/ab/x.png`. Only
+/// looks at the text, see `MediaStore::normalize` for symlinks.
+pub fn normalize_path(path: &str) -> String {
+    let mut normalized: Vec<Component> = vec![];
+    for component in Path::new(path).components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir if matches!(normalized.last(), Some(Component::Normal(_))) => {
+                normalized.pop();
+            }
+            // There's nothing above the root
+            Component::ParentDir if matches!(normalized.last(), Some(Component::RootDir)) => (),
+            component => normalized.push(component),
+        }
+    }
+
+    normalized
+        .into_iter()
+        .collect::<PathBuf>()
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Save an image on it's path of the media store.
+pub async fn write_image(
+    media: &dyn MediaStore,
+    path: &str,
+    content: Vec<u8>,
+    image_type: ImageType,
+) -> Result<(), String> {
+    if !is_plain_upload_path(path) {
+        return Err(outside_uploads());
+    }
+    media.put(path, content, image_type.mime_type()).await
+}
+
+/// Remove an image that nothing references anymore, and it's variants.
+pub async fn remove_image(
+    media: &dyn MediaStore,
+    path: &str,
+    image_type: Option<ImageType>,
+) -> Result<(), String> {
+    if !is_plain_upload_path(path) {
+        return Err(outside_uploads());
+    }
+
+    if let Some(image_type) = image_type {
+        for size in ImageSize::ALL {
+            if let Some(variant) = variant_path(path, image_type, size) {
+                // Variants are generated lazily, so they may not exist.
+                let _ = media.delete(&variant).await;
+            }
+        }
+    }
+
+    media.delete(path).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_only_the_last_component() {
+        assert_eq!(
+            original_filename("../../etc/passwd").as_deref(),
+            Some("passwd")
+        );
+        assert_eq!(original_filename("C:\\x\\..\\y").as_deref(), Some("y"));
+        assert_eq!(
+            original_filename("photos/2024\\beach.jpg").as_deref(),
+            Some("beach.jpg")
+        );
+        assert_eq!(original_filename("cat.png").as_deref(), Some("cat.png"));
+    }
+
+    #[test]
+    fn drops_reordering_and_control_characters() {
+        assert_eq!(
+            original_filename("invoice\u{202E}gpj.exe").as_deref(),
+            Some("invoicegpj.exe")
+        );
+        assert_eq!(
+            original_filename("\u{2066}a\u{200F}b\u{2069}.png").as_deref(),
+            Some("ab.png")
+        );
+        assert_eq!(
+            original_filename("a\nb\r\tc\0\u{7F}\u{9B}.png").as_deref(),
+            Some("abc.png")
+        );
+    }
+
+    #[test]
+    fn refuses_names_without_a_file() {
+        for name in [
+            "", ".", "..", "  ", "dir/", "a/..", "C:\\", "\u{202E}", "\n\t",
+        ] {
+            assert_eq!(original_filename(name), None, "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn shortens_long_names() {
+        let name = original_filename(&"a".repeat(1000)).unwrap();
+        assert_eq!(name.chars().count(), MAX_FILENAME_LENGTH);
+
+        // Counted in characters, so multi-byte ones aren't cut in half
+        let name = original_filename(&"é".repeat(1000)).unwrap();
+        assert_eq!(name.chars().count(), MAX_FILENAME_LENGTH);
+    }
+
+    #[test]
+    fn normalizes_paths_by_their_text() {
+        assert_eq!(
+            normalize_path("data/uploads/ab/x.png"),
+            "data/uploads/ab/x.png"
+        );
+        assert_eq!(
+            normalize_path("data/uploads/./ab/x.png"),
+            "data/uploads/ab/x.png"
+        );
+        assert_eq!(
+            normalize_path("data//uploads/ab/../ab/x.png"),
+            "data/uploads/ab/x.png"
+        );
+        assert_eq!(normalize_path("./data/uploads/x.png"), "data/uploads/x.png");
+        assert_eq!(normalize_path("../uploads/x.png"), "../uploads/x.png");
+        assert_eq!(normalize_path("/data/../../x.png"), "/x.png");
+    }
+
+    /// An empty directory to act as the uploads one, and another beside it.
+    async fn directories(test: &str) -> (PathBuf, PathBuf) {
+        let base = std::env::temp_dir().join(format!("storage-{}-{}", std::process::id(), test));
+        let _ = fs::remove_dir_all(&base).await;
+        let (uploads, outside) = (base.join("uploads"), base.join("outside"));
+        fs::create_dir_all(uploads.join("ab")).await.unwrap();
+        fs::create_dir_all(&outside).await.unwrap();
+        (uploads, outside)
+    }
+
+    #[tokio::test]
+    async fn resolves_paths_inside_the_uploads() {
+        let (uploads, _) = directories("inside").await;
+        let root = fs::canonicalize(&uploads).await.unwrap();
+
+        fs::write(uploads.join("ab/existing.png"), b"png")
+            .await
+            .unwrap();
+        for path in [
+            "ab/existing.png",
+            "ab/new.png",
+            "ab/./new.png",
+            "ab/../new.png",
+        ] {
+            let resolved = resolve_inside(&uploads, &uploads.join(path)).await;
+            assert!(
+                resolved
+                    .as_ref()
+                    .is_ok_and(|resolved| resolved.starts_with(&root)),
+                "{}: {:?}",
+                path,
+                resolved
+            );
+        }
+
+        // On Unix backslashes are part of the name
+        let resolved = resolve_inside(&uploads, &uploads.join("C:\\x\\..\\y")).await;
+        assert_eq!(resolved, Ok(root.join("C:\\x\\..\\y")));
+
+        // Names too long for the filesystem can't escape either, writing them just fails
+        let long = uploads.join("a".repeat(1000));
+        assert!(resolve_inside(&uploads, &long)
+            .await
+            .is_ok_and(|resolved| resolved.starts_with(&root)));
+
+        let _ = fs::remove_dir_all(uploads.parent().unwrap()).await;
+    }
+
+    #[tokio::test]
+    async fn refuses_paths_leaving_the_uploads() {
+        let (uploads, outside) = directories("leaving").await;
+        fs::write(outside.join("secret.png"), b"png").await.unwrap();
+
+        for path in [
+            uploads.join("../outside/secret.png"),
+            uploads.join("../../etc/passwd"),
+            uploads.join("ab/../../outside/new.png"),
+            uploads.join("."),
+            uploads.join(".."),
+            uploads.join("ab/.."),
+            uploads.clone(),
+            PathBuf::from("/etc/passwd"),
+            PathBuf::new(),
+        ] {
+            // Paths through directories that don't exist fail to resolve at all
+            let resolved = resolve_inside(&uploads, &path).await;
+            assert!(resolved.is_err(), "{:?}: {:?}", path, resolved);
+        }
+
+        let _ = fs::remove_dir_all(uploads.parent().unwrap()).await;
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn refuses_symlinks_leaving_the_uploads() {
+        let (uploads, outside) = directories("symlinks").await;
+        fs::write(outside.join("secret.png"), b"png").await.unwrap();
+        fs::symlink(&outside, uploads.join("link")).await.unwrap();
+        fs::symlink(outside.join("secret.png"), uploads.join("ab/secret.png"))
+            .await
+            .unwrap();
+
+        for path in ["link/secret.png", "link/new.png", "ab/secret.png"] {
+            assert_eq!(
+                resolve_inside(&uploads, &uploads.join(path)).await,
+                Err(outside_uploads()),
+                "{}",
+                path
+            );
+        }
+
+        let _ = fs::remove_dir_all(uploads.parent().unwrap()).await;
+    }
+}
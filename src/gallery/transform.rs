@@ -0,0 +1,558 @@
+//! ## Transforms
+//! Images can be resized and converted when requested, with query parameters on `/gallery/:id`:
+//! - `w` and `h`, the size in pixels, from [`ALLOWED_DIMENSIONS`], the widths of `ImageSize`;
+//! - `fit`, `contain` (the default) to fit inside `w`x`h`, `cover` to fill it cropping the
+//!   borders, or `fill` to stretch the image to it;
+//! - `fmt`, one of `webp`, `avif`, `png` or `jpeg`;
+//! - `q`, the quality for `jpeg` and `avif`, from [`ALLOWED_QUALITIES`]. PNG and WebP are lossless.
+//!
+//! Only safelisted values are accepted, so a client can't make us fill the disk with every size
+//! between one and a million pixels, and only [`MAX_ENCODING`] images are transformed at once.
+//! Results are cached in [`CACHE_DIR`], under a directory named by the original's hash and keyed
+//! by a SHA-256 of that hash and the transform, so the same request is only ever transformed once
+//! and [`remove_cached`] can drop them with the original. The cache is always on this host,
+//! whatever the media store, as it can be made again.
+
+use super::{media::MediaStore, mime::ImageType, storage::content_hash, variants::decode};
+use image::{
+    codecs::{avif::AvifEncoder, jpeg::JpegEncoder, png::PngEncoder, webp::WebPEncoder},
+    imageops::FilterType,
+    DynamicImage, ImageEncoder,
+};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::io::Cursor;
+use tokio::{fs, sync::Semaphore};
+
+pub const CACHE_DIR: &str = "data/cache/transforms";
+
+pub const ALLOWED_DIMENSIONS: [u32; 3] = [320, 800, 1600];
+
+pub const ALLOWED_QUALITIES: [u8; 6] = [50, 60, 70, 80, 90, 100];
+
+const DEFAULT_QUALITY: u8 = 80;
+
+/// The speed avif is encoded with, from 1 (slowest) to 10. Anything lower takes seconds on big
+/// images.
+const AVIF_SPEED: u8 = 8;
+
+/// How many transforms run at once, the others wait for their turn.
+pub const MAX_ENCODING: usize = 2;
+
+static ENCODING: Semaphore = Semaphore::const_new(MAX_ENCODING);
+
+/// The query parameters of `/gallery/:id`.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct TransformQuery {
+    pub w: Option<u32>,
+    pub h: Option<u32>,
+    pub fit: Option<String>,
+    pub fmt: Option<String>,
+    pub q: Option<u8>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Fit {
+    Contain,
+    Cover,
+    Fill,
+}
+
+impl Fit {
+    pub const ALL: [Fit; 3] = [Fit::Contain, Fit::Cover, Fit::Fill];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Fit::Contain => "contain",
+            Fit::Cover => "cover",
+            Fit::Fill => "fill",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Fit> {
+        Self::ALL.into_iter().find(|fit| fit.name() == name)
+    }
+}
+
+/// The formats an image can be converted to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Webp,
+    Avif,
+    Png,
+    Jpeg,
+}
+
+impl OutputFormat {
+    pub const ALL: [OutputFormat; 4] = [
+        OutputFormat::Webp,
+        OutputFormat::Avif,
+        OutputFormat::Png,
+        OutputFormat::Jpeg,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            OutputFormat::Webp => "webp",
+            OutputFormat::Avif => "avif",
+            OutputFormat::Png => "png",
+            OutputFormat::Jpeg => "jpeg",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<OutputFormat> {
+        Self::ALL.into_iter().find(|format| format.name() == name)
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            OutputFormat::Webp => "image/webp",
+            OutputFormat::Avif => "image/avif",
+            OutputFormat::Png => "image/png",
+            OutputFormat::Jpeg => "image/jpeg",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Webp => "webp",
+            OutputFormat::Avif => "avif",
+            OutputFormat::Png => "png",
+            OutputFormat::Jpeg => "jpg",
+        }
+    }
+
+    /// Images keep their type when no `fmt` is asked. GIFs lose their animation anyway, so they
+    /// become PNGs.
+    fn for_image(image_type: ImageType) -> OutputFormat {
+        match image_type {
+            ImageType::Jpeg => OutputFormat::Jpeg,
+            ImageType::Webp => OutputFormat::Webp,
+            _ => OutputFormat::Png,
+        }
+    }
+
+    fn is_lossy(&self) -> bool {
+        matches!(self, OutputFormat::Avif | OutputFormat::Jpeg)
+    }
+}
+
+/// A validated transform, ready to be applied.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Transform {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub fit: Fit,
+    pub format: OutputFormat,
+    pub quality: u8,
+}
+
+impl Transform {
+    /// Validate the query against the safelists. `Ok(None)` means no transform was asked and the
+    /// original should be served.
+    pub fn from_query(
+        query: &TransformQuery,
+        image_type: ImageType,
+    ) -> Result<Option<Transform>, String> {
+        if query.w.is_none()
+            && query.h.is_none()
+            && query.fit.is_none()
+            && query.fmt.is_none()
+            && query.q.is_none()
+        {
+            return Ok(None);
+        }
+
+        if image_type == ImageType::Svg {
+            return Err("SVG images can't be transformed.".to_string());
+        }
+
+        for dimension in [query.w, query.h].into_iter().flatten() {
+            if !ALLOWED_DIMENSIONS.contains(&dimension) {
+                return Err(format!(
+                    "`w` and `h` must be one of {:?}.",
+                    ALLOWED_DIMENSIONS
+                ));
+            }
+        }
+
+        let fit = match query.fit.as_deref() {
+            None => Fit::Contain,
+            Some(name) => {
+                Fit::from_name(name).ok_or("`fit` must be one of `contain`, `cover` or `fill`.")?
+            }
+        };
+        if fit != Fit::Contain && (query.w.is_none() || query.h.is_none()) {
+            return Err(format!("`fit={}` needs both `w` and `h`.", fit.name()));
+        }
+
+        let format = match query.fmt.as_deref() {
+            None => OutputFormat::for_image(image_type),
+            Some(name) => OutputFormat::from_name(name)
+                .ok_or("`fmt` must be one of `webp`, `avif`, `png` or `jpeg`.")?,
+        };
+
+        let quality = match query.q {
+            None => DEFAULT_QUALITY,
+            Some(quality) if ALLOWED_QUALITIES.contains(&quality) => quality,
+            Some(_) => {
+                return Err(format!("`q` must be one of {:?}.", ALLOWED_QUALITIES));
+            }
+        };
+
+        Ok(Some(Transform {
+            width: query.w,
+            height: query.h,
+            fit,
+            format,
+            // Lossless formats ignore the quality, keep it out of their cache key.
+            quality: if format.is_lossy() { quality } else { 100 },
+        }))
+    }
+
+    /// The cache key, a SHA-256 of the original's hash, see `storage::content_hash`, and the
+    /// transform.
+    pub fn cache_key(&self, original_hash: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(original_hash.as_bytes());
+        hasher.update(
+            format!(
+                "w={:?};h={:?};fit={};fmt={};q={}",
+                self.width,
+                self.height,
+                self.fit.name(),
+                self.format.name(),
+                self.quality
+            )
+            .as_bytes(),
+        );
+
+        hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    pub fn cache_path(&self, original_hash: &str) -> String {
+        format!(
+            "{}/{}.{}",
+            cache_dir(original_hash),
+            self.cache_key(original_hash),
+            self.format.extension()
+        )
+    }
+
+    fn resize(&self, image: DynamicImage) -> DynamicImage {
+        let (width, height) = match (self.width, self.height) {
+            (None, None) => return image,
+            (width, height) => (width.unwrap_or(u32::MAX), height.unwrap_or(u32::MAX)),
+        };
+
+        match self.fit {
+            // Never enlarge images that already fit.
+            Fit::Contain if image.width() <= width && image.height() <= height => image,
+            Fit::Contain => image.resize(width, height, FilterType::Lanczos3),
+            Fit::Cover => image.resize_to_fill(width, height, FilterType::Lanczos3),
+            Fit::Fill => image.resize_exact(width, height, FilterType::Lanczos3),
+        }
+    }
+
+    fn encode(&self, image: &DynamicImage) -> Result<Vec<u8>, String> {
+        let mut encoded = Cursor::new(vec![]);
+
+        match self.format {
+            OutputFormat::Jpeg => {
+                let image = image.to_rgb8();
+                JpegEncoder::new_with_quality(&mut encoded, self.quality).write_image(
+                    &image,
+                    image.width(),
+                    image.height(),
+                    image::ExtendedColorType::Rgb8,
+                )
+            }
+            OutputFormat::Png => {
+                let image = image.to_rgba8();
+                PngEncoder::new(&mut encoded).write_image(
+                    &image,
+                    image.width(),
+                    image.height(),
+                    image::ExtendedColorType::Rgba8,
+                )
+            }
+            OutputFormat::Webp => {
+                let image = image.to_rgba8();
+                WebPEncoder::new_lossless(&mut encoded).write_image(
+                    &image,
+                    image.width(),
+                    image.height(),
+                    image::ExtendedColorType::Rgba8,
+                )
+            }
+            OutputFormat::Avif => {
+                let image = image.to_rgba8();
+                AvifEncoder::new_with_speed_quality(&mut encoded, AVIF_SPEED, self.quality)
+                    .write_image(
+                        &image,
+                        image.width(),
+                        image.height(),
+                        image::ExtendedColorType::Rgba8,
+                    )
+            }
+        }
+        .map_err(|err| err.to_string())?;
+
+        Ok(encoded.into_inner())
+    }
+
+    /// Apply the transform. It's slow, so call it from a blocking task.
+    pub fn apply(&self, original: &[u8], image_type: ImageType) -> Result<Vec<u8>, String> {
+        let image = decode(original, image_type)?;
+        self.encode(&self.resize(image))
+    }
+}
+
+/// The directory with every transform of the original with `original_hash`.
+fn cache_dir(original_hash: &str) -> String {
+    format!("{}/{}", CACHE_DIR, original_hash)
+}
+
+/// Whether `hash` looks like one made by `storage::content_hash`, so it's safe on a path.
+fn is_content_hash(hash: &str) -> bool {
+    !hash.is_empty() && hash.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Remove the cached transforms of the original with `original_hash`, once it's deleted.
+pub async fn remove_cached(original_hash: &str) -> Result<(), String> {
+    if !is_content_hash(original_hash) {
+        return Err(format!("\"{}\" is not a content hash.", original_hash));
+    }
+
+    match fs::remove_dir_all(cache_dir(original_hash)).await {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.to_string()),
+        _ => Ok(()),
+    }
+}
+
+async fn read_original(media: &dyn MediaStore, image_path: &str) -> Result<Vec<u8>, String> {
+    media
+        .get(image_path)
+        .await?
+        .ok_or_else(|| "The original image doesn't exist.".to_string())
+}
+
+/// Find the transformed image on the cache, transforming the original the first time. Images
+/// saved before their hash was, have it computed from their content. Returns the cached path.
+pub async fn transformed_file(
+    media: &dyn MediaStore,
+    image_path: &str,
+    original_hash: Option<String>,
+    image_type: ImageType,
+    transform: Transform,
+) -> Result<String, String> {
+    let mut original = None;
+    let original_hash = match original_hash.filter(|hash| is_content_hash(hash)) {
+        Some(original_hash) => original_hash,
+        None => {
+            let content = read_original(media, image_path).await?;
+            let original_hash = content_hash(&content);
+            original = Some(content);
+            original_hash
+        }
+    };
+    let path = transform.cache_path(&original_hash);
+
+    if fs::try_exists(&path).await.unwrap_or(false) {
+        return Ok(path);
+    }
+
+    let original = match original {
+        Some(original) => original,
+        None => read_original(media, image_path).await?,
+    };
+    let _encoding = ENCODING.acquire().await.map_err(|err| err.to_string())?;
+    // Someone else may have transformed it while we waited
+    if fs::try_exists(&path).await.unwrap_or(false) {
+        return Ok(path);
+    }
+    let transformed = tokio::task::spawn_blocking(move || transform.apply(&original, image_type))
+        .await
+        .map_err(|err| err.to_string())??;
+
+    // Written aside and renamed, so a concurrent request never streams half a file.
+    fs::create_dir_all(cache_dir(&original_hash))
+        .await
+        .map_err(|err| err.to_string())?;
+    let partial_path = format!("{}.{:016x}.partial", path, rand::random::<u64>());
+    fs::write(&partial_path, &transformed)
+        .await
+        .map_err(|err| err.to_string())?;
+    fs::rename(&partial_path, &path)
+        .await
+        .map_err(|err| err.to_string())?;
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(
+        w: Option<u32>,
+        h: Option<u32>,
+        fit: Option<&str>,
+        fmt: Option<&str>,
+        q: Option<u8>,
+    ) -> TransformQuery {
+        TransformQuery {
+            w,
+            h,
+            fit: fit.map(str::to_string),
+            fmt: fmt.map(str::to_string),
+            q,
+        }
+    }
+
+    fn transform(query: &TransformQuery, image_type: ImageType) -> Transform {
+        Transform::from_query(query, image_type).unwrap().unwrap()
+    }
+
+    #[test]
+    fn no_parameters_serve_the_original() {
+        assert_eq!(
+            Transform::from_query(&TransformQuery::default(), ImageType::Png),
+            Ok(None)
+        );
+        assert_eq!(
+            Transform::from_query(&TransformQuery::default(), ImageType::Svg),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn accepts_safelisted_values() {
+        let jpeg = transform(
+            &query(Some(800), Some(320), Some("cover"), Some("avif"), Some(60)),
+            ImageType::Jpeg,
+        );
+        assert_eq!(
+            jpeg,
+            Transform {
+                width: Some(800),
+                height: Some(320),
+                fit: Fit::Cover,
+                format: OutputFormat::Avif,
+                quality: 60,
+            }
+        );
+
+        // Images keep their type, and GIFs become PNGs
+        let webp = transform(&query(Some(320), None, None, None, None), ImageType::Webp);
+        assert_eq!((webp.fit, webp.format), (Fit::Contain, OutputFormat::Webp));
+        let gif = transform(&query(None, Some(1600), None, None, None), ImageType::Gif);
+        assert_eq!(gif.format, OutputFormat::Png);
+        let jpeg = transform(&query(None, None, None, None, Some(90)), ImageType::Jpeg);
+        assert_eq!((jpeg.format, jpeg.quality), (OutputFormat::Jpeg, 90));
+    }
+
+    #[test]
+    fn rejects_values_off_the_safelists() {
+        for (query, image_type) in [
+            (query(Some(321), None, None, None, None), ImageType::Png),
+            (query(Some(320), Some(0), None, None, None), ImageType::Png),
+            (query(Some(1920), None, None, None, None), ImageType::Png),
+            (
+                query(None, None, Some("stretch"), None, None),
+                ImageType::Png,
+            ),
+            (
+                query(Some(320), None, Some("cover"), None, None),
+                ImageType::Png,
+            ),
+            (
+                query(None, Some(320), Some("fill"), None, None),
+                ImageType::Png,
+            ),
+            (query(None, None, None, Some("gif"), None), ImageType::Png),
+            (query(None, None, None, None, Some(85)), ImageType::Jpeg),
+            (query(Some(320), None, None, None, None), ImageType::Svg),
+        ] {
+            assert!(
+                Transform::from_query(&query, image_type).is_err(),
+                "{:?} on {:?} should be refused",
+                query,
+                image_type
+            );
+        }
+    }
+
+    #[test]
+    fn lossless_formats_ignore_the_quality() {
+        let png = transform(
+            &query(Some(320), None, None, Some("png"), Some(50)),
+            ImageType::Png,
+        );
+        let same = transform(
+            &query(Some(320), None, None, Some("png"), None),
+            ImageType::Png,
+        );
+        assert_eq!(png.quality, 100);
+        assert_eq!(png.cache_key("abc"), same.cache_key("abc"));
+
+        let webp = transform(
+            &query(None, None, None, Some("webp"), Some(60)),
+            ImageType::Png,
+        );
+        assert_eq!(webp.quality, 100);
+    }
+
+    #[test]
+    fn cache_keys_tell_transforms_and_originals_apart() {
+        let base = transform(
+            &query(Some(320), None, None, Some("jpeg"), None),
+            ImageType::Png,
+        );
+        let keys = [
+            base.cache_key("abc"),
+            base.cache_key("abd"),
+            Transform {
+                quality: 60,
+                ..base
+            }
+            .cache_key("abc"),
+            Transform {
+                width: Some(800),
+                ..base
+            }
+            .cache_key("abc"),
+            Transform {
+                height: Some(320),
+                ..base
+            }
+            .cache_key("abc"),
+            Transform {
+                format: OutputFormat::Avif,
+                ..base
+            }
+            .cache_key("abc"),
+        ];
+        for (index, key) in keys.iter().enumerate() {
+            assert_eq!(key.len(), 64);
+            assert!(!keys[index + 1..].contains(key));
+        }
+        assert_eq!(base.cache_key("abc"), keys[0]);
+
+        assert_eq!(
+            base.cache_path("abc"),
+            format!("{}/abc/{}.jpg", CACHE_DIR, keys[0])
+        );
+    }
+
+    #[tokio::test]
+    async fn only_removes_content_hashes() {
+        assert!(remove_cached("../..").await.is_err());
+        assert!(remove_cached("").await.is_err());
+        assert!(remove_cached("0123456789abcdef").await.is_ok());
+    }
+}
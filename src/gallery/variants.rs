@@ -0,0 +1,237 @@
+//! ## Variants
+//! Every raster image gets resized copies, one for each [`ImageSize`], saved next to the original
+//! as `<original path>.<size>.<extension>`. JPEGs stay JPEGs and every other type becomes a PNG.
+//! SVGs scale by themselves, so they have no variants.
+//!
+//! Every image decoded on the server goes through [`decode`], which refuses images bigger than
+//! [`decode_limits`], so a small file can't unpack into gigabytes of pixels.
+
+use super::{media::MediaStore, mime::ImageType};
+use crate::api::images::ImageSize;
+use image::{
+    codecs::jpeg::JpegEncoder, imageops::FilterType, DynamicImage, ImageFormat, ImageReader, Limits,
+};
+use std::io::Cursor;
+
+const JPEG_QUALITY: u8 = 85;
+
+/// The widest and tallest image that is decoded.
+const MAX_DIMENSION: u32 = 10_000;
+
+/// The most memory a decoder may take, enough for a 8000x8000 RGBA image.
+const MAX_DECODE_ALLOC: u64 = 256 * 1024 * 1024;
+
+/// The limits of every decoder, see [`decode`].
+pub fn decode_limits() -> Limits {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODE_ALLOC);
+    limits
+}
+
+/// The type variants of an image are saved as, `None` for SVGs.
+pub fn variant_type(image_type: ImageType) -> Option<ImageType> {
+    match image_type {
+        ImageType::Svg => None,
+        ImageType::Jpeg => Some(ImageType::Jpeg),
+        _ => Some(ImageType::Png),
+    }
+}
+
+pub fn variant_path(image_path: &str, image_type: ImageType, size: ImageSize) -> Option<String> {
+    variant_type(image_type).map(|variant_type| {
+        format!(
+            "{}.{}.{}",
+            image_path,
+            size.name(),
+            variant_type.extension()
+        )
+    })
+}
+
+pub fn decode(content: &[u8], image_type: ImageType) -> Result<DynamicImage, String> {
+    let format = match image_type {
+        ImageType::Png => ImageFormat::Png,
+        ImageType::Jpeg => ImageFormat::Jpeg,
+        ImageType::Gif => ImageFormat::Gif,
+        ImageType::Webp => ImageFormat::WebP,
+        ImageType::Svg => return Err("SVG images can't be decoded".to_string()),
+    };
+
+    let mut reader = ImageReader::with_format(Cursor::new(content), format);
+    reader.limits(decode_limits());
+    reader.decode().map_err(|err| err.to_string())
+}
+
+pub fn encode(image: &DynamicImage, image_type: ImageType) -> Result<Vec<u8>, String> {
+    let mut encoded = Cursor::new(vec![]);
+
+    match image_type {
+        ImageType::Jpeg => JpegEncoder::new_with_quality(&mut encoded, JPEG_QUALITY)
+            .encode_image(&image.to_rgb8())
+            .map_err(|err| err.to_string())?,
+        _ => image
+            .write_to(&mut encoded, ImageFormat::Png)
+            .map_err(|err| err.to_string())?,
+    }
+
+    Ok(encoded.into_inner())
+}
+
+/// Resize `image` to fit `width`, keeping the aspect ratio and never enlarging it.
+pub fn resize(image: &DynamicImage, width: u32) -> DynamicImage {
+    if image.width() <= width {
+        image.clone()
+    } else {
+        image.resize(width, u32::MAX, FilterType::Lanczos3)
+    }
+}
+
+/// Generate a variant of an image. It's slow, so call it from a blocking task.
+pub fn generate_variant(
+    content: &[u8],
+    image_type: ImageType,
+    size: ImageSize,
+) -> Result<Vec<u8>, String> {
+    let variant_type = variant_type(image_type).ok_or("SVG images have no variants")?;
+    let image = decode(content, image_type)?;
+    encode(&resize(&image, size.width()), variant_type)
+}
+
+/// Generate and save every variant of an image.
+pub async fn write_variants(
+    media: &dyn MediaStore,
+    image_path: &str,
+    content: Vec<u8>,
+    image_type: ImageType,
+) -> Result<(), String> {
+    let variant_type = match variant_type(image_type) {
+        Some(variant_type) => variant_type,
+        None => return Ok(()),
+    };
+
+    let variants = tokio::task::spawn_blocking(move || {
+        let image = decode(&content, image_type)?;
+        ImageSize::ALL
+            .into_iter()
+            .map(|size| Ok((size, encode(&resize(&image, size.width()), variant_type)?)))
+            .collect::<Result<Vec<_>, String>>()
+    })
+    .await
+    .map_err(|err| err.to_string())??;
+
+    for (size, variant) in variants {
+        let path = variant_path(image_path, image_type, size).unwrap_or_default();
+        media.put(&path, variant, variant_type.mime_type()).await?;
+    }
+
+    Ok(())
+}
+
+/// Find the path of a variant of an image, generating it first when missing, like for images
+/// uploaded before variants existed. Returns the path and it's type.
+pub async fn variant_file(
+    media: &dyn MediaStore,
+    image_path: &str,
+    image_type: ImageType,
+    size: ImageSize,
+) -> Result<(String, ImageType), String> {
+    let (path, variant_type) = match (
+        variant_path(image_path, image_type, size),
+        variant_type(image_type),
+    ) {
+        (Some(path), Some(variant_type)) => (path, variant_type),
+        // SVGs are served as they are.
+        _ => return Ok((image_path.to_string(), image_type)),
+    };
+
+    if media.exists(&path).await.unwrap_or(false) {
+        return Ok((path, variant_type));
+    }
+
+    let original = media
+        .get(image_path)
+        .await?
+        .ok_or_else(|| "The original image doesn't exist.".to_string())?;
+    let variant =
+        tokio::task::spawn_blocking(move || generate_variant(&original, image_type, size))
+            .await
+            .map_err(|err| err.to_string())??;
+    media.put(&path, variant, variant_type.mime_type()).await?;
+
+    Ok((path, variant_type))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gallery::media::{tests::MemoryStore, MediaStore};
+    use image::RgbaImage;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(
+            width,
+            height,
+            image::Rgba([200, 80, 20, 255]),
+        ));
+        encode(&image, ImageType::Png).unwrap()
+    }
+
+    #[test]
+    fn variants_sit_next_to_the_original() {
+        assert_eq!(
+            variant_path("gallery/ab/cd.png", ImageType::Png, ImageSize::Thumbnail).as_deref(),
+            Some("gallery/ab/cd.png.thumbnail.png")
+        );
+        assert_eq!(
+            variant_path("gallery/ab/cd.jpg", ImageType::Jpeg, ImageSize::Large).as_deref(),
+            Some("gallery/ab/cd.jpg.large.jpg")
+        );
+        assert_eq!(
+            variant_path("gallery/ab/cd.webp", ImageType::Webp, ImageSize::Medium).as_deref(),
+            Some("gallery/ab/cd.webp.medium.png")
+        );
+        assert_eq!(
+            variant_path("gallery/ab/cd.svg", ImageType::Svg, ImageSize::Medium),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn writes_every_variant_without_enlarging() {
+        let media = MemoryStore::default();
+        write_variants(&media, "gallery/a.png", png(1000, 500), ImageType::Png)
+            .await
+            .unwrap();
+
+        for size in ImageSize::ALL {
+            let path = variant_path("gallery/a.png", ImageType::Png, size).unwrap();
+            let variant =
+                image::load_from_memory(&media.get(&path).await.unwrap().unwrap()).unwrap();
+            assert_eq!(variant.width(), size.width().min(1000));
+            assert_eq!(variant.height(), variant.width() / 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn svgs_and_broken_images_get_no_variants() {
+        let media = MemoryStore::default();
+        write_variants(&media, "gallery/a.svg", b"<svg/>".to_vec(), ImageType::Svg)
+            .await
+            .unwrap();
+        assert!(
+            write_variants(&media, "gallery/b.png", b"nope".to_vec(), ImageType::Png)
+                .await
+                .is_err()
+        );
+        assert!(media.paths().is_empty());
+    }
+
+    #[test]
+    fn refuses_images_over_the_limits() {
+        let huge = png(MAX_DIMENSION + 1, 1);
+        assert!(decode(&huge, ImageType::Png).is_err());
+        assert!(decode(&png(MAX_DIMENSION, 1), ImageType::Png).is_ok());
+    }
+}
@@ -1,4 +1,6 @@
 pub mod app;
+#[cfg(feature = "ssr")]
+pub mod caching;
 pub mod error_template;
 #[cfg(feature = "ssr")]
 pub mod fileserv;
@@ -8,7 +10,22 @@ pub mod components;
 pub mod pages;
 
 // Functionality
+#[cfg(feature = "ssr")]
+pub mod activitypub;
 pub mod api;
+#[cfg(feature = "ssr")]
+pub mod gallery;
+pub mod markdown;
+#[cfg(feature = "ssr")]
+pub mod maintenance;
+#[cfg(feature = "ssr")]
+pub mod newsletter;
+#[cfg(feature = "ssr")]
+pub mod streaming;
+#[cfg(feature = "ssr")]
+pub mod webmention;
+#[cfg(all(test, feature = "ssr"))]
+mod testing;
 leptos_i18n::load_locales!();
 
 // Integration
@@ -21,6 +38,17 @@ use sea_orm::DatabaseConnection;
 pub struct AppState {
     pub conn: DatabaseConnection,
     pub secret_key: String,
+    /// The public address of the site, without a trailing slash. Used on links sent to other sites.
+    pub site_url: String,
+    pub fetcher: std::sync::Arc<dyn webmention::Fetcher>,
+    pub mailer: std::sync::Arc<dyn newsletter::Mailer>,
+    pub upload_limits: gallery::storage::UploadLimits,
+    /// Where the gallery files are kept, see `gallery::media`.
+    pub media: std::sync::Arc<dyn gallery::media::MediaStore>,
+    /// Held while gallery files and the rows pointing to them change together, as every image
+    /// with the same content shares one file, and from an upload's quota check to it's insert.
+    /// Taken before `AppState` itself, never while holding it.
+    pub gallery_lock: std::sync::Arc<tokio::sync::Mutex<()>>,
 }
 
 #[cfg(feature = "hydrate")]
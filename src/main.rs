@@ -2,7 +2,14 @@
 #[cfg(feature = "ssr")]
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    use axum::{extract::Path, routing::get, Router};
+    use axum::{
+        body::Bytes,
+        extract::{Path, Query},
+        http::HeaderMap,
+        middleware,
+        routing::{get, post},
+        Router,
+    };
 
     use tower_sessions::cookie::time::Duration;
     use tower_sessions::{Expiry, SessionManagerLayer};
@@ -19,8 +26,22 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     use leptos::*;
     use leptos_axum::{generate_route_list, LeptosRoutes};
+    use orangethewell_web::activitypub::{
+        actor_handler, article_handler, followers_handler, inbox_handler, outbox_handler,
+        webfinger_handler,
+    };
     use orangethewell_web::app::*;
-    use orangethewell_web::fileserv::{file_and_error_handler, get_image_by_id_handler};
+    use orangethewell_web::caching::conditional_get;
+    use orangethewell_web::fileserv::{
+        file_and_error_handler, get_image_by_id_handler, get_image_variant_handler,
+    };
+    use orangethewell_web::api::images::{backfill_placeholders, rebuild_image_usage};
+    use orangethewell_web::api::maintenance::OrphanAction;
+    use orangethewell_web::gallery::media::{copy_media, media_store_from_env, LocalStore, S3Store};
+    use orangethewell_web::gallery::storage::UploadLimits;
+    use orangethewell_web::maintenance::{collect_orphans, format_report};
+    use orangethewell_web::newsletter::{digest_job, one_click_unsubscribe_handler, SmtpMailer};
+    use orangethewell_web::webmention::{receive_webmention_handler, HttpFetcher};
     use orangethewell_web::AppState;
 
     dotenv().ok();
@@ -56,10 +77,88 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .await
         .unwrap();
     Migrator::up(&conn, None).await.unwrap();
+    // Articles saved before `image_usage` existed, or edited by hand on disk
+    if let Err(db_err) = rebuild_image_usage(&conn).await {
+        logging::log!("Couldn't rebuild the image usage. DbErr: {}", db_err);
+    }
+    // Where uploads are kept, MEDIA_STORE=local|s3, see `orangethewell_web::gallery::media`
+    let media = media_store_from_env()?;
+
+    // `--copy-media-to-s3` only copies the local uploads to the S3_* bucket, before switching
+    // MEDIA_STORE to s3
+    if env::args().any(|arg| arg == "--copy-media-to-s3") {
+        let report = copy_media(&LocalStore, &S3Store::from_env()?).await?;
+        println!(
+            "Copied {} files, {} were there already.",
+            report.copied, report.skipped
+        );
+        for failure in &report.failures {
+            println!("  {}", failure);
+        }
+        return Ok(());
+    }
+
+    // `--collect-orphans[=dry-run|quarantine|delete]` only runs the orphan collector, see
+    // `orangethewell_web::maintenance`
+    if let Some(flag) = env::args().find(|arg| arg.starts_with("--collect-orphans")) {
+        let action = match flag.split_once('=') {
+            Some((_, name)) => OrphanAction::from_name(name)
+                .ok_or("--collect-orphans takes dry-run, quarantine or delete")?,
+            None => OrphanAction::DryRun,
+        };
+        let report = collect_orphans(&conn, media.as_ref(), action).await?;
+        print!("{}", format_report(&report));
+        return Ok(());
+    }
+
+    // Decoding every older image takes a while, so the site starts without waiting for it
+    let placeholder_conn = conn.clone();
+    let placeholder_media = media.clone();
+    tokio::spawn(async move {
+        if let Err(db_err) =
+            backfill_placeholders(&placeholder_conn, placeholder_media.as_ref()).await
+        {
+            logging::log!("Couldn't fill the image placeholders. DbErr: {}", db_err);
+        }
+    });
 
     let secret_key = env::var("SECRET_KEY").unwrap();
-    let state = Arc::new(Mutex::new(AppState { conn, secret_key }));
+    let site_url = env::var("SITE_URL")
+        .unwrap_or_else(|_| format!("http://{}", addr))
+        .trim_end_matches('/')
+        .to_string();
+    // Newsletter mails, SMTP_URL can point to a local catcher like MailHog when testing
+    let mailer = SmtpMailer::new(
+        &env::var("SMTP_URL").unwrap_or("smtp://localhost:1025".to_string()),
+        &env::var("MAIL_FROM").unwrap_or("Orange Museum <newsletter@localhost>".to_string()),
+    )?;
+    let digest_period = env::var("NEWSLETTER_DIGEST_HOURS")
+        .ok()
+        .and_then(|hours| hours.parse().ok())
+        .unwrap_or(24);
+
+    let state = Arc::new(Mutex::new(AppState {
+        conn,
+        secret_key,
+        site_url,
+        fetcher: Arc::new(HttpFetcher::new()),
+        mailer: Arc::new(mailer),
+        upload_limits: UploadLimits::from_env(),
+        media,
+        gallery_lock: Arc::new(Mutex::new(())),
+    }));
     let state_2 = state.clone();
+    let state_3 = state.clone();
+    let state_4 = state.clone();
+    let state_5 = state.clone();
+    let state_6 = state.clone();
+    let state_7 = state.clone();
+    let state_8 = state.clone();
+    let state_9 = state.clone();
+    let state_10 = state.clone();
+    let state_11 = state.clone();
+
+    tokio::spawn(digest_job(state.clone(), std::time::Duration::from_hours(digest_period)));
 
     // build our application with a route
     let app = Router::new()
@@ -69,11 +168,55 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             move || provide_context(state.clone()),
             App,
         )
+        .layer(middleware::from_fn(conditional_get))
         .fallback(file_and_error_handler)
         .with_state(leptos_options)
         .route(
             "/gallery/:id",
-            get(move |id: Path<i32>| get_image_by_id_handler(id, state_2.clone())),
+            get(move |id: Path<i32>, query: Query<_>, headers: HeaderMap| {
+                get_image_by_id_handler(id, query, headers, state_2.clone())
+            }),
+        )
+        .route(
+            "/gallery/:id/:size",
+            get(move |path: Path<(i32, String)>, headers: HeaderMap| {
+                get_image_variant_handler(path, headers, state_11.clone())
+            }),
+        )
+        .route(
+            "/webmention",
+            post(move |form| receive_webmention_handler(form, state_3.clone())),
+        )
+        .route(
+            "/newsletter/one-click/:token",
+            post(move |token: Path<String>| one_click_unsubscribe_handler(token, state_10.clone())),
+        )
+        // ActivityPub federation
+        .route(
+            "/.well-known/webfinger",
+            get(move |query: Query<_>| webfinger_handler(query, state_4.clone())),
+        )
+        .route(
+            "/ap/actors/:name",
+            get(move |name: Path<String>| actor_handler(name, state_5.clone())),
+        )
+        .route(
+            "/ap/actors/:name/outbox",
+            get(move |name: Path<String>| outbox_handler(name, state_6.clone())),
+        )
+        .route(
+            "/ap/actors/:name/followers",
+            get(move |name: Path<String>| followers_handler(name, state_7.clone())),
+        )
+        .route(
+            "/ap/actors/:name/articles/:slug",
+            get(move |path: Path<(String, String)>| article_handler(path, state_8.clone())),
+        )
+        .route(
+            "/ap/actors/:name/inbox",
+            post(move |name: Path<String>, headers: HeaderMap, body: Bytes| {
+                inbox_handler(name, headers, body, state_9.clone())
+            }),
         )
         //.layer(axum::Extension(Arc::new(state.clone())))
         .layer(session_layer);
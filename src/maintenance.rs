@@ -0,0 +1,354 @@
+//! ## Maintenance
+//! The files of the media store and the articles in [`ARTICLES_DIR`] can drift from
+//! `image_metadata` and `post_metadata`, when a write fails half way or files are edited by hand.
+//! [`collect_orphans`] compares both sides and reports files without rows and rows without files.
+//! It runs from the "Manutenção" admin page, or on start with
+//! `--collect-orphans[=dry-run|quarantine|delete]`, which exits once done.
+//!
+//! Stray files are only touched when asked: [`OrphanAction::Quarantine`] moves them under
+//! [`QUARANTINE_DIR`], keeping their path inside `data/`, so they can be put back, or under
+//! `quarantine/` on an object storage, and [`OrphanAction::Delete`] removes them. Files changed in
+//! the last [`GRACE_PERIOD`] are left alone, as an upload writes it's file before it's row. Rows
+//! without files are only reported, as restoring a backup may bring their files back.
+
+use crate::api::images::ImageSize;
+use crate::api::maintenance::{MissingFileModel, OrphanAction, OrphanReportModel};
+use crate::gallery::{media::MediaStore, mime::ImageType, variants::variant_path};
+use chrono::Utc;
+use entities::prelude::{ImageMetadata, PostMetadata};
+use sea_orm::{DatabaseConnection, DbErr, EntityTrait};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+use tokio::fs;
+
+/// Where articles are saved, as `<slug>.md`.
+pub const ARTICLES_DIR: &str = "data";
+
+/// Where quarantined files are moved, in a directory for each run.
+pub const QUARANTINE_DIR: &str = "data/quarantine";
+
+/// How old a file must be to be taken as a stray.
+pub const GRACE_PERIOD: Duration = Duration::from_secs(60 * 60);
+
+/// The canonical path of `path`, when it exists.
+async fn existing(path: &str) -> Option<PathBuf> {
+    fs::canonicalize(path).await.ok()
+}
+
+/// The articles in [`ARTICLES_DIR`], with the path they're reached by and their canonical one.
+async fn article_files() -> Vec<(String, PathBuf)> {
+    let mut files = vec![];
+    let Ok(mut entries) = fs::read_dir(ARTICLES_DIR).await else {
+        return files;
+    };
+
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+        let is_article = entry
+            .file_type()
+            .await
+            .is_ok_and(|file_type| !file_type.is_dir())
+            && path.extension().is_some_and(|extension| extension == "md");
+        if is_article {
+            let canonical = fs::canonicalize(&path).await.unwrap_or(path.clone());
+            files.push((path.to_string_lossy().into_owned(), canonical));
+        }
+    }
+
+    files.sort();
+    files
+}
+
+/// Whether the file was changed during the last [`GRACE_PERIOD`], so may be in the middle of
+/// being saved.
+fn is_recent(modified: Option<SystemTime>) -> bool {
+    modified
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .map_or(true, |age| age < GRACE_PERIOD)
+}
+
+/// Move an article to the same place inside the quarantine named `run`.
+async fn quarantine_article(path: &str, run: &str) -> Result<(), String> {
+    let relative = Path::new(path)
+        .strip_prefix(ARTICLES_DIR)
+        .map_err(|err| err.to_string())?;
+    let target = Path::new(QUARANTINE_DIR).join(run).join(relative);
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|err| err.to_string())?;
+    }
+    fs::rename(path, target)
+        .await
+        .map_err(|err| err.to_string())
+}
+
+/// The files of the store that no path of `known` leads to, and that are older than
+/// [`GRACE_PERIOD`]. Both sides are normalized by the store, so `data/uploads/./ab/x.png` or a
+/// symlink still match the file they lead to.
+async fn stray_uploads(
+    media: &dyn MediaStore,
+    known: &HashSet<String>,
+    failures: &mut Vec<String>,
+) -> Vec<String> {
+    let uploads = match media.list().await {
+        Ok(uploads) => uploads,
+        Err(err) => {
+            failures.push(format!("Listing the uploads: {}", err));
+            return vec![];
+        }
+    };
+
+    let mut normalized_known = HashSet::new();
+    for path in known {
+        normalized_known.insert(media.normalize(path).await);
+    }
+
+    let mut stray = vec![];
+    for upload in uploads {
+        if !is_recent(upload.modified)
+            && !normalized_known.contains(&media.normalize(&upload.path).await)
+        {
+            stray.push(upload.path);
+        }
+    }
+    stray
+}
+
+/// Quarantine or delete the stray uploads as `action` says, giving the ones that failed with why.
+async fn handle_stray_uploads(
+    media: &dyn MediaStore,
+    paths: &[String],
+    action: OrphanAction,
+    run: &str,
+) -> Vec<String> {
+    let mut failures = vec![];
+    for path in paths {
+        let handled = match action {
+            OrphanAction::DryRun => Ok(()),
+            OrphanAction::Quarantine => media.quarantine(path, run).await,
+            OrphanAction::Delete => media.delete(path).await,
+        };
+        if let Err(err) = handled {
+            failures.push(format!("{}: {}", path, err));
+        }
+    }
+    failures
+}
+
+/// Compare the files with the database, then report, quarantine or delete the stray files as
+/// `action` says.
+pub async fn collect_orphans(
+    conn: &DatabaseConnection,
+    media: &dyn MediaStore,
+    action: OrphanAction,
+) -> Result<OrphanReportModel, DbErr> {
+    let mut report = OrphanReportModel {
+        action,
+        ..Default::default()
+    };
+
+    // Images, with every variant they may have
+    let mut known_uploads = HashSet::new();
+    for image in ImageMetadata::find().all(conn).await? {
+        if !media.exists(&image.image_path).await.unwrap_or(false) {
+            report.images_without_file.push(MissingFileModel {
+                id: image.id,
+                path: image.image_path.clone(),
+                name: image.original_filename.clone(),
+            });
+        }
+
+        if let Some(image_type) = ImageType::from_mime_type(&image.mime_type) {
+            known_uploads.extend(
+                ImageSize::ALL
+                    .into_iter()
+                    .filter_map(|size| variant_path(&image.image_path, image_type, size)),
+            );
+        }
+        known_uploads.insert(image.image_path);
+    }
+
+    // Articles
+    let mut known_articles = HashSet::new();
+    for article in PostMetadata::find().all(conn).await? {
+        match existing(&article.content_path).await {
+            Some(path) => {
+                known_articles.insert(path);
+            }
+            None => report.articles_without_file.push(MissingFileModel {
+                id: article.id,
+                path: article.content_path.clone(),
+                name: Some(article.title.clone()),
+            }),
+        }
+    }
+
+    report.stray_uploads = stray_uploads(media, &known_uploads, &mut report.failures).await;
+    for (path, canonical) in article_files().await {
+        let modified = fs::metadata(&path)
+            .await
+            .ok()
+            .and_then(|metadata| metadata.modified().ok());
+        if !known_articles.contains(&canonical) && !is_recent(modified) {
+            report.stray_articles.push(path);
+        }
+    }
+
+    let run = Utc::now().format("%Y%m%d-%H%M%S").to_string();
+    let failures = handle_stray_uploads(media, &report.stray_uploads, action, &run).await;
+    report.failures.extend(failures);
+    for path in report.stray_articles.clone() {
+        let handled = match action {
+            OrphanAction::DryRun => Ok(()),
+            OrphanAction::Quarantine => quarantine_article(&path, &run).await,
+            OrphanAction::Delete => fs::remove_file(&path).await.map_err(|err| err.to_string()),
+        };
+        if let Err(err) = handled {
+            report.failures.push(format!("{}: {}", path, err));
+        }
+    }
+    if action == OrphanAction::Quarantine {
+        report.quarantine_dir = Some(format!("{}/{}", QUARANTINE_DIR, run));
+    }
+
+    Ok(report)
+}
+
+/// The report as text, for the command line.
+pub fn format_report(report: &OrphanReportModel) -> String {
+    let mut text = String::new();
+    let mut section = |title: &str, lines: Vec<String>| {
+        text.push_str(&format!("{} ({}):\n", title, lines.len()));
+        for line in lines {
+            text.push_str(&format!("  {}\n", line));
+        }
+    };
+    let missing = |rows: &[MissingFileModel]| -> Vec<String> {
+        rows.iter()
+            .map(|row| match &row.name {
+                Some(name) => format!("#{} {} ({})", row.id, row.path, name),
+                None => format!("#{} {}", row.id, row.path),
+            })
+            .collect()
+    };
+
+    section("Uploads without an image", report.stray_uploads.clone());
+    section("Articles without a row", report.stray_articles.clone());
+    section(
+        "Images without a file",
+        missing(&report.images_without_file),
+    );
+    section(
+        "Articles without a file",
+        missing(&report.articles_without_file),
+    );
+    section("Failures", report.failures.clone());
+
+    match (report.action, &report.quarantine_dir) {
+        (OrphanAction::DryRun, _) => text.push_str("Dry run, nothing was changed.\n"),
+        (OrphanAction::Quarantine, Some(dir)) => {
+            text.push_str(&format!("Stray files were moved to {}.\n", dir))
+        }
+        (OrphanAction::Quarantine, None) => (),
+        (OrphanAction::Delete, _) => text.push_str("Stray files were deleted.\n"),
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gallery::media::tests::MemoryStore;
+
+    fn known(paths: &[&str]) -> HashSet<String> {
+        paths.iter().map(|path| path.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn finds_stray_uploads() {
+        let media = MemoryStore::with_files(&[
+            "data/uploads/ab/kept.png",
+            "data/uploads/ab/kept.medium.webp",
+            "data/uploads/cd/stray.png",
+        ]);
+        media.put_recent("data/uploads/ef/uploading.png");
+
+        let mut failures = vec![];
+        let stray = stray_uploads(
+            &media,
+            &known(&[
+                "data/uploads/ab/kept.png",
+                "data/uploads/ab/kept.medium.webp",
+            ]),
+            &mut failures,
+        )
+        .await;
+        assert_eq!(stray, vec!["data/uploads/cd/stray.png"]);
+        assert!(failures.is_empty());
+    }
+
+    #[tokio::test]
+    async fn matches_paths_spelled_differently() {
+        let media = MemoryStore::with_files(&["data/uploads/ab/x.png", "data/uploads/cd/y.png"]);
+
+        let mut failures = vec![];
+        let stray = stray_uploads(
+            &media,
+            &known(&["data/uploads/./ab/x.png", "data/uploads/ab/../cd/y.png"]),
+            &mut failures,
+        )
+        .await;
+        assert!(stray.is_empty(), "{:?}", stray);
+    }
+
+    #[tokio::test]
+    async fn quarantines_stray_uploads() {
+        let media =
+            MemoryStore::with_files(&["data/uploads/ab/kept.png", "data/uploads/cd/stray.png"]);
+        let stray = vec!["data/uploads/cd/stray.png".to_string()];
+
+        let failures = handle_stray_uploads(&media, &stray, OrphanAction::DryRun, "run").await;
+        assert!(failures.is_empty());
+        assert_eq!(media.list().await.unwrap().len(), 2);
+
+        let failures = handle_stray_uploads(&media, &stray, OrphanAction::Quarantine, "run").await;
+        assert!(failures.is_empty());
+        assert_eq!(
+            media.paths(),
+            vec![
+                "data/uploads/ab/kept.png",
+                "quarantine/run/data/uploads/cd/stray.png"
+            ]
+        );
+        // Quarantined files aren't listed, so they aren't strays anymore
+        let mut failures = vec![];
+        let stray_after =
+            stray_uploads(&media, &known(&["data/uploads/ab/kept.png"]), &mut failures).await;
+        assert!(stray_after.is_empty());
+    }
+
+    #[tokio::test]
+    async fn deletes_stray_uploads_and_reports_failures() {
+        let media =
+            MemoryStore::with_files(&["data/uploads/ab/kept.png", "data/uploads/cd/stray.png"]);
+        let stray = vec![
+            "data/uploads/cd/stray.png".to_string(),
+            "data/uploads/ef/gone.png".to_string(),
+        ];
+
+        let failures = handle_stray_uploads(&media, &stray, OrphanAction::Delete, "run").await;
+        assert_eq!(
+            failures,
+            vec!["data/uploads/ef/gone.png: The file doesn't exist."]
+        );
+        assert_eq!(media.paths(), vec!["data/uploads/ab/kept.png"]);
+
+        let failures = handle_stray_uploads(&media, &stray, OrphanAction::Quarantine, "run").await;
+        assert_eq!(failures.len(), 2);
+    }
+}
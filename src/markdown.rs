@@ -0,0 +1,119 @@
+//! ## Markdown
+//! Turns the article's markdown into the HTML shown by `ArticleReader`. Besides CommonMark, it
+//! supports tables, footnotes, strikethrough, task lists, our shortcodes, admonition blocks and
+//! math formulas.
+//!
+//! Admonitions and formulas are rendered apart from the markdown and swapped in by a
+//! placeholder, so markdown doesn't touch the generated HTML.
+
+pub mod admonitions;
+pub mod math;
+pub mod shortcodes;
+
+use crate::api::images::ImagePreview;
+use pulldown_cmark::{html, Options, Parser};
+use std::collections::HashMap;
+
+/// Private use characters that surround the index of a placeholder.
+const PLACEHOLDER_START: char = '\u{E000}';
+const PLACEHOLDER_END: char = '\u{E001}';
+
+/// Render an article to HTML. Set `preview` when showing the article to it's writer, so mistakes
+/// like unknown shortcodes are shown instead of hidden.
+///
+/// Math is only converted when rendering on the server.
+pub fn render_article(content: &str, preview: bool) -> String {
+    render_article_with_images(content, preview, &HashMap::new(), &HashMap::new())
+}
+
+/// Render an article with the gallery alt texts, sizes and placeholders of it's images, see
+/// `crate::api::images::image_alt_texts` and `crate::api::images::image_previews`.
+pub fn render_article_with_images(
+    content: &str,
+    preview: bool,
+    alt_texts: &HashMap<i32, String>,
+    previews: &HashMap<i32, ImagePreview>,
+) -> String {
+    let content = shortcodes::expand(content, preview, alt_texts, previews);
+    let mut placeholders = vec![];
+    let inner_html = render_blocks(&content, &mut placeholders);
+    restore(&inner_html, &placeholders)
+}
+
+/// HTML kept apart from the markdown. Block placeholders stand alone on their paragraph, which
+/// is dropped when restoring them.
+struct Placeholder {
+    html: String,
+    block: bool,
+}
+
+fn render_blocks(content: &str, placeholders: &mut Vec<Placeholder>) -> String {
+    let content = admonitions::expand(content, |kind, title, inner| {
+        let inner_html = render_blocks(inner, placeholders);
+        placeholder(
+            placeholders,
+            admonitions::render(kind, title, &inner_html),
+            true,
+        )
+    });
+
+    #[cfg(feature = "ssr")]
+    let content = math::expand(&content, |latex, display| {
+        placeholder(placeholders, math::render(latex, display), display)
+    });
+
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_FOOTNOTES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TASKLISTS);
+
+    let parser = Parser::new_ext(&content, options);
+    let mut inner_html = String::new();
+    html::push_html(&mut inner_html, parser);
+
+    inner_html
+}
+
+fn placeholder(placeholders: &mut Vec<Placeholder>, html: String, block: bool) -> String {
+    placeholders.push(Placeholder { html, block });
+    format!(
+        "{}{}{}",
+        PLACEHOLDER_START,
+        placeholders.len() - 1,
+        PLACEHOLDER_END
+    )
+}
+
+/// Swap the placeholders back by their HTML. Placeholders are replaced from the last to the first,
+/// as a block may have other placeholders inside it.
+fn restore(inner_html: &str, placeholders: &[Placeholder]) -> String {
+    let mut inner_html = inner_html.to_string();
+
+    for (index, placeholder) in placeholders.iter().enumerate().rev() {
+        let token = format!("{}{}{}", PLACEHOLDER_START, index, PLACEHOLDER_END);
+        if placeholder.block {
+            inner_html = inner_html.replace(&format!("<p>{}</p>", token), &placeholder.html);
+        }
+        inner_html = inner_html.replace(&token, &placeholder.html);
+    }
+
+    inner_html
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_blocks_drop_their_paragraph() {
+        let html = render_article("$x$\n\n$$y$$\n\n> [!NOTE]\n> $z$\n", false);
+
+        assert!(html.starts_with("<p><math"));
+        assert!(html.contains("</math></p>\n<math"));
+        assert!(html.contains("display=\"block\""));
+        assert!(html.contains("role=\"note\"><p class=\"md-admonition-title\">Note</p><p><math"));
+        assert!(!html.contains("<p><div"));
+        assert!(!html.contains(PLACEHOLDER_START));
+    }
+}
@@ -0,0 +1,203 @@
+//! ## Admonitions
+//! GitHub-style admonition blocks, written as a blockquote starting with the kind of the block:
+//!
+//! ```markdown
+//! > [!NOTE]
+//! > Useful information that users should know.
+//! ```
+//!
+//! The kinds are `NOTE`, `TIP`, `IMPORTANT`, `WARNING` and `CAUTION`. Text after the kind, as in
+//! `> [!TIP] Before you start`, replaces the default title of the block.
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AdmonitionKind {
+    Note,
+    Tip,
+    Important,
+    Warning,
+    Caution,
+}
+
+impl AdmonitionKind {
+    fn parse(marker: &str) -> Option<Self> {
+        match marker {
+            "NOTE" => Some(Self::Note),
+            "TIP" => Some(Self::Tip),
+            "IMPORTANT" => Some(Self::Important),
+            "WARNING" => Some(Self::Warning),
+            "CAUTION" => Some(Self::Caution),
+            _ => None,
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Self::Note => "Note",
+            Self::Tip => "Tip",
+            Self::Important => "Important",
+            Self::Warning => "Warning",
+            Self::Caution => "Caution",
+        }
+    }
+
+    /// The background of the block, the border and shadow are the same of `RetroContainer`.
+    fn class(&self) -> &'static str {
+        match self {
+            Self::Note => "bg-sky-300",
+            Self::Tip => "bg-green-300",
+            Self::Important => "bg-purple-300",
+            Self::Warning => "bg-yellow-300",
+            Self::Caution => "bg-red-400",
+        }
+    }
+}
+
+/// Replace every admonition block on `content` by the value returned from `replace`, which
+/// receives the kind of the block, it's custom title and it's inner markdown. Fenced code blocks
+/// are left untouched.
+pub fn expand(
+    content: &str,
+    mut replace: impl FnMut(AdmonitionKind, Option<&str>, &str) -> String,
+) -> String {
+    let mut expanded = String::with_capacity(content.len());
+    let mut lines = content.split_inclusive('\n').peekable();
+    let mut fence: Option<&str> = None;
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim_start();
+
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            expanded.push_str(line);
+            continue;
+        }
+
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            fence = Some(&trimmed[..3]);
+            expanded.push_str(line);
+            continue;
+        }
+
+        let (kind, title) = match quoted(line)
+            .map(str::trim)
+            .and_then(|marker| marker.strip_prefix("[!"))
+            .and_then(|marker| marker.split_once(']'))
+            .and_then(|(kind, title)| {
+                let title = title.trim();
+                AdmonitionKind::parse(kind).map(|kind| (kind, (!title.is_empty()).then_some(title)))
+            }) {
+            Some(marker) => marker,
+            None => {
+                expanded.push_str(line);
+                continue;
+            }
+        };
+
+        let mut inner = String::new();
+        while let Some(quote) = lines.peek().copied().and_then(quoted) {
+            inner.push_str(quote);
+            lines.next();
+        }
+
+        expanded.push_str(&replace(kind, title, &inner));
+        expanded.push_str("\n\n");
+    }
+
+    expanded
+}
+
+/// The content of a blockquote line without the `>` marker, or `None` if it isn't quoted.
+fn quoted(line: &str) -> Option<&str> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    if indent > 3 {
+        return None;
+    }
+
+    let quote = line[indent..].strip_prefix('>')?;
+    Some(quote.strip_prefix(' ').unwrap_or(quote))
+}
+
+/// Wrap the rendered content of an admonition block, under `title` or the title of it's kind.
+pub fn render(kind: AdmonitionKind, title: Option<&str>, inner_html: &str) -> String {
+    format!(
+        "<div class=\"md-admonition shadow-[#000000aa_8px_8px] rounded-[18px] border-solid border-transparent border-[12px] [border-image:url('/border.png')_8_stretch] {}\" role=\"note\"><p class=\"md-admonition-title\">{}</p>{}</div>",
+        kind.class(),
+        super::shortcodes::escape(title.unwrap_or(kind.title())),
+        inner_html
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand_marked(content: &str) -> String {
+        expand(content, |kind, title, inner| {
+            format!("[{:?}|{}|{}]", kind, title.unwrap_or("-"), inner)
+        })
+    }
+
+    #[test]
+    fn expands_every_kind() {
+        for (marker, kind) in [
+            ("NOTE", AdmonitionKind::Note),
+            ("TIP", AdmonitionKind::Tip),
+            ("IMPORTANT", AdmonitionKind::Important),
+            ("WARNING", AdmonitionKind::Warning),
+            ("CAUTION", AdmonitionKind::Caution),
+        ] {
+            let expanded = expand_marked(&format!("> [!{}]\n> Text\n", marker));
+            assert_eq!(expanded, format!("[{:?}|-|Text\n]\n\n", kind));
+        }
+    }
+
+    #[test]
+    fn ends_on_the_first_line_out_of_the_quote() {
+        let expanded = expand_marked("Before\n\n> [!NOTE]\n> One\n>\n> Two\nAfter\n");
+        assert_eq!(expanded, "Before\n\n[Note|-|One\n\nTwo\n]\n\nAfter\n");
+    }
+
+    #[test]
+    fn keeps_unknown_kinds_and_plain_quotes() {
+        for content in [
+            "> [!DANGER]\n> Text\n",
+            "> [!note]\n> Text\n",
+            "> [NOTE]\n> Text\n",
+            "> Just a quote\n",
+            "    > [!NOTE]\n",
+        ] {
+            assert_eq!(expand_marked(content), content);
+        }
+    }
+
+    #[test]
+    fn leaves_code_blocks_untouched() {
+        let content = "```markdown\n> [!NOTE]\n> Text\n```\n~~~\n> [!TIP]\n~~~\n";
+        assert_eq!(expand_marked(content), content);
+    }
+
+    #[test]
+    fn nested_blocks_reach_the_inner_markdown() {
+        let expanded = expand_marked("> [!WARNING]\n> > [!TIP]\n> > Inner\n> Outer\n");
+        assert_eq!(expanded, "[Warning|-|> [!TIP]\n> Inner\nOuter\n]\n\n");
+        assert_eq!(expand_marked("> [!TIP]\n> Inner\n"), "[Tip|-|Inner\n]\n\n");
+    }
+
+    #[test]
+    fn escapes_custom_titles() {
+        let expanded = expand_marked("> [!TIP] Don't \"quote\" <me>\n> Text\n");
+        assert_eq!(expanded, "[Tip|Don't \"quote\" <me>|Text\n]\n\n");
+
+        let html = render(
+            AdmonitionKind::Tip,
+            Some("A \"quoted\" <title>"),
+            "<p>Text</p>",
+        );
+        assert!(html.contains(
+            "<p class=\"md-admonition-title\">A &quot;quoted&quot; &lt;title&gt;</p><p>Text</p>"
+        ));
+        assert!(render(AdmonitionKind::Caution, None, "").contains(">Caution</p>"));
+    }
+}
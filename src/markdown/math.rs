@@ -0,0 +1,214 @@
+//! ## Math
+//! TeX formulas written as `$...$` (inline) or `$$...$$` (display) are converted to MathML on
+//! the server, so readers don't need any JavaScript to see them. Use `\$` for a literal dollar.
+
+/// Replace every formula on `content` by the value returned from `replace`, which receives the
+/// TeX source and whether it's a display formula. Code blocks and code spans are left untouched.
+pub fn expand(content: &str, mut replace: impl FnMut(&str, bool) -> String) -> String {
+    let mut expanded = String::with_capacity(content.len());
+    let mut text = String::new();
+    let mut fence: Option<&str> = None;
+
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        match fence {
+            Some(marker) => {
+                if trimmed.starts_with(marker) {
+                    fence = None;
+                }
+                expanded.push_str(line);
+            }
+            None if trimmed.starts_with("```") || trimmed.starts_with("~~~") => {
+                expanded.push_str(&expand_text(&text, &mut replace));
+                text.clear();
+                fence = Some(&trimmed[..3]);
+                expanded.push_str(line);
+            }
+            None => text.push_str(line),
+        }
+    }
+
+    expanded.push_str(&expand_text(&text, &mut replace));
+    expanded
+}
+
+fn expand_text(text: &str, replace: &mut impl FnMut(&str, bool) -> String) -> String {
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find(['$', '`', '\\']) {
+        expanded.push_str(&rest[..start]);
+        let tail = &rest[start..];
+
+        if tail.starts_with('\\') {
+            // Keep escapes as they are, markdown will take care of them.
+            let escaped = tail.chars().nth(1).map(char::len_utf8).unwrap_or(0);
+            expanded.push_str(&tail[..1 + escaped]);
+            rest = &tail[1 + escaped..];
+        } else if tail.starts_with('`') {
+            // Code spans close on a run with the same amount of backticks.
+            let ticks = tail.len() - tail.trim_start_matches('`').len();
+            let after = &tail[ticks..];
+            let end = find_run(after, '`', ticks).map_or(tail.len(), |end| ticks + end + ticks);
+            expanded.push_str(&tail[..end]);
+            rest = &tail[end..];
+        } else if let Some(formula) = tail.strip_prefix("$$") {
+            match formula.find("$$") {
+                Some(end) if !formula[..end].trim().is_empty() => {
+                    expanded.push_str(&replace(formula[..end].trim(), true));
+                    rest = &formula[end + 2..];
+                }
+                _ => {
+                    expanded.push_str("$$");
+                    rest = formula;
+                }
+            }
+        } else {
+            let formula = &tail[1..];
+            match inline_end(formula) {
+                Some(end) => {
+                    expanded.push_str(&replace(&formula[..end], false));
+                    rest = &formula[end + 1..];
+                }
+                None => {
+                    expanded.push('$');
+                    rest = formula;
+                }
+            }
+        }
+    }
+
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Find a run of exactly `count` repetitions of `c`.
+fn find_run(text: &str, c: char, count: usize) -> Option<usize> {
+    let mut offset = 0;
+    while let Some(start) = text[offset..].find(c) {
+        let start = offset + start;
+        let run = text[start..].len() - text[start..].trim_start_matches(c).len();
+        if run == count {
+            return Some(start);
+        }
+        offset = start + run;
+    }
+    None
+}
+
+/// Inline formulas can't start or end with a space or span more than one line, and the closing
+/// `$` can't be followed by a digit, so prices like "$5 and $10" are kept as text.
+fn inline_end(formula: &str) -> Option<usize> {
+    if formula.starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    let mut escaped = false;
+    for (index, c) in formula.char_indices() {
+        match c {
+            '\n' => return None,
+            '\\' => escaped = !escaped,
+            '$' if !escaped => {
+                let before = formula[..index].chars().last()?;
+                let after = formula[index + 1..].chars().next();
+                if before.is_whitespace() || after.is_some_and(|c| c.is_ascii_digit()) {
+                    return None;
+                }
+                return Some(index);
+            }
+            _ => escaped = false,
+        }
+    }
+
+    None
+}
+
+/// Convert a TeX formula to MathML. Formulas that fail to convert are shown as code.
+#[cfg(feature = "ssr")]
+pub fn render(latex: &str, display: bool) -> String {
+    use latex2mathml::{latex_to_mathml, DisplayStyle};
+
+    let style = if display {
+        DisplayStyle::Block
+    } else {
+        DisplayStyle::Inline
+    };
+
+    match latex_to_mathml(latex, style) {
+        Ok(mathml) => mathml,
+        Err(_) => format!(
+            "<code class=\"md-math-error\">{}</code>",
+            super::shortcodes::escape(latex)
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand_marked(content: &str) -> String {
+        expand(content, |latex, display| {
+            if display {
+                format!("[[{}]]", latex)
+            } else {
+                format!("[{}]", latex)
+            }
+        })
+    }
+
+    #[test]
+    fn expands_inline_and_display_formulas() {
+        assert_eq!(
+            expand_marked("Area $\\pi r^2$ here"),
+            "Area [\\pi r^2] here"
+        );
+        assert_eq!(
+            expand_marked("$$\n  \\sum_i x_i\n$$\nAfter"),
+            "[[\\sum_i x_i]]\nAfter"
+        );
+        assert_eq!(expand_marked("$a$ and $b$"), "[a] and [b]");
+    }
+
+    #[test]
+    fn keeps_escaped_dollars() {
+        assert_eq!(expand_marked("Costs \\$5 or \\$10"), "Costs \\$5 or \\$10");
+        assert_eq!(expand_marked("$a \\$ b$"), "[a \\$ b]");
+    }
+
+    #[test]
+    fn keeps_unclosed_delimiters_and_prices() {
+        for content in [
+            "Only $x here",
+            "Only $$x here",
+            "Empty $$ $$ display",
+            "$5 and $10",
+            "$ x$",
+            "$x $",
+            "$x\ny$",
+        ] {
+            assert_eq!(expand_marked(content), content);
+        }
+    }
+
+    #[test]
+    fn leaves_code_untouched() {
+        let content = "```\n$x$\n~~~\n$$y$$\n```\n";
+        assert_eq!(expand_marked(content), content);
+
+        assert_eq!(expand_marked("`$x$` and $y$"), "`$x$` and [y]");
+        assert_eq!(expand_marked("``a ` $x$`` $y$"), "``a ` $x$`` [y]");
+        assert_eq!(expand_marked("~~~\n$x$\n~~~\n$y$"), "~~~\n$x$\n~~~\n[y]");
+    }
+
+    #[cfg(feature = "ssr")]
+    #[test]
+    fn shows_invalid_formulas_as_code() {
+        assert!(render("x^2", false).starts_with("<math"));
+        assert!(render("x^2", true).contains("display=\"block\""));
+        assert_eq!(
+            render("\\frac{<b>", false),
+            "<code class=\"md-math-error\">\\frac{&lt;b&gt;</code>"
+        );
+    }
+}
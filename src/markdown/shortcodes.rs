@@ -0,0 +1,492 @@
+//! ## Shortcodes
+//! Shortcodes are small tags written inside the article's markdown, like
+//! `{{< image id=12 caption="A bird" >}}`, that expand to HTML before the markdown itself is
+//! rendered. They exist so writers don't need to hand-type gallery URLs or embed markup.
+//!
+//! Available shortcodes:
+//! - `image id=<gallery id> [caption="..."] [alt="..."]`, the alt text defaults to the one on the
+//!   gallery
+//! - `youtube id=<video id> [title="..."]`
+//! - `video src=<url> [caption="..."]`
+//! - `godot game=<build name> [title="..."]`, for the Godot builds in `public/`, like `Stellarbonds`.
+
+use crate::api::images::{image_srcset, image_url, ImagePreview, ImageSize, PLACEHOLDER_ONLOAD};
+use std::collections::HashMap;
+
+/// A shortcode parsed from the article, with it's name and `key=value` arguments in order.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Shortcode {
+    pub name: String,
+    pub args: Vec<(String, String)>,
+}
+
+impl Shortcode {
+    pub fn arg(&self, key: &str) -> Option<&str> {
+        self.args
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+const OPEN: &str = "{{<";
+const CLOSE: &str = ">}}";
+
+/// Expand every shortcode on `content`. Shortcodes inside fenced code blocks are left untouched.
+///
+/// When `preview` is enabled, unknown or malformed shortcodes are replaced by a visible warning
+/// for the writer; otherwise they are hidden from readers. Images without an `alt` take theirs
+/// from `alt_texts`, by gallery id, and get their size and placeholder from `previews`.
+pub fn expand(
+    content: &str,
+    preview: bool,
+    alt_texts: &HashMap<i32, String>,
+    previews: &HashMap<i32, ImagePreview>,
+) -> String {
+    outside_fences(content, |line| {
+        expand_line(line, preview, alt_texts, previews)
+    })
+}
+
+/// The gallery ids of the `image` shortcodes on `content`, in order.
+pub fn image_ids(content: &str) -> Vec<i32> {
+    let mut ids = vec![];
+
+    outside_fences(content, |line| {
+        let mut rest = line;
+        while let Some(start) = rest.find(OPEN) {
+            let after_open = &rest[start + OPEN.len()..];
+            let Some(end) = after_open.find(CLOSE) else {
+                break;
+            };
+            if let Some(id) = parse(&after_open[..end])
+                .filter(|shortcode| shortcode.name == "image")
+                .and_then(|shortcode| shortcode.arg("id").and_then(|id| id.parse::<i32>().ok()))
+            {
+                if !ids.contains(&id) {
+                    ids.push(id);
+                }
+            }
+            rest = &after_open[end + CLOSE.len()..];
+        }
+        line.to_string()
+    });
+
+    ids
+}
+
+/// Map the lines of `content` that aren't inside fenced code blocks.
+pub(crate) fn outside_fences(content: &str, mut map: impl FnMut(&str) -> String) -> String {
+    let mut mapped = String::with_capacity(content.len());
+    let mut fence: Option<&str> = None;
+
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        match fence {
+            Some(marker) => {
+                if trimmed.starts_with(marker) {
+                    fence = None;
+                }
+                mapped.push_str(line);
+            }
+            None if trimmed.starts_with("```") || trimmed.starts_with("~~~") => {
+                fence = Some(&trimmed[..3]);
+                mapped.push_str(line);
+            }
+            None => mapped.push_str(&map(line)),
+        }
+    }
+
+    mapped
+}
+
+fn expand_line(
+    line: &str,
+    preview: bool,
+    alt_texts: &HashMap<i32, String>,
+    previews: &HashMap<i32, ImagePreview>,
+) -> String {
+    let mut expanded = String::with_capacity(line.len());
+    let mut rest = line;
+
+    while let Some(start) = rest.find(OPEN) {
+        expanded.push_str(&rest[..start]);
+        let after_open = &rest[start + OPEN.len()..];
+
+        match after_open.find(CLOSE) {
+            Some(end) => {
+                let source = &rest[start..start + OPEN.len() + end + CLOSE.len()];
+                expanded.push_str(&match parse(&after_open[..end]) {
+                    Some(shortcode) => render(&shortcode, source, preview, alt_texts, previews),
+                    None => warning(source, "malformed shortcode", preview),
+                });
+                rest = &after_open[end + CLOSE.len()..];
+            }
+            None => {
+                expanded.push_str(&warning(
+                    rest[start..].trim_end(),
+                    "unclosed shortcode",
+                    preview,
+                ));
+                if rest.ends_with('\n') {
+                    expanded.push('\n');
+                }
+                rest = "";
+            }
+        }
+    }
+
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Parse the inside of a shortcode, like ` image id=12 caption="A bird" `.
+pub fn parse(source: &str) -> Option<Shortcode> {
+    let mut chars = source.trim().chars().peekable();
+    let mut shortcode = Shortcode::default();
+
+    while let Some(c) = chars.peek() {
+        if c.is_whitespace() {
+            break;
+        }
+        shortcode.name.push(chars.next()?);
+    }
+
+    if shortcode.name.is_empty()
+        || !shortcode
+            .name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        return None;
+    }
+
+    loop {
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let mut key = String::new();
+        while let Some(&c) = chars.peek() {
+            if c == '=' || c.is_whitespace() {
+                break;
+            }
+            key.push(c);
+            chars.next();
+        }
+
+        if key.is_empty() || chars.next() != Some('=') {
+            return None;
+        }
+
+        let mut value = String::new();
+        match chars.peek() {
+            Some('"') => {
+                chars.next();
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => value.push(chars.next()?),
+                        c => value.push(c),
+                    }
+                }
+            }
+            _ => {
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() {
+                        break;
+                    }
+                    value.push(c);
+                    chars.next();
+                }
+            }
+        }
+
+        shortcode.args.push((key, value));
+    }
+
+    Some(shortcode)
+}
+
+fn render(
+    shortcode: &Shortcode,
+    source: &str,
+    preview: bool,
+    alt_texts: &HashMap<i32, String>,
+    previews: &HashMap<i32, ImagePreview>,
+) -> String {
+    match shortcode.name.as_str() {
+        "image" => match shortcode.arg("id").and_then(|id| id.parse::<i32>().ok()) {
+            // Saving the article would fail, so tell the writer before
+            Some(id) if preview && !alt_texts.contains_key(&id) => warning(
+                source,
+                "the image needs alt text on the gallery",
+                preview,
+            ),
+            Some(id) => {
+                let caption = shortcode.arg("caption");
+                let alt = shortcode
+                    .arg("alt")
+                    .or(alt_texts.get(&id).map(String::as_str))
+                    .or(caption)
+                    .unwrap_or_default();
+                figure(
+                    &format!(
+                        "<img class=\"w-full h-auto\" src=\"{}\" srcset=\"{}\" sizes=\"(min-width: 1024px) 1024px, 100vw\" alt=\"{}\" loading=\"lazy\" decoding=\"async\"{}>",
+                        image_url(id, Some(ImageSize::Large)),
+                        image_srcset(id),
+                        escape(alt),
+                        preview_attributes(previews.get(&id))
+                    ),
+                    caption,
+                )
+            }
+            None => warning(source, "`image` needs a numeric `id`", preview),
+        },
+
+        "youtube" => match shortcode.arg("id").filter(|id| is_token(id)) {
+            Some(id) => embed(
+                &format!("https://www.youtube-nocookie.com/embed/{}", id),
+                shortcode.arg("title").unwrap_or("YouTube video"),
+                "accelerometer; encrypted-media; gyroscope; picture-in-picture; fullscreen",
+            ),
+            None => warning(source, "`youtube` needs the video `id`", preview),
+        },
+
+        "video" => match shortcode.arg("src").filter(|src| is_safe_url(src)) {
+            Some(src) => figure(
+                &format!(
+                    "<video class=\"w-full h-auto\" controls preload=\"metadata\" src=\"{0}\"><a href=\"{0}\">{0}</a></video>",
+                    escape(src)
+                ),
+                shortcode.arg("caption"),
+            ),
+            None => warning(source, "`video` needs a http(s) or relative `src`", preview),
+        },
+
+        "godot" => match shortcode.arg("game").filter(|game| is_token(game)) {
+            Some(game) => {
+                let title = shortcode.arg("title").unwrap_or(game);
+                format!(
+                    "{}<p class=\"text-center\"><a class=\"hover:underline\" target=\"_blank\" href=\"/{}.html\">{}</a></p>",
+                    embed(&format!("/{}.html", game), title, "fullscreen; gamepad"),
+                    game,
+                    escape(title)
+                )
+            }
+            None => warning(source, "`godot` needs the build name on `game`", preview),
+        },
+
+        _ => warning(source, "unknown shortcode", preview),
+    }
+}
+
+/// The `width`, `height` and placeholder of an image, so the article doesn't move while it loads.
+fn preview_attributes(preview: Option<&ImagePreview>) -> String {
+    let Some(preview) = preview else {
+        return String::new();
+    };
+    let mut attributes = String::new();
+
+    if let (Some(width), Some(height)) = (preview.width, preview.height) {
+        attributes.push_str(&format!(" width=\"{}\" height=\"{}\"", width, height));
+    }
+    if let Some(style) = preview.style() {
+        attributes.push_str(&format!(
+            " style=\"{}\" onload=\"{}\"",
+            escape(&style),
+            PLACEHOLDER_ONLOAD
+        ));
+    }
+
+    attributes
+}
+
+fn figure(media: &str, caption: Option<&str>) -> String {
+    match caption {
+        Some(caption) => format!(
+            "<figure class=\"md-figure\">{}<figcaption>{}</figcaption></figure>",
+            media,
+            escape(caption)
+        ),
+        None => format!("<figure class=\"md-figure\">{}</figure>", media),
+    }
+}
+
+fn embed(src: &str, title: &str, allow: &str) -> String {
+    format!(
+        "<div class=\"md-embed\"><iframe src=\"{}\" title=\"{}\" allow=\"{}\" loading=\"lazy\" allowfullscreen></iframe></div>",
+        escape(src),
+        escape(title),
+        allow
+    )
+}
+
+fn warning(source: &str, reason: &str, preview: bool) -> String {
+    if preview {
+        format!(
+            "<span class=\"md-shortcode-warning\" role=\"alert\">⚠ {}: <code>{}</code></span>",
+            reason,
+            escape(source)
+        )
+    } else {
+        String::new()
+    }
+}
+
+/// Ids and build names are only made of letters, numbers, `-` and `_`.
+fn is_token(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn is_safe_url(value: &str) -> bool {
+    value.starts_with("https://")
+        || value.starts_with("http://")
+        || (value.starts_with('/') && !value.starts_with("//"))
+}
+
+/// Escape text to be placed inside HTML content or a quoted attribute.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand_plain(content: &str, preview: bool) -> String {
+        expand(content, preview, &HashMap::new(), &HashMap::new())
+    }
+
+    fn alt_texts() -> HashMap<i32, String> {
+        HashMap::from([(12, "A \"red\" bird".to_string())])
+    }
+
+    #[test]
+    fn parses_name_and_arguments() {
+        let shortcode = parse(r#" image id=12 caption="A \"quoted\" bird" "#).unwrap();
+
+        assert_eq!(shortcode.name, "image");
+        assert_eq!(shortcode.arg("id"), Some("12"));
+        assert_eq!(shortcode.arg("caption"), Some("A \"quoted\" bird"));
+        assert_eq!(shortcode.arg("alt"), None);
+    }
+
+    #[test]
+    fn rejects_malformed_shortcodes() {
+        assert_eq!(parse(""), None);
+        assert_eq!(parse("im<age id=1"), None);
+        assert_eq!(parse("image id"), None);
+        assert_eq!(parse("image =1"), None);
+        assert_eq!(parse(r#"image caption="never closed"#), None);
+    }
+
+    #[test]
+    fn expands_images_with_escaped_text() {
+        let expanded = expand(
+            r#"{{< image id=12 caption="<script>" >}}"#,
+            false,
+            &alt_texts(),
+            &HashMap::new(),
+        );
+
+        assert!(expanded.contains("src=\"/gallery/12/large\""));
+        assert!(expanded.contains("alt=\"A &quot;red&quot; bird\""));
+        assert!(expanded.contains("<figcaption>&lt;script&gt;</figcaption>"));
+        assert!(!expanded.contains("<script>"));
+    }
+
+    #[test]
+    fn images_take_their_size_and_placeholder() {
+        let previews = HashMap::from([(
+            12,
+            ImagePreview {
+                width: Some(640),
+                height: Some(480),
+                placeholder: Some("data:image/png;base64,AA==".to_string()),
+            },
+        )]);
+        let expanded = expand("{{< image id=12 >}}", false, &alt_texts(), &previews);
+
+        assert!(expanded.contains(" width=\"640\" height=\"480\""));
+        assert!(expanded.contains("background-image:url(data:image/png;base64,AA==)"));
+        assert!(expanded.contains(&format!("onload=\"{}\"", PLACEHOLDER_ONLOAD)));
+
+        let without_preview = expand("{{< image id=12 >}}", false, &alt_texts(), &HashMap::new());
+        assert!(!without_preview.contains("width="));
+        assert!(!without_preview.contains("onload="));
+    }
+
+    #[test]
+    fn images_without_alt_text_warn_the_writer() {
+        let expanded = expand_plain("{{< image id=3 >}}", true);
+        assert!(expanded.contains("the image needs alt text on the gallery"));
+    }
+
+    #[test]
+    fn warnings_are_only_shown_on_preview() {
+        let content = "before {{< <b>unknown</b> >}} after";
+
+        let preview = expand_plain(content, true);
+        assert!(preview.contains("md-shortcode-warning"));
+        assert!(preview.contains("&lt;b&gt;unknown&lt;/b&gt;"));
+        assert!(!preview.contains("<b>"));
+
+        assert_eq!(expand_plain(content, false), "before  after");
+    }
+
+    #[test]
+    fn unclosed_shortcodes_keep_the_line_break() {
+        let content = "text {{< image id=12\nnext line\n";
+
+        assert_eq!(expand_plain(content, false), "text \nnext line\n");
+        let preview = expand_plain(content, true);
+        assert!(preview.contains("unclosed shortcode"));
+        assert!(preview.ends_with("\nnext line\n"));
+    }
+
+    #[test]
+    fn rejects_unsafe_embeds() {
+        assert_eq!(expand_plain("{{< youtube id=\"a\\\" onload=x\" >}}", false), "");
+        assert_eq!(expand_plain("{{< video src=\"javascript:alert(1)\" >}}", false), "");
+        assert_eq!(expand_plain("{{< video src=//evil.example/v.mp4 >}}", false), "");
+        assert_eq!(expand_plain("{{< godot game=../secret >}}", false), "");
+
+        let video = expand_plain("{{< video src=/clips/a.mp4 >}}", false);
+        assert!(video.contains("src=\"/clips/a.mp4\""));
+    }
+
+    #[test]
+    fn skips_fenced_code() {
+        let content = "```\n{{< image id=12 >}}\n```\n~~~md\n{{< nope >}}\n~~~\n{{< image id=12 >}}\n";
+        let expanded = expand(content, false, &alt_texts(), &HashMap::new());
+
+        assert!(expanded.starts_with("```\n{{< image id=12 >}}\n```\n~~~md\n{{< nope >}}\n~~~\n"));
+        assert!(expanded.contains("<figure"));
+    }
+
+    #[test]
+    fn lists_image_ids_outside_fences_once() {
+        let content =
+            "{{< image id=4 >}} {{< image id=7 >}}\n```\n{{< image id=9 >}}\n```\n{{< image id=4 >}}\n{{< youtube id=5 >}}";
+
+        assert_eq!(image_ids(content), vec![4, 7]);
+    }
+}
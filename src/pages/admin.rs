@@ -1,6 +1,11 @@
 pub mod dashboard;
 pub mod login;
 
+pub mod albums;
 pub mod gallery;
 pub mod identity;
+pub mod maintenance;
+pub mod mentions;
 pub mod office;
+pub mod subscribers;
+pub mod upload;
@@ -0,0 +1,240 @@
+use leptos::*;
+
+use crate::{
+    api::{
+        albums::{
+            create_album, delete_album, get_all_albums, update_album, AlbumModel, AlbumVisibility,
+        },
+        images::{image_url, ImageSize, PublicImageModel},
+    },
+    components::image::ResponsiveImage,
+};
+
+/// Manages the albums from the Gallery page. Images selected on the gallery can be added to the
+/// album being edited.
+#[component]
+pub fn AlbumManager(selection: RwSignal<Vec<i32>>) -> impl IntoView {
+    let albums = create_resource(|| (), |_| async move { get_all_albums().await });
+    let editing = create_rw_signal(None::<AlbumModel>);
+
+    let title = create_rw_signal(String::new());
+    let slug = create_rw_signal(String::new());
+    let description = create_rw_signal(String::new());
+    let cover_image_id = create_rw_signal(String::new());
+    let visibility = create_rw_signal(AlbumVisibility::default());
+    let images = create_rw_signal(Vec::<i32>::new());
+
+    let edit = move |album: Option<AlbumModel>| {
+        let album = album.unwrap_or_default();
+        title.set(album.title.clone());
+        slug.set(album.slug.clone());
+        description.set(album.description.clone().unwrap_or_default());
+        cover_image_id.set(
+            album
+                .cover_image_id
+                .map(|id| id.to_string())
+                .unwrap_or_default(),
+        );
+        visibility.set(album.visibility);
+        images.set(album.images.iter().map(|image| image.id).collect());
+        editing.set(Some(album));
+    };
+
+    let form_album = move || AlbumModel {
+        id: editing
+            .get_untracked()
+            .map(|album| album.id)
+            .unwrap_or_default(),
+        title: title.get_untracked(),
+        slug: slug.get_untracked(),
+        description: Some(description.get_untracked())
+            .filter(|description| !description.is_empty()),
+        cover_image_id: cover_image_id.get_untracked().parse().ok(),
+        visibility: visibility.get_untracked(),
+        images: images
+            .get_untracked()
+            .into_iter()
+            .map(|id| PublicImageModel {
+                id,
+                ..Default::default()
+            })
+            .collect(),
+        ..Default::default()
+    };
+
+    let save_action = create_action(move |album: &AlbumModel| {
+        let album = album.clone();
+        async move {
+            // New albums are created first, then get their images like any other.
+            let album = if album.id == 0 {
+                let images = album.images.clone();
+                AlbumModel {
+                    images,
+                    ..create_album(album).await?
+                }
+            } else {
+                album
+            };
+            let saved = update_album(album).await;
+            if let Ok(saved) = &saved {
+                editing.set(Some(saved.clone()));
+                albums.refetch();
+            }
+            saved
+        }
+    });
+
+    let delete_action = create_action(move |album_id: &i32| {
+        let album_id = *album_id;
+        async move {
+            let deleted = delete_album(album_id).await;
+            if deleted.is_ok() {
+                editing.set(None);
+                albums.refetch();
+            }
+            deleted
+        }
+    });
+
+    let move_image = move |from: usize, to: usize| {
+        images.update(|images| {
+            if to < images.len() {
+                images.swap(from, to);
+            }
+        })
+    };
+
+    view! {
+        <div class="my-2">
+        <h3 class="text-2xl font-bold">"Álbuns"</h3>
+        <div class="flex flex-wrap gap-2 my-2">
+            <Suspense>
+            {move || albums.get().map(|albums| match albums {
+                Ok(albums) => albums.into_iter()
+                    .map(|album| {
+                        let id = album.id;
+                        let title = album.title.clone();
+                        view! {
+                            <button
+                                on:click=move |_| edit(Some(album.clone()))
+                                class="border-4 px-2 py-1"
+                                class=("border-black", move || editing.get().is_some_and(|album| album.id == id))
+                                class=("border-transparent", move || !editing.get().is_some_and(|album| album.id == id))
+                            >
+                                {title}
+                            </button>
+                        }
+                    }).collect_view(),
+                Err(err) => view! {
+                    <pre>{err.to_string()}</pre>
+                }.into_view(),
+            })}
+            </Suspense>
+            <button on:click=move |_| edit(None) class="border-4 border-transparent px-2 py-1">"+ Novo álbum"</button>
+        </div>
+        {move || editing.get().map(|album| album.id).map(|album_id| view! {
+            <form autocomplete="off">
+                <p class="text-left font-bold">"Título"</p>
+                <input type="text" on:input=move |ev| {
+                    title.set(event_target_value(&ev));
+                } prop:value=title class="bg-orange-400 border-4 border-t-orange-900 border-l-orange-800 border-r-orange-300 border-b-orange-300 w-full flex-grow p-2 focus:outline-none"/>
+                <p class="text-left font-bold">"Slug"</p>
+                <input type="text" on:input=move |ev| {
+                    slug.set(event_target_value(&ev));
+                } prop:value=slug class="bg-orange-400 border-4 border-t-orange-900 border-l-orange-800 border-r-orange-300 border-b-orange-300 w-full flex-grow p-2 focus:outline-none" placeholder="ferias-2024"/>
+                <p class="text-left font-bold">"Descrição"</p>
+                <textarea on:input=move |ev| {
+                    description.set(event_target_value(&ev));
+                } prop:value=description class="bg-orange-400 border-4 border-t-orange-900 border-l-orange-800 border-r-orange-300 border-b-orange-300 w-full flex-grow p-2 focus:outline-none"></textarea>
+                <p class="text-left font-bold">"Visibilidade"</p>
+                <select on:change=move |ev| {
+                    if let Some(selected) = AlbumVisibility::from_name(&event_target_value(&ev)) {
+                        visibility.set(selected);
+                    }
+                } class="bg-orange-400 border-4 border-t-orange-900 border-l-orange-800 border-r-orange-300 border-b-orange-300 w-full p-2 focus:outline-none">
+                    {AlbumVisibility::ALL.into_iter()
+                        .map(|option| view! {
+                            <option value=option.name() selected=move || visibility.get() == option>{match option {
+                                AlbumVisibility::Public => "Público",
+                                AlbumVisibility::Unlisted => "Não listado",
+                                AlbumVisibility::Private => "Privado",
+                            }}</option>
+                        })
+                        .collect_view()}
+                </select>
+                <p class="text-left font-bold">"Capa (id da imagem, a primeira se vazio)"</p>
+                <input type="number" on:input=move |ev| {
+                    cover_image_id.set(event_target_value(&ev));
+                } prop:value=cover_image_id class="bg-orange-400 border-4 border-t-orange-900 border-l-orange-800 border-r-orange-300 border-b-orange-300 w-full flex-grow p-2 focus:outline-none"/>
+                <p class="text-left font-bold">"Imagens"</p>
+                <ol class="my-2">
+                {move || images.get().into_iter().enumerate()
+                    .map(|(index, id)| view! {
+                        <li class="flex items-center gap-2 my-1">
+                            <ResponsiveImage id=id class="h-12 w-12 object-cover border-2 border-black" sizes="48px"/>
+                            <a href=image_url(id, Some(ImageSize::Large)) target="_blank" class="flex-grow hover:underline">{format!("Imagem {}", id)}</a>
+                            <button title="Subir" on:click=move |ev| {
+                                ev.prevent_default();
+                                if index > 0 {
+                                    move_image(index, index - 1);
+                                }
+                            }>"↑"</button>
+                            <button title="Descer" on:click=move |ev| {
+                                ev.prevent_default();
+                                move_image(index, index + 1);
+                            }>"↓"</button>
+                            <button title="Remover" on:click=move |ev| {
+                                ev.prevent_default();
+                                images.update(|images| { images.remove(index); });
+                            }>"✕"</button>
+                        </li>
+                    }).collect_view()}
+                </ol>
+                <button
+                    on:click=move |ev| {
+                        ev.prevent_default();
+                        let selected = selection.get();
+                        images.update(|images| images.extend(
+                            selected.into_iter().filter(|id| !images.contains(id)).collect::<Vec<_>>()
+                        ));
+                    }
+                    disabled=move || selection.with(Vec::is_empty)
+                    class="bg-orange-400 border-4 border-b-orange-900 active:border-t-orange-900 border-r-orange-800 active:border-l-orange-900 border-l-orange-300 active:border-r-orange-300 border-t-orange-300 active:border-b-orange-300 w-full cursor-pointer py-2"
+                >
+                    "Adicionar selecionadas"
+                </button>
+                <div class="flex my-2">
+                <button
+                    on:click=move |ev| {
+                        ev.prevent_default();
+                        if album_id == 0 {
+                            editing.set(None);
+                        } else {
+                            delete_action.dispatch(album_id);
+                        }
+                    }
+                    class="bg-orange-400 mr-2 border-4 border-b-orange-900 active:border-t-orange-900 border-r-orange-800 active:border-l-orange-900 border-l-orange-300 active:border-r-orange-300 border-t-orange-300 active:border-b-orange-300 w-full cursor-pointer py-2"
+                >
+                    {if album_id == 0 { "Cancelar" } else { "Excluir álbum" }}
+                </button>
+                <button
+                    on:click=move |ev| {
+                        ev.prevent_default();
+                        save_action.dispatch(form_album());
+                    }
+                    class="bg-orange-400 border-4 border-b-orange-900 active:border-t-orange-900 border-r-orange-800 active:border-l-orange-900 border-l-orange-300 active:border-r-orange-300 border-t-orange-300 active:border-b-orange-300 w-full cursor-pointer py-2"
+                >
+                    "Salvar álbum"
+                </button>
+                </div>
+            </form>
+        })}
+        {move || save_action.value().get().and_then(|result| result.err()).map(|err| view! {
+            <pre class="text-[#630000]">{err.to_string()}</pre>
+        })}
+        {move || delete_action.value().get().and_then(|result| result.err()).map(|err| view! {
+            <pre class="text-[#630000]">{err.to_string()}</pre>
+        })}
+        </div>
+    }
+}
@@ -28,6 +28,9 @@ pub fn Dashboard() -> impl IntoView {
                         <li><A href="/admin/dashboard/office"><img class="h-16 w-auto m-1 inline-block" src="/writer.svg"/>"Escritório"</A></li>
                         <li><A href="/admin/dashboard/gallery"><img class="h-16 w-auto m-1 inline-block" src="/gallery.svg"/>"Galeria"</A></li>
                         <li><A href="/admin/dashboard/identity"><img class="h-16 w-auto m-1 inline-block" src="/identity.svg"/>"Identidade"</A></li>
+                        <li><A href="/admin/dashboard/mentions"><img class="h-16 w-auto m-1 inline-block" src="/email.svg"/>"Menções"</A></li>
+                        <li><A href="/admin/dashboard/subscribers"><img class="h-16 w-auto m-1 inline-block" src="/email.svg"/>"Assinantes"</A></li>
+                        <li><A href="/admin/dashboard/maintenance"><img class="h-16 w-auto m-1 inline-block" src="/gallery.svg"/>"Manutenção"</A></li>
                     </ul>
                 </RetroContainer>
                 <RetroContainer class="flex-1 flex">
@@ -3,66 +3,412 @@ use base64::{
     engine::{self, general_purpose},
     Engine as _,
 };
+use chrono::NaiveDate;
 use leptos::{logging::log, *};
 use leptos_router::*;
 use wasm_bindgen::JsCast;
-use web_sys::{FormData, HtmlFormElement, SubmitEvent};
 
 use crate::{
     api::{
-        images::{get_image, get_image_len, upload_image_to_gallery, GetImage},
-        users::{LoginUser, UserModel},
+        images::{
+            delete_image_from_gallery, get_image, get_image_metadata, image_url, list_images,
+            update_image_metadata, GetImage, ImageAltTextModel, ImageFilter, ImageMetadataModel,
+            ImagePreview, ImageSort, ALT_TEXT_LOCALES,
+        },
+        users::{get_all_users, get_user, LoginUser, UserModel},
     },
     components::{
         background::Brickwall,
         container::RetroContainer,
+        image::ResponsiveImage,
         navbar::{Navbar, NavbarReserved},
     },
-    pages::articles::list::ArticleList,
+    pages::{
+        admin::{albums::AlbumManager, upload::UploadZone},
+        articles::list::ArticleList,
+    },
 };
 
 use crate::i18n::*;
 
-/// The home page component, renders on / default path
+/// The types the gallery accepts, as shown on the filter.
+const IMAGE_TYPES: [(&str, &str); 5] = [
+    ("image/png", "PNG"),
+    ("image/jpeg", "JPEG"),
+    ("image/gif", "GIF"),
+    ("image/webp", "WebP"),
+    ("image/svg+xml", "SVG"),
+];
+
+/// Manages the gallery images, renders on /admin/dashboard/gallery
 #[component]
 pub fn Gallery() -> impl IntoView {
     let i18n = use_i18n();
 
-    let image_count = create_resource(|| (), |_| async move { get_image_len().await });
-    let upload_action = create_action(|data: &FormData| {
-        let data = data.clone();
-        upload_image_to_gallery(data.into())
+    let filter = create_rw_signal(ImageFilter::default());
+    let sort = create_rw_signal(ImageSort::default());
+    let page = create_rw_signal(0u64);
+    let images = create_resource(
+        move || (filter.get(), sort.get(), page.get()),
+        |(filter, sort, page)| list_images(filter, sort, page),
+    );
+    let uploaders = create_resource(|| (), |_| async move { get_all_users().await });
+
+    // Changing the filters starts over from the first page
+    let set_filter = move |update: &dyn Fn(&mut ImageFilter)| {
+        filter.update(|filter| update(filter));
+        page.set(0);
+    };
+
+    let selection = create_rw_signal(Vec::<i32>::new());
+    let editing = create_rw_signal(None::<i32>);
+    let copied = create_rw_signal(false);
+
+    // Only the images in `forced` are deleted while used by articles, the ones the user confirmed
+    let delete_action = create_action(move |(image_ids, forced): &(Vec<i32>, Vec<i32>)| {
+        let (image_ids, forced) = (image_ids.clone(), forced.clone());
+        async move {
+            let mut errors = vec![];
+            for image_id in image_ids {
+                let force = forced.contains(&image_id);
+                if let Err(err) = delete_image_from_gallery(image_id, force).await {
+                    errors.push(err.to_string());
+                }
+            }
+            selection.set(vec![]);
+            editing.set(None);
+            images.refetch();
+            errors
+        }
     });
 
     view! {
         <div class="flex flex-col">
-        <h2 class="text-3xl text-center py-2 font-bold">"Gallery"</h2>
+        <h2 class="text-3xl text-center py-2 font-bold">"Galeria"</h2>
+        <div class="flex flex-wrap gap-2 my-2">
+            <select on:change=move |ev| {
+                let uploader_id = event_target_value(&ev).parse::<i32>().ok();
+                set_filter(&|filter| filter.uploader_id = uploader_id);
+            } class="bg-orange-400 border-4 border-t-orange-900 border-l-orange-800 border-r-orange-300 border-b-orange-300 p-2 focus:outline-none">
+                <option value="">"Qualquer autor"</option>
+                <Suspense>
+                {move || uploaders.get().and_then(Result::ok).map(|users| users.into_iter()
+                    .map(|user| view! { <option value=user.id>{user.username}</option> })
+                    .collect_view())}
+                </Suspense>
+            </select>
+            <input type="date" title="Enviadas desde" on:change=move |ev| {
+                let from = NaiveDate::parse_from_str(&event_target_value(&ev), "%Y-%m-%d").ok();
+                set_filter(&|filter| filter.uploaded_from = from);
+            } class="bg-orange-400 border-4 border-t-orange-900 border-l-orange-800 border-r-orange-300 border-b-orange-300 p-2 focus:outline-none"/>
+            <input type="date" title="Enviadas até" on:change=move |ev| {
+                let until = NaiveDate::parse_from_str(&event_target_value(&ev), "%Y-%m-%d").ok();
+                set_filter(&|filter| filter.uploaded_until = until);
+            } class="bg-orange-400 border-4 border-t-orange-900 border-l-orange-800 border-r-orange-300 border-b-orange-300 p-2 focus:outline-none"/>
+            <select on:change=move |ev| {
+                let mime_type = Some(event_target_value(&ev)).filter(|mime_type| !mime_type.is_empty());
+                set_filter(&|filter| filter.mime_type = mime_type.clone());
+            } class="bg-orange-400 border-4 border-t-orange-900 border-l-orange-800 border-r-orange-300 border-b-orange-300 p-2 focus:outline-none">
+                <option value="">"Qualquer tipo"</option>
+                {IMAGE_TYPES.into_iter()
+                    .map(|(mime_type, name)| view! { <option value=mime_type>{name}</option> })
+                    .collect_view()}
+            </select>
+            <label class="flex items-center gap-1">
+                <input type="checkbox" on:change=move |ev| {
+                    let unused = event_target_checked(&ev);
+                    set_filter(&|filter| filter.unused = unused);
+                }/>
+                "Sem uso"
+            </label>
+            <select on:change=move |ev| {
+                sort.set(ImageSort::from_name(&event_target_value(&ev)).unwrap_or_default());
+                page.set(0);
+            } class="bg-orange-400 border-4 border-t-orange-900 border-l-orange-800 border-r-orange-300 border-b-orange-300 p-2 focus:outline-none">
+                <option value="newest">"Mais recentes"</option>
+                <option value="oldest">"Mais antigas"</option>
+                <option value="largest">"Maiores"</option>
+                <option value="smallest">"Menores"</option>
+            </select>
+        </div>
+        <div class="flex my-2">
+            <button
+                on:click=move |_| {
+                    let urls = selection.get()
+                        .into_iter()
+                        .map(|image_id| absolute_url(&image_url(image_id, None)))
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    copied.set(copy_to_clipboard(&urls));
+                }
+                disabled=move || selection.with(Vec::is_empty)
+                class="bg-orange-400 mr-2 border-4 border-b-orange-900 active:border-t-orange-900 border-r-orange-800 active:border-l-orange-900 border-l-orange-300 active:border-r-orange-300 border-t-orange-300 active:border-b-orange-300 w-full cursor-pointer py-2"
+            >
+                {move || if copied.get() { "URLs copiadas" } else { "Copiar URLs" }}
+            </button>
+            <button
+                on:click=move |_| {
+                    let selected = selection.get();
+                    // Images used on other pages are still refused by the server
+                    let used = images.get()
+                        .and_then(Result::ok)
+                        .map(|images| images.images.iter()
+                            .filter(|image| selected.contains(&image.id) && image.article_count > 0)
+                            .map(|image| image.id)
+                            .collect::<Vec<_>>())
+                        .unwrap_or_default();
+                    if !used.is_empty() {
+                        let confirmed = window()
+                            .confirm_with_message(&format!(
+                                "{} das imagens selecionadas são usadas em artigos, que vão ficar sem elas. Excluir mesmo assim?",
+                                used.len()
+                            ))
+                            .unwrap_or(false);
+                        if !confirmed {
+                            return;
+                        }
+                    }
+                    delete_action.dispatch((selected, used));
+                }
+                disabled=move || selection.with(Vec::is_empty)
+                class="bg-orange-400 border-4 border-b-orange-900 active:border-t-orange-900 border-r-orange-800 active:border-l-orange-900 border-l-orange-300 active:border-r-orange-300 border-t-orange-300 active:border-b-orange-300 w-full cursor-pointer py-2"
+            >
+                {move || format!("Excluir selecionadas ({})", selection.with(Vec::len))}
+            </button>
+        </div>
+        {move || delete_action.value().get().filter(|errors| !errors.is_empty()).map(|errors| view! {
+            <pre class="text-[#630000]">{errors.join("\n")}</pre>
+        })}
         <div class="grid flex-grow grid-cols-2 md:grid-cols-3 overflow-auto gap-4">
             <Suspense>
-            {
-                match image_count.get() {
-                    Some(Ok(count)) => (1..count + 1).into_iter()
-                        .map(|id| view!{<img class="hover:border-4 w-full object-cover aspect-square duration-300 transition" src={format!("/gallery/{}", id)}/>})
-                        .collect_view(),
-
-                    _ => view! {
-                        <>
-                        <pre>"A error occured."</pre>
-                        </>
-                    }.into()
-                }
-            }
+            {move || images.get().map(|images| match images {
+                Ok(images) if images.images.is_empty() => view! {
+                    <p class="text-center col-span-full">"Nenhuma imagem encontrada."</p>
+                }.into_view(),
+                Ok(images) => images.images.into_iter()
+                    .map(|image| {
+                        let id = image.id;
+                        let alt = image.alt_text(ALT_TEXT_LOCALES[0])
+                            .or(image.original_filename.as_deref())
+                            .unwrap_or_default()
+                            .to_string();
+                        view! {
+                            <div class="relative">
+                                <input
+                                    type="checkbox"
+                                    class="absolute top-2 left-2 w-5 h-5"
+                                    prop:checked=move || selection.with(|selection| selection.contains(&id))
+                                    on:change=move |_| {
+                                        copied.set(false);
+                                        selection.update(|selection| match selection.iter().position(|selected| *selected == id) {
+                                            Some(index) => { selection.remove(index); }
+                                            None => selection.push(id),
+                                        });
+                                    }
+                                />
+                                <button class="w-full" on:click=move |_| editing.set(Some(id))>
+                                    <ResponsiveImage id=id alt=alt preview=ImagePreview::from(&image) class="hover:border-4 w-full object-cover aspect-square duration-300 transition" sizes="(min-width: 768px) 33vw, 50vw"/>
+                                </button>
+                                {(image.article_count > 0).then(|| view! {
+                                    <span class="absolute bottom-2 left-2 bg-orange-400 border-2 border-black px-1 text-sm">
+                                        {format!("Usada em {} artigo(s)", image.article_count)}
+                                    </span>
+                                })}
+                            </div>
+                        }
+                    })
+                    .collect_view(),
+                Err(err) => view! {
+                    <pre class="col-span-full">{err.to_string()}</pre>
+                }.into_view(),
+            })}
             </Suspense>
         </div>
-        <form on:submit=move |ev: SubmitEvent| {
-            ev.prevent_default();
-            let target = ev.target().unwrap().unchecked_into::<HtmlFormElement>();
-            let form_data = FormData::new_with_form(&target).unwrap();
-            upload_action.dispatch(form_data);
-        }>
-        <input name="file_to_upload" type="file"/>
-        <button>"Enviar"</button>
+        <div class="flex items-center my-2">
+            <button
+                on:click=move |_| page.update(|page| *page = page.saturating_sub(1))
+                disabled=move || page.get() == 0
+                class="bg-orange-400 border-4 border-b-orange-900 active:border-t-orange-900 border-r-orange-800 active:border-l-orange-900 border-l-orange-300 active:border-r-orange-300 border-t-orange-300 active:border-b-orange-300 w-full cursor-pointer py-2"
+            >
+                "Anterior"
+            </button>
+            <span class="w-full text-center">
+                {move || images.get().and_then(Result::ok).map(|images| {
+                    format!("Página {} de {} ({} imagens)", images.page + 1, images.pages.max(1), images.total)
+                })}
+            </span>
+            <button
+                on:click=move |_| page.update(|page| *page += 1)
+                disabled=move || images.get()
+                    .and_then(Result::ok)
+                    .map_or(true, |images| images.page + 1 >= images.pages)
+                class="bg-orange-400 border-4 border-b-orange-900 active:border-t-orange-900 border-r-orange-800 active:border-l-orange-900 border-l-orange-300 active:border-r-orange-300 border-t-orange-300 active:border-b-orange-300 w-full cursor-pointer py-2"
+            >
+                "Próxima"
+            </button>
+        </div>
+        {move || editing.get().map(|id| view! { <ImageEditor id=id selected=editing/> })}
+        <AlbumManager selection=selection/>
+        <UploadZone on_uploaded=move |_| images.refetch()/>
+        </div>
+    }
+}
+
+fn absolute_url(path: &str) -> String {
+    let origin = window().location().origin().unwrap_or_default();
+    format!("{}{}", origin, path)
+}
+
+/// Copy `text` through a hidden text area, as the Clipboard API isn't stable on `web_sys` yet.
+fn copy_to_clipboard(text: &str) -> bool {
+    use web_sys::{HtmlDocument, HtmlTextAreaElement};
+
+    let document = document();
+    let Some(text_area) = document
+        .create_element("textarea")
+        .ok()
+        .and_then(|element| element.dyn_into::<HtmlTextAreaElement>().ok())
+    else {
+        return false;
+    };
+    let Some(body) = document.body() else {
+        return false;
+    };
+
+    text_area.set_value(text);
+    let _ = body.append_child(&text_area);
+    text_area.select();
+    let copied = document
+        .unchecked_into::<HtmlDocument>()
+        .exec_command("copy")
+        .unwrap_or(false);
+    text_area.remove();
+
+    copied
+}
+
+/// Edits the alt texts, caption, credit and license of the selected image, showing what else is
+/// known about it.
+#[component]
+fn ImageEditor(id: i32, selected: RwSignal<Option<i32>>) -> impl IntoView {
+    let alt_texts = ALT_TEXT_LOCALES.map(|_| create_rw_signal(String::new()));
+    let caption = create_rw_signal(String::new());
+    let credit = create_rw_signal(String::new());
+    let license = create_rw_signal(String::new());
+
+    let image = create_resource(
+        move || id,
+        move |id| async move {
+            let image = get_image_metadata(id).await.ok().flatten()?;
+            for (locale, alt_text) in ALT_TEXT_LOCALES.iter().zip(alt_texts) {
+                alt_text.set(image.alt_text(locale).unwrap_or_default().to_string());
+            }
+            caption.set(image.caption.clone().unwrap_or_default());
+            credit.set(image.credit.clone().unwrap_or_default());
+            license.set(image.license.clone().unwrap_or_default());
+
+            let uploader = match image.uploader_id {
+                Some(uploader_id) => get_user(uploader_id)
+                    .await
+                    .ok()
+                    .flatten()
+                    .map(|user| user.username),
+                None => None,
+            };
+            Some((image, uploader))
+        },
+    );
+
+    let update_action = create_action(move |updated_image: &ImageMetadataModel| {
+        let updated_image = updated_image.clone();
+        async move {
+            let updated = update_image_metadata(updated_image).await;
+            if updated.is_ok() {
+                image.refetch();
+            }
+            updated
+        }
+    });
+
+    view! {
+        <div class="my-2">
+        <Suspense>
+        {move || image.get().flatten().map(|(image, uploader)| view! {
+            <h3 class="text-2xl font-bold">{format!("Imagem {}", image.id)}</h3>
+            <ul class="my-2">
+                <li>{format!("Dimensões: {}", match (image.width, image.height) {
+                    (Some(width), Some(height)) => format!("{}x{}", width, height),
+                    _ => "desconhecidas".to_string(),
+                })}</li>
+                <li>{format!("Tamanho: {}", image.byte_size
+                    .map(|bytes| format!("{:.1} KiB", bytes as f64 / 1024.0))
+                    .unwrap_or("desconhecido".to_string()))}</li>
+                <li>{format!("Enviada por: {}", uploader.unwrap_or("desconhecido".to_string()))}</li>
+                <li>{format!("Enviada em: {}", image.created_at.format("%d/%m/%Y %H:%M"))}</li>
+                <li>{format!("Usada em {} artigo(s)", image.article_count)}</li>
+                {image.captured_at.map(|captured_at| view! {
+                    <li>{format!("Capturada em: {}", captured_at.format("%d/%m/%Y %H:%M"))}</li>
+                })}
+            </ul>
+        })}
+        </Suspense>
+        <form autocomplete="off">
+            {ALT_TEXT_LOCALES.iter().zip(alt_texts).map(|(locale, alt_text)| view! {
+                <p class="text-left font-bold">{format!("Texto alternativo ({})", locale)}</p>
+                <textarea on:input=move |ev| {
+                    alt_text.set(event_target_value(&ev));
+                } prop:value=alt_text class="bg-orange-400 border-4 border-t-orange-900 border-l-orange-800 border-r-orange-300 border-b-orange-300 w-full flex-grow p-2 focus:outline-none"></textarea>
+            }).collect_view()}
+            <p class="text-left font-bold">"Legenda"</p>
+            <textarea on:input=move |ev| {
+                caption.set(event_target_value(&ev));
+            } prop:value=caption class="bg-orange-400 border-4 border-t-orange-900 border-l-orange-800 border-r-orange-300 border-b-orange-300 w-full flex-grow p-2 focus:outline-none"></textarea>
+            <p class="text-left font-bold">"Créditos"</p>
+            <input type="text" on:input=move |ev| {
+                credit.set(event_target_value(&ev));
+            } prop:value=credit class="bg-orange-400 border-4 border-t-orange-900 border-l-orange-800 border-r-orange-300 border-b-orange-300 w-full flex-grow p-2 focus:outline-none"/>
+            <p class="text-left font-bold">"Licença"</p>
+            <input type="text" on:input=move |ev| {
+                license.set(event_target_value(&ev));
+            } prop:value=license class="bg-orange-400 border-4 border-t-orange-900 border-l-orange-800 border-r-orange-300 border-b-orange-300 w-full flex-grow p-2 focus:outline-none" placeholder="CC BY 4.0"/>
+            <div class="flex my-2">
+            <button
+                on:click=move |ev| {
+                    ev.prevent_default();
+                    selected.set(None);
+                }
+                class="bg-orange-400 mr-2 border-4 border-b-orange-900 active:border-t-orange-900 border-r-orange-800 active:border-l-orange-900 border-l-orange-300 active:border-r-orange-300 border-t-orange-300 active:border-b-orange-300 w-full cursor-pointer py-2"
+            >
+                "Fechar"
+            </button>
+            <button
+                on:click=move |ev| {
+                    ev.prevent_default();
+                    update_action.dispatch(ImageMetadataModel {
+                        id,
+                        alt_texts: ALT_TEXT_LOCALES
+                            .iter()
+                            .zip(alt_texts)
+                            .map(|(locale, alt_text)| ImageAltTextModel {
+                                locale: locale.to_string(),
+                                alt_text: alt_text.get(),
+                            })
+                            .collect(),
+                        caption: Some(caption.get()),
+                        credit: Some(credit.get()),
+                        license: Some(license.get()),
+                        ..Default::default()
+                    });
+                }
+                class="bg-orange-400 border-4 border-b-orange-900 active:border-t-orange-900 border-r-orange-800 active:border-l-orange-900 border-l-orange-300 active:border-r-orange-300 border-t-orange-300 active:border-b-orange-300 w-full cursor-pointer py-2"
+            >
+                "Atualizar"
+            </button>
+            </div>
         </form>
+        {move || update_action.value().get().and_then(|result| result.err()).map(|err| view! {
+            <pre class="text-[#630000]">{err.to_string()}</pre>
+        })}
         </div>
     }
 }
@@ -4,7 +4,10 @@ use wasm_bindgen::JsCast;
 use web_sys::{FormData, HtmlFormElement, SubmitEvent};
 
 use crate::{
-    api::users::{get_user, update_user, user_logged_in, LoginUser, UserModel},
+    api::users::{
+        get_social_links, get_user, update_social_links, update_user, user_logged_in, LoginUser,
+        SocialLinkModel, UserModel,
+    },
     components::{
         background::Brickwall,
         container::RetroContainer,
@@ -24,6 +27,11 @@ pub fn Identity() -> impl IntoView {
     let name = create_rw_signal(String::new());
     let mail = create_rw_signal(String::new());
     let pass = create_rw_signal(String::new());
+    let display_name = create_rw_signal(String::new());
+    let avatar = create_rw_signal(String::new());
+    let bio = create_rw_signal(String::new());
+    // One link per line, as "Label https://url"
+    let social_links = create_rw_signal(String::new());
 
     // user_logged_in().await.unwrap().unwrap()
     let user = create_resource(
@@ -37,6 +45,18 @@ pub fn Identity() -> impl IntoView {
             id.set(user_id);
             name.set(user.username);
             mail.set(user.email);
+            display_name.set(user.display_name.unwrap_or_default());
+            avatar.set(user.avatar.unwrap_or_default());
+            bio.set(user.bio.unwrap_or_default());
+            social_links.set(
+                get_social_links(user_id)
+                    .await
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|link| format!("{} {}", link.label, link.url))
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+            );
         },
     );
 
@@ -56,6 +76,22 @@ pub fn Identity() -> impl IntoView {
                     <input type="password" on:input=move |ev| {
                         pass.set(event_target_value(&ev));
                     } prop:value="" class="bg-orange-400 border-4 border-t-orange-900 border-l-orange-800 border-r-orange-300 border-b-orange-300 w-full flex-grow p-2 focus:outline-none" type="password"/>
+                    <p class="text-left font-bold">"Nome de exibição"</p>
+                    <input type="text" on:input=move |ev| {
+                        display_name.set(event_target_value(&ev));
+                    } prop:value=display_name class="bg-orange-400 border-4 border-t-orange-900 border-l-orange-800 border-r-orange-300 border-b-orange-300 w-full flex-grow p-2 focus:outline-none"/>
+                    <p class="text-left font-bold">"Avatar"</p>
+                    <input type="text" on:input=move |ev| {
+                        avatar.set(event_target_value(&ev));
+                    } prop:value=avatar class="bg-orange-400 border-4 border-t-orange-900 border-l-orange-800 border-r-orange-300 border-b-orange-300 w-full flex-grow p-2 focus:outline-none" placeholder="/gallery/1"/>
+                    <p class="text-left font-bold">"Biografia"</p>
+                    <textarea on:input=move |ev| {
+                        bio.set(event_target_value(&ev));
+                    } prop:value=bio class="bg-orange-400 border-4 border-t-orange-900 border-l-orange-800 border-r-orange-300 border-b-orange-300 w-full flex-grow p-2 focus:outline-none"></textarea>
+                    <p class="text-left font-bold">"Redes sociais"</p>
+                    <textarea on:input=move |ev| {
+                        social_links.set(event_target_value(&ev));
+                    } prop:value=social_links class="bg-orange-400 border-4 border-t-orange-900 border-l-orange-800 border-r-orange-300 border-b-orange-300 w-full flex-grow p-2 focus:outline-none" placeholder="Instagram https://www.instagram.com/orangethewell/"></textarea>
                     <div class="flex my-2">
                     <button
                         on:click=move |ev| {
@@ -74,10 +110,24 @@ pub fn Identity() -> impl IntoView {
                                 username: name.get(),
                                 email: mail.get(),
                                 password: pass.get(),
+                                display_name: Some(display_name.get()),
+                                avatar: Some(avatar.get()),
+                                bio: Some(bio.get()),
                                 ..Default::default()
                             };
+                            let links: Vec<SocialLinkModel> = social_links.get()
+                                .lines()
+                                .filter_map(|line| line.trim().rsplit_once(' '))
+                                .map(|(label, url)| SocialLinkModel {
+                                    label: label.to_string(),
+                                    url: url.to_string(),
+                                    ..Default::default()
+                                })
+                                .collect();
                             spawn_local(async move {
+                                let user_id = upuser.id;
                                 update_user(upuser).await;
+                                update_social_links(user_id, links).await;
                             });
                         }
                         class="bg-orange-400 border-4 border-b-orange-900 active:border-t-orange-900 border-r-orange-800 active:border-l-orange-900 border-l-orange-300 active:border-r-orange-300 border-t-orange-300 active:border-b-orange-300 w-full cursor-pointer py-2"
@@ -0,0 +1,111 @@
+use leptos::*;
+
+use crate::api::maintenance::{
+    collect_orphan_files, MissingFileModel, OrphanAction, OrphanReportModel,
+};
+
+/// Finds the uploads and articles out of sync with the database, renders on
+/// /admin/dashboard/maintenance
+#[component]
+pub fn Maintenance() -> impl IntoView {
+    let collect_action = create_action(|action: &OrphanAction| collect_orphan_files(*action));
+
+    let run = move |action: OrphanAction| {
+        let question = match action {
+            OrphanAction::DryRun => None,
+            OrphanAction::Quarantine => Some("Mover os arquivos sem registro para a quarentena?"),
+            OrphanAction::Delete => {
+                Some("Excluir os arquivos sem registro? Isso não pode ser desfeito.")
+            }
+        };
+        if let Some(question) = question {
+            if !window().confirm_with_message(question).unwrap_or(false) {
+                return;
+            }
+        }
+        collect_action.dispatch(action);
+    };
+
+    view! {
+        <div class="flex flex-col flex-grow">
+        <h2 class="text-3xl text-center py-2 font-bold">"Manutenção"</h2>
+        <p>"Procura uploads e artigos em data/*.md sem registro no banco, e registros sem arquivo. Arquivos alterados na última hora são ignorados."</p>
+        <div class="flex my-2">
+            <button
+                on:click=move |_| run(OrphanAction::DryRun)
+                disabled=move || collect_action.pending().get()
+                class="bg-orange-400 mr-2 border-4 border-b-orange-900 active:border-t-orange-900 border-r-orange-800 active:border-l-orange-900 border-l-orange-300 active:border-r-orange-300 border-t-orange-300 active:border-b-orange-300 w-full cursor-pointer py-2"
+            >
+                "Verificar sem alterar"
+            </button>
+            <button
+                on:click=move |_| run(OrphanAction::Quarantine)
+                disabled=move || collect_action.pending().get()
+                class="bg-orange-400 mr-2 border-4 border-b-orange-900 active:border-t-orange-900 border-r-orange-800 active:border-l-orange-900 border-l-orange-300 active:border-r-orange-300 border-t-orange-300 active:border-b-orange-300 w-full cursor-pointer py-2"
+            >
+                "Mover para a quarentena"
+            </button>
+            <button
+                on:click=move |_| run(OrphanAction::Delete)
+                disabled=move || collect_action.pending().get()
+                class="bg-orange-400 border-4 border-b-orange-900 active:border-t-orange-900 border-r-orange-800 active:border-l-orange-900 border-l-orange-300 active:border-r-orange-300 border-t-orange-300 active:border-b-orange-300 w-full cursor-pointer py-2"
+            >
+                "Excluir"
+            </button>
+        </div>
+        {move || collect_action.pending().get().then(|| view! {
+            <p class="text-center">"Procurando..."</p>
+        })}
+        <div class="flex-grow overflow-auto">
+        {move || collect_action.value().get().map(|report| match report {
+            Ok(report) => view! { <OrphanReport report=report/> }.into_view(),
+            Err(err) => view! {
+                <pre class="text-[#630000]">{err.to_string()}</pre>
+            }.into_view(),
+        })}
+        </div>
+        </div>
+    }
+}
+
+#[component]
+fn OrphanReport(report: OrphanReportModel) -> impl IntoView {
+    let outcome = match (report.action, &report.quarantine_dir) {
+        (OrphanAction::DryRun, _) => "Nada foi alterado.".to_string(),
+        (OrphanAction::Quarantine, Some(dir)) => format!("Os arquivos foram movidos para {}.", dir),
+        (OrphanAction::Quarantine, None) => String::new(),
+        (OrphanAction::Delete, _) => "Os arquivos foram excluídos.".to_string(),
+    };
+    let missing = |rows: Vec<MissingFileModel>| {
+        rows.into_iter()
+            .map(|row| match row.name {
+                Some(name) => format!("#{} {} ({})", row.id, row.path, name),
+                None => format!("#{} {}", row.id, row.path),
+            })
+            .collect::<Vec<_>>()
+    };
+
+    view! {
+        <p class="font-bold my-2">{outcome}</p>
+        <ReportSection title="Uploads sem imagem" lines=report.stray_uploads/>
+        <ReportSection title="Artigos sem registro" lines=report.stray_articles/>
+        <ReportSection title="Imagens sem arquivo" lines=missing(report.images_without_file)/>
+        <ReportSection title="Artigos sem arquivo" lines=missing(report.articles_without_file)/>
+        {(!report.failures.is_empty()).then(|| view! {
+            <h3 class="text-xl font-bold text-[#630000]">"Falhas"</h3>
+            <pre class="text-[#630000] whitespace-pre-wrap">{report.failures.join("\n")}</pre>
+        })}
+    }
+}
+
+#[component]
+fn ReportSection(title: &'static str, lines: Vec<String>) -> impl IntoView {
+    view! {
+        <h3 class="text-xl font-bold">{format!("{} ({})", title, lines.len())}</h3>
+        <ul class="mb-2">
+            {lines.into_iter()
+                .map(|line| view! { <li class="text-sm break-all">{line}</li> })
+                .collect_view()}
+        </ul>
+    }
+}
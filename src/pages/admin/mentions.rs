@@ -0,0 +1,55 @@
+use leptos::*;
+
+use crate::api::webmentions::{get_pending_webmentions, moderate_webmention};
+
+/// Lists the webmentions waiting for moderation, renders on /admin/dashboard/mentions
+#[component]
+pub fn Mentions() -> impl IntoView {
+    let mentions = create_resource(|| (), |_| async move { get_pending_webmentions().await });
+
+    let moderate_action = create_action(move |(mention_id, approve): &(i32, bool)| {
+        let (mention_id, approve) = (*mention_id, *approve);
+        async move {
+            moderate_webmention(mention_id, approve).await;
+            mentions.refetch();
+        }
+    });
+
+    view! {
+        <div class="flex flex-col flex-grow">
+        <h2 class="text-3xl text-center py-2 font-bold">"Menções"</h2>
+        <ul class="flex-grow overflow-auto">
+        <Suspense>
+        {move || {
+            mentions.get()
+                .map(|mentions| match mentions {
+                    Ok(mentions) if mentions.is_empty() => view! {
+                        <p class="text-center">"Nenhuma menção aguardando moderação."</p>
+                    }.into_view(),
+                    Ok(mentions) => mentions.into_iter()
+                        .map(|mention| view! {
+                            <li class="flex items-center py-2 px-4">
+                                <div class="flex-grow">
+                                    <a href=mention.source.clone() target="_blank" rel="noopener nofollow" class="font-bold hover:underline">
+                                        {mention.title.clone().unwrap_or_else(|| mention.source.clone())}
+                                    </a>
+                                    <p class="text-sm break-all">{mention.source.clone()}</p>
+                                </div>
+                                <button on:click=move |_| {
+                                    moderate_action.dispatch((mention.id, true));
+                                } class="p-2">"Aprovar"</button>
+                                <button on:click=move |_| {
+                                    moderate_action.dispatch((mention.id, false));
+                                } class="p-2">"Rejeitar"</button>
+                            </li>
+                        }).collect_view(),
+                    Err(err) => view! {
+                        <pre>{err.to_string()}</pre>
+                    }.into_view(),
+                })
+        }}
+        </Suspense>
+        </ul>
+        </div>
+    }
+}
@@ -6,8 +6,11 @@ use web_sys::{FormData, HtmlFormElement, SubmitEvent};
 
 use crate::{
     api::{
-        posts::{create_article, delete_article, get_all_articles, update_article, PostModel},
-        users::{get_user, user_logged_in, LoginUser, UserModel},
+        posts::{
+            create_article, delete_article, get_all_articles, get_featured_articles, pin_article,
+            preview_article, reorder_pinned_articles, update_article, PostModel,
+        },
+        users::{user_logged_in, LoginUser, UserModel},
     },
     components::{
         background::Brickwall,
@@ -25,6 +28,35 @@ pub fn Office() -> impl IntoView {
     let i18n = use_i18n();
 
     let articles_resource = create_resource(|| (), |_| async move { get_all_articles().await });
+    let featured_resource = create_resource(|| (), |_| async move { get_featured_articles().await });
+
+    let pin_action = create_action(move |(article_id, pinned): &(i32, bool)| {
+        let (article_id, pinned) = (*article_id, *pinned);
+        async move {
+            pin_article(article_id, pinned).await;
+            articles_resource.refetch();
+            featured_resource.refetch();
+        }
+    });
+
+    let reorder_action = create_action(move |article_ids: &Vec<i32>| {
+        let article_ids = article_ids.clone();
+        async move {
+            reorder_pinned_articles(article_ids).await;
+            featured_resource.refetch();
+        }
+    });
+
+    // Swap the pinned article on `index` with it's neighbour on `index + offset`
+    let move_pinned = move |index: usize, offset: isize| {
+        if let Some(Ok(mut articles)) = featured_resource.get() {
+            let target = index as isize + offset;
+            if target >= 0 && (target as usize) < articles.len() {
+                articles.swap(index, target as usize);
+                reorder_action.dispatch(articles.iter().map(|article| article.id).collect());
+            }
+        }
+    };
 
     let toggle_writer = create_rw_signal(false);
     let editable = create_rw_signal(-1);
@@ -32,7 +64,18 @@ pub fn Office() -> impl IntoView {
     let title = create_rw_signal(String::new());
     let slug = create_rw_signal(String::new());
     let short_description = create_rw_signal(String::new());
+    let cover_image = create_rw_signal(String::new());
     let content = create_rw_signal(String::new());
+    let preview = create_rw_signal(false);
+    let preview_html = create_resource(
+        move || preview.get().then(|| content.get()),
+        |content| async move {
+            match content {
+                Some(content) => preview_article(content).await.unwrap_or_else(|err| err.to_string()),
+                None => String::new(),
+            }
+        },
+    );
 
     view! {
         <div class="flex flex-col">
@@ -48,13 +91,31 @@ pub fn Office() -> impl IntoView {
                     <input type="text" on:input=move |ev| {
                         slug.set(event_target_value(&ev));
                     } prop:value=slug class="bg-orange-400 border-4 border-t-orange-900 border-l-orange-800 border-r-orange-300 border-b-orange-300 w-full flex-grow p-2 focus:outline-none"/>
+                    <p class="text-left font-bold">"Cover image"</p>
+                    <input type="text" on:input=move |ev| {
+                        cover_image.set(event_target_value(&ev));
+                    } prop:value=cover_image class="bg-orange-400 border-4 border-t-orange-900 border-l-orange-800 border-r-orange-300 border-b-orange-300 w-full flex-grow p-2 focus:outline-none" placeholder="/gallery/1"/>
                     <textarea on:input=move |ev| {
                         short_description.set(event_target_value(&ev));
                     } class="bg-orange-400 border-4 border-t-orange-900 border-l-orange-800 border-r-orange-300 border-b-orange-300 w-full flex-grow p-2 focus:outline-none" placeholder="A short description..."></textarea>
                     <textarea on:input=move |ev| {
                         content.set(event_target_value(&ev));
-                    } class="bg-orange-400 border-4 border-t-orange-900 border-l-orange-800 border-r-orange-300 border-b-orange-300 w-full flex-grow p-2 focus:outline-none" placeholder="Write your article..."></textarea>
+                    } prop:value=content class=move || format!("bg-orange-400 border-4 border-t-orange-900 border-l-orange-800 border-r-orange-300 border-b-orange-300 w-full flex-grow p-2 focus:outline-none {}", if preview.get() { "hidden" } else { "" }) placeholder="Write your article..."></textarea>
+                    <Show when=move || preview.get()>
+                        <Suspense>
+                            <div class="bg-orange-300 border-4 border-black p-2 md-content-area" inner_html=move || preview_html.get().unwrap_or_default()></div>
+                        </Suspense>
+                    </Show>
                     <div class="flex my-2">
+                    <button
+                        on:click=move |ev| {
+                            ev.prevent_default();
+                            preview.update(|preview| *preview = !*preview);
+                        }
+                        class="bg-orange-400 mr-2 border-4 border-b-orange-900 active:border-t-orange-900 border-r-orange-800 active:border-l-orange-900 border-l-orange-300 active:border-r-orange-300 border-t-orange-300 active:border-b-orange-300 w-full cursor-pointer py-2"
+                    >
+                        {move || if preview.get() { "Editar" } else { "Pré-visualizar" }}
+                    </button>
                     <button
                         on:click=move |ev| {
                             ev.prevent_default();
@@ -71,12 +132,12 @@ pub fn Office() -> impl IntoView {
                                 title: title.get(),
                                 slug: slug.get(),
                                 short_desc: Some(short_description.get()),
+                                cover_image: Some(cover_image.get()).filter(|cover| !cover.is_empty()),
                                 content: content.get(),
                                 ..Default::default()
                             };
                             spawn_local(async move {
-                                let user_id = user_logged_in().await.unwrap().unwrap();
-                                uppost.writer = get_user(user_id).await.unwrap().unwrap();
+                                uppost.writer.id = user_logged_in().await.unwrap().unwrap();
                                 if editable.get() != -1 {
                                     uppost.id = editable.get();
                                     update_article(uppost).await;
@@ -95,6 +156,30 @@ pub fn Office() -> impl IntoView {
                 </form>
             }.into_view(),
             false => view! {
+                <Suspense>
+                {move || {
+                    featured_resource.get()
+                        .and_then(|articles| articles.ok())
+                        .filter(|articles| !articles.is_empty())
+                        .map(|articles| view! {
+                            <h2 class="text-3xl font-bold">"Pinned articles"</h2>
+                            <ol class="py-3 px-4">
+                            {articles.into_iter()
+                                .enumerate()
+                                .map(|(index, article)| view! {
+                                    <li class="flex items-center">
+                                        <span class="flex-grow">{article.title}</span>
+                                        <button on:click=move |_| move_pinned(index, -1) class="p-2">"↑"</button>
+                                        <button on:click=move |_| move_pinned(index, 1) class="p-2">"↓"</button>
+                                        <button on:click=move |_| {
+                                            pin_action.dispatch((article.id, false));
+                                        } class="p-2">"Unpin"</button>
+                                    </li>
+                                }).collect_view()}
+                            </ol>
+                        })
+                }}
+                </Suspense>
                 <h2 class="text-3xl font-bold">"Articles available"</h2>
                 <ul class="flex-grow overflow-auto">
                 <Suspense>
@@ -120,9 +205,13 @@ pub fn Office() -> impl IntoView {
                                                         editable.set(article.id);
                                                         slug.set(article.slug.clone());
                                                         short_description.set(article.short_desc.clone().unwrap_or_default());
+                                                        cover_image.set(article.cover_image.clone().unwrap_or_default());
                                                         title.set(article.title.clone());
                                                         toggle_writer.set(true);
                                                     } class="p-2">"Edit"</button>
+                                                    <button on:click=move |_| {
+                                                        pin_action.dispatch((article.id, !article.pinned));
+                                                    } class="p-2">{if article.pinned { "Unpin" } else { "Pin" }}</button>
                                                     <button on:click=move |_| {
                                                         spawn_local(async move {
                                                             delete_article(article.id).await;
@@ -0,0 +1,82 @@
+use base64::{engine::general_purpose, Engine as _};
+use leptos::*;
+
+use crate::api::newsletter::{delete_subscriber, export_subscribers, get_subscribers, send_digest};
+
+/// Lists the newsletter subscribers, renders on /admin/dashboard/subscribers
+#[component]
+pub fn Subscribers() -> impl IntoView {
+    let subscribers = create_resource(|| (), |_| async move { get_subscribers().await });
+
+    let delete_action = create_action(move |subscriber_id: &i32| {
+        let subscriber_id = *subscriber_id;
+        async move {
+            delete_subscriber(subscriber_id).await;
+            subscribers.refetch();
+        }
+    });
+
+    let export_action = create_action(|_: &()| export_subscribers());
+    let digest_action = create_action(|_: &()| send_digest());
+
+    view! {
+        <div class="flex flex-col flex-grow">
+        <h2 class="text-3xl text-center py-2 font-bold">"Assinantes"</h2>
+        <div class="flex my-2">
+            <button
+                on:click=move |_| export_action.dispatch(())
+                class="bg-orange-400 mr-2 border-4 border-b-orange-900 active:border-t-orange-900 border-r-orange-800 active:border-l-orange-900 border-l-orange-300 active:border-r-orange-300 border-t-orange-300 active:border-b-orange-300 w-full cursor-pointer py-2"
+            >
+                "Exportar CSV"
+            </button>
+            <button
+                on:click=move |_| digest_action.dispatch(())
+                class="bg-orange-400 border-4 border-b-orange-900 active:border-t-orange-900 border-r-orange-800 active:border-l-orange-900 border-l-orange-300 active:border-r-orange-300 border-t-orange-300 active:border-b-orange-300 w-full cursor-pointer py-2"
+            >
+                "Enviar resumo agora"
+            </button>
+        </div>
+        {move || export_action.value().get().map(|csv| match csv {
+            Ok(csv) => view! {
+                <a
+                    href=format!("data:text/csv;base64,{}", general_purpose::STANDARD.encode(csv))
+                    download="assinantes.csv"
+                    class="text-center font-bold hover:underline"
+                >
+                    "Baixar assinantes.csv"
+                </a>
+            }.into_view(),
+            Err(err) => view! { <pre>{err.to_string()}</pre> }.into_view(),
+        })}
+        {move || digest_action.value().get().map(|sent| match sent {
+            Ok(sent) => view! { <p class="text-center">{format!("Resumo enviado para {} assinantes.", sent)}</p> }.into_view(),
+            Err(err) => view! { <pre>{err.to_string()}</pre> }.into_view(),
+        })}
+        <ul class="flex-grow overflow-auto">
+        <Suspense>
+        {move || {
+            subscribers.get()
+                .map(|subscribers| match subscribers {
+                    Ok(subscribers) if subscribers.is_empty() => view! {
+                        <p class="text-center">"Nenhum assinante ainda."</p>
+                    }.into_view(),
+                    Ok(subscribers) => subscribers.into_iter()
+                        .map(|subscriber| view! {
+                            <li class="flex items-center py-2 px-4">
+                                <span class="flex-grow break-all">{subscriber.email.clone()}</span>
+                                <span class="px-2">{if subscriber.confirmed { "Confirmado" } else { "Pendente" }}</span>
+                                <button on:click=move |_| {
+                                    delete_action.dispatch(subscriber.id);
+                                } class="p-2">"Remover"</button>
+                            </li>
+                        }).collect_view(),
+                    Err(err) => view! {
+                        <pre>{err.to_string()}</pre>
+                    }.into_view(),
+                })
+        }}
+        </Suspense>
+        </ul>
+        </div>
+    }
+}
@@ -0,0 +1,227 @@
+use leptos::{server_fn::ServerFn, *};
+use server_fn::error::{NoCustomError, ServerFnErrorSerde};
+use wasm_bindgen::{closure::Closure, JsCast};
+use web_sys::{
+    DragEvent, File, FileList, FormData, HtmlInputElement, ProgressEvent, XmlHttpRequest,
+};
+
+use crate::api::images::UploadImage;
+
+/// The types the gallery accepts, for the file picker.
+const ACCEPTED_TYPES: &str = "image/png,image/jpeg,image/gif,image/webp,image/svg+xml";
+
+/// How many files are sent at once, the others wait on the list.
+const MAX_UPLOADS_IN_FLIGHT: usize = 3;
+
+#[derive(Clone, PartialEq)]
+enum UploadStatus {
+    Queued,
+    Sending,
+    Sent,
+    Failed(String),
+}
+
+/// A file dropped or picked, sent on it's own request so it can show it's progress and be sent
+/// again when it fails.
+#[derive(Clone)]
+struct PendingUpload {
+    key: usize,
+    file: File,
+    progress: RwSignal<f64>,
+    status: RwSignal<UploadStatus>,
+}
+
+/// Send a file to `UploadImage` through a `XMLHttpRequest`, as `fetch` can't tell how much of the
+/// body was sent. `on_settled` runs once the request is over, whether it worked or not.
+fn send(upload: &PendingUpload, on_uploaded: Callback<()>, on_settled: impl FnOnce() + 'static) {
+    let (progress, status) = (upload.progress, upload.status);
+    progress.set(0.0);
+    status.set(UploadStatus::Sending);
+
+    let fail = move |error: &str| status.set(UploadStatus::Failed(error.to_string()));
+
+    let Ok(xhr) = XmlHttpRequest::new() else {
+        return fail("O navegador não conseguiu criar a requisição.");
+    };
+    let Ok(form_data) = FormData::new() else {
+        return fail("O navegador não conseguiu montar o formulário.");
+    };
+    if form_data
+        .append_with_blob_and_filename("file_to_upload", &upload.file, &upload.file.name())
+        .is_err()
+        || xhr.open("POST", UploadImage::PATH).is_err()
+    {
+        return fail("O navegador não conseguiu montar a requisição.");
+    }
+    let _ = xhr.set_request_header("Accept", "application/json");
+
+    let on_progress = Closure::<dyn FnMut(ProgressEvent)>::new(move |ev: ProgressEvent| {
+        if ev.length_computable() && ev.total() > 0.0 {
+            progress.set(ev.loaded() / ev.total());
+        }
+    });
+    if let Ok(xhr_upload) = xhr.upload() {
+        xhr_upload.set_onprogress(Some(on_progress.as_ref().unchecked_ref()));
+    }
+
+    // `loadend` comes once, after the request either finished or failed
+    let request = xhr.clone();
+    let on_loadend = Closure::once_into_js(move || {
+        drop(on_progress);
+        match request.status().unwrap_or_default() {
+            200..=299 => {
+                progress.set(1.0);
+                status.set(UploadStatus::Sent);
+                on_uploaded.call(());
+            }
+            0 => fail("A conexão com o servidor falhou."),
+            _ => {
+                let body = request.response_text().ok().flatten().unwrap_or_default();
+                fail(&<ServerFnError<NoCustomError> as ServerFnErrorSerde>::de(&body).to_string());
+            }
+        }
+        on_settled();
+    });
+    xhr.set_onloadend(Some(on_loadend.unchecked_ref()));
+
+    if xhr.send_with_opt_form_data(Some(&form_data)).is_err() {
+        fail("O navegador não conseguiu enviar a imagem.");
+    }
+}
+
+/// Send the queued uploads in order, keeping at most [`MAX_UPLOADS_IN_FLIGHT`] of them sending.
+fn send_queued(uploads: RwSignal<Vec<PendingUpload>>, on_uploaded: Callback<()>) {
+    let pending = uploads.get_untracked();
+    let mut sending = pending
+        .iter()
+        .filter(|upload| upload.status.get_untracked() == UploadStatus::Sending)
+        .count();
+
+    for upload in pending
+        .iter()
+        .filter(|upload| upload.status.get_untracked() == UploadStatus::Queued)
+    {
+        if sending >= MAX_UPLOADS_IN_FLIGHT {
+            break;
+        }
+        send(upload, on_uploaded, move || {
+            send_queued(uploads, on_uploaded)
+        });
+        // Requests the browser couldn't even start have failed already
+        if upload.status.get_untracked() == UploadStatus::Sending {
+            sending += 1;
+        }
+    }
+}
+
+/// Where images are dropped or picked to be sent to the gallery, many at a time, each with it's
+/// progress and any error.
+#[component]
+pub fn UploadZone(#[prop(into)] on_uploaded: Callback<()>) -> impl IntoView {
+    let uploads = create_rw_signal(Vec::<PendingUpload>::new());
+    let next_key = store_value(0usize);
+    let dragging = create_rw_signal(false);
+
+    let add_files = move |files: FileList| {
+        for index in 0..files.length() {
+            let Some(file) = files.get(index) else {
+                continue;
+            };
+            let key = next_key.get_value();
+            next_key.set_value(key + 1);
+
+            let upload = PendingUpload {
+                key,
+                file,
+                progress: create_rw_signal(0.0),
+                status: create_rw_signal(UploadStatus::Queued),
+            };
+            uploads.update(|uploads| uploads.push(upload));
+        }
+        send_queued(uploads, on_uploaded);
+    };
+
+    view! {
+        <div
+            on:dragover=move |ev: DragEvent| {
+                ev.prevent_default();
+                dragging.set(true);
+            }
+            on:dragleave=move |_| dragging.set(false)
+            on:drop=move |ev: DragEvent| {
+                ev.prevent_default();
+                dragging.set(false);
+                if let Some(files) = ev.data_transfer().and_then(|data_transfer| data_transfer.files()) {
+                    add_files(files);
+                }
+            }
+            class="border-4 border-dashed p-6 my-2 text-center"
+            class=("border-orange-900", move || !dragging.get())
+            class=("border-orange-300", move || dragging.get())
+            class=("bg-orange-400", move || dragging.get())
+        >
+            <p>"Arraste as imagens para cá, ou"</p>
+            <label class="underline cursor-pointer">
+                "escolha no computador"
+                <input type="file" multiple accept=ACCEPTED_TYPES class="hidden" on:change=move |ev| {
+                    let input = event_target::<HtmlInputElement>(&ev);
+                    if let Some(files) = input.files() {
+                        add_files(files);
+                    }
+                    // Picking the same files again still sends them
+                    input.set_value("");
+                }/>
+            </label>
+        </div>
+        <ul class="my-2">
+            <For
+                each=move || uploads.get()
+                key=|upload| upload.key
+                let:upload
+            >
+                {
+                    let (progress, status) = (upload.progress, upload.status);
+                    let key = upload.key;
+                    view! {
+                        <li class="my-1">
+                            <div class="flex items-center gap-2">
+                            <span class="w-1/3 truncate" title=upload.file.name()>{upload.file.name()}</span>
+                            <div class="flex-grow h-3 border-2 border-black bg-orange-900">
+                                <div
+                                    class="h-full"
+                                    class=("bg-orange-300", move || status.get() != UploadStatus::Sent)
+                                    class=("bg-green-500", move || status.get() == UploadStatus::Sent)
+                                    style:width=move || format!("{:.0}%", progress.get() * 100.0)
+                                ></div>
+                            </div>
+                            {move || match status.get() {
+                                UploadStatus::Queued => view! { <span>"Na fila"</span> }.into_view(),
+                                UploadStatus::Sending => view! {
+                                    <span>{format!("{:.0}%", progress.get() * 100.0)}</span>
+                                }.into_view(),
+                                UploadStatus::Sent => view! { <span>"Enviada"</span> }.into_view(),
+                                UploadStatus::Failed(_) => view! {
+                                    <span class="text-[#630000]">"Falhou"</span>
+                                    <button on:click=move |_| {
+                                        status.set(UploadStatus::Queued);
+                                        send_queued(uploads, on_uploaded);
+                                    }>"Tentar novamente"</button>
+                                }.into_view(),
+                            }}
+                            <button title="Remover da lista" on:click=move |_| {
+                                uploads.update(|uploads| uploads.retain(|upload| upload.key != key));
+                            }>"✕"</button>
+                            </div>
+                            {move || match status.get() {
+                                UploadStatus::Failed(error) => Some(view! {
+                                    <pre class="text-[#630000] whitespace-pre-wrap">{error}</pre>
+                                }),
+                                _ => None,
+                            }}
+                        </li>
+                    }
+                }
+            </For>
+        </ul>
+    }
+}
@@ -0,0 +1,250 @@
+use leptos::*;
+use leptos_i18n::Locale as _;
+use leptos_meta::*;
+use leptos_router::*;
+
+use crate::{
+    api::{
+        albums::{get_album, get_public_albums},
+        images::{image_url, ImagePreview, ImageSize, PublicImageModel},
+    },
+    components::{
+        background::Brickwall, container::RetroContainer, image::ResponsiveImage,
+        navbar::NavbarReserved,
+    },
+};
+
+use crate::i18n::*;
+
+#[derive(Params, PartialEq)]
+pub struct AlbumParams {
+    slug: String,
+}
+
+/// The alt text of an image on the visitor's locale, or on any locale it has.
+fn alt_for(image: &PublicImageModel, locale: Locale) -> String {
+    image
+        .alt_text(locale.as_str())
+        .or(image
+            .alt_texts
+            .first()
+            .map(|alt_text| alt_text.alt_text.as_str()))
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// The public albums, renders on /albums
+#[component]
+pub fn Albums() -> impl IntoView {
+    let i18n = use_i18n();
+    let albums = create_resource(|| (), |_| get_public_albums());
+
+    view! {
+        <Title text="Albums"/>
+        <Brickwall>
+            <NavbarReserved/>
+            <div class="p-4 px-8">
+                <RetroContainer>
+                    <h1 class="text-4xl text-white font-bold my-1">{t!(i18n, albums.albums)}</h1>
+                    <p>{t!(i18n, albums.albums_description)}</p>
+                </RetroContainer>
+                <ul class="pt-3 grid grid-cols-1 md:grid-cols-2 lg:grid-cols-3 gap-6">
+                <Suspense>
+                {move || {
+                    albums.get()
+                        .map(|albums| match albums {
+                            Ok(albums) if albums.is_empty() => view! {
+                                <li class="py-3">
+                                    <RetroContainer>
+                                        <p>{t!(i18n, albums.no_albums)}</p>
+                                    </RetroContainer>
+                                </li>
+                            }.into_view(),
+                            Ok(albums) => albums.into_iter()
+                                .map(|album| {
+                                    let total = album.images.len();
+                                    view! {
+                                        <li class="py-3">
+                                            <A href=format!("/albums/{}", album.slug)>
+                                                <RetroContainer class="hover:brightness-110">
+                                                    {album.cover().map(|cover| view! {
+                                                        <ResponsiveImage
+                                                            id=cover
+                                                            preview=album.images.iter()
+                                                                .find(|image| image.id == cover)
+                                                                .map(ImagePreview::from)
+                                                                .unwrap_or_default()
+                                                            class="w-full aspect-[4/3] object-cover border-4 border-black"
+                                                            sizes="(min-width: 1024px) 33vw, (min-width: 768px) 50vw, 100vw"
+                                                        />
+                                                    })}
+                                                    <h2 class="text-2xl text-white font-bold my-1">{album.title}</h2>
+                                                    <p class="text-[#630000]">{t!(i18n, albums.photo_count, count = move || total as i32, total = total)}</p>
+                                                </RetroContainer>
+                                            </A>
+                                        </li>
+                                    }
+                                }).collect_view(),
+                            Err(msg) => view! {
+                                <li class="py-3">
+                                    <RetroContainer>
+                                        {t!(i18n, home.post_error, msg = msg.to_string())}
+                                    </RetroContainer>
+                                </li>
+                            }.into_view()
+                        })
+                }}
+                </Suspense>
+                </ul>
+            </div>
+        </Brickwall>
+    }
+}
+
+/// An album with it's photos, renders on /albums/:slug
+#[component]
+pub fn AlbumViewer() -> impl IntoView {
+    let i18n = use_i18n();
+    let params = use_params::<AlbumParams>();
+
+    let slug = move || {
+        params.with(|params| {
+            params
+                .as_ref()
+                .map(|params| params.slug.clone())
+                .unwrap_or_default()
+        })
+    };
+
+    let album = create_resource(slug, get_album);
+
+    view! {
+        <Brickwall>
+            <NavbarReserved/>
+            <div class="p-4 px-8">
+            <Suspense>
+            {move || {
+                album.get()
+                    .map(|album| match album {
+                        Ok(Some(album)) => view! {
+                            <Title text=album.title.clone()/>
+                            <RetroContainer>
+                                <h1 class="text-4xl text-white font-bold my-1">{album.title.clone()}</h1>
+                                {album.description.clone().map(|description| view! {
+                                    <p class="whitespace-pre-line">{description}</p>
+                                })}
+                            </RetroContainer>
+                            <Lightbox images=album.images/>
+                        }.into_view(),
+                        Ok(None) => view! {
+                            <Title text="Albums"/>
+                            <RetroContainer>
+                                <p>{t!(i18n, albums.album_not_found)}</p>
+                            </RetroContainer>
+                        }.into_view(),
+                        Err(msg) => view! {
+                            <RetroContainer>
+                                {t!(i18n, home.post_error, msg = msg.to_string())}
+                            </RetroContainer>
+                        }.into_view()
+                    })
+            }}
+            </Suspense>
+            </div>
+        </Brickwall>
+    }
+}
+
+/// The photo grid of an album. Clicking a photo opens it bigger, and the arrow keys move between
+/// photos while it's open.
+#[component]
+fn Lightbox(images: Vec<PublicImageModel>) -> impl IntoView {
+    let i18n = use_i18n();
+    let images = store_value(images);
+    let total = images.with_value(Vec::len);
+    let (open, set_open) = create_signal(None::<usize>);
+
+    let previous = move || {
+        set_open.update(|open| {
+            *open = open.map(|index| (index + total - 1) % total);
+        })
+    };
+    let next = move || {
+        set_open.update(|open| {
+            *open = open.map(|index| (index + 1) % total);
+        })
+    };
+
+    let keys = window_event_listener(ev::keydown, move |ev| {
+        if open.get_untracked().is_none() {
+            return;
+        }
+        match ev.key().as_str() {
+            "ArrowLeft" => previous(),
+            "ArrowRight" => next(),
+            "Escape" => set_open.set(None),
+            _ => return,
+        }
+        ev.prevent_default();
+    });
+    on_cleanup(move || keys.remove());
+
+    let button_class = "bg-orange-400 border-4 border-b-orange-900 active:border-t-orange-900 border-r-orange-800 active:border-l-orange-900 border-l-orange-300 active:border-r-orange-300 border-t-orange-300 active:border-b-orange-300 cursor-pointer py-2 px-4";
+
+    view! {
+        <ul class="pt-6 grid grid-cols-2 md:grid-cols-3 lg:grid-cols-4 gap-4">
+            {images.get_value().into_iter().enumerate()
+                .map(|(index, image)| {
+                    let alt = alt_for(&image, i18n.get_locale_untracked());
+                    view! {
+                        <li>
+                            <button class="w-full focus:outline-none focus:brightness-110" on:click=move |_| set_open.set(Some(index))>
+                                <ResponsiveImage
+                                    id=image.id
+                                    alt=alt
+                                    preview=ImagePreview::from(&image)
+                                    class="w-full aspect-square object-cover border-4 border-black shadow-[#000000aa_8px_8px]"
+                                    sizes="(min-width: 1024px) 25vw, (min-width: 768px) 33vw, 50vw"
+                                />
+                            </button>
+                        </li>
+                    }
+                }).collect_view()}
+        </ul>
+        {move || open.get().and_then(|index| images.with_value(|images| images.get(index).cloned())).map(|image| {
+            let index = open.get().unwrap_or_default();
+            let alt = alt_for(&image, i18n.get_locale());
+            view! {
+                <div
+                    class="fixed inset-0 z-50 bg-[#000000cc] flex items-center justify-center p-4"
+                    role="dialog"
+                    aria-modal="true"
+                    aria-label=alt.clone()
+                    on:click=move |_| set_open.set(None)
+                >
+                    <div class="max-w-5xl w-full" on:click=|ev| ev.stop_propagation()>
+                        <RetroContainer>
+                            <img
+                                class="w-full max-h-[75vh] object-contain border-4 border-black bg-black"
+                                src=image_url(image.id, Some(ImageSize::Large))
+                                alt=alt
+                            />
+                            {image.caption.clone().map(|caption| view! {
+                                <p class="mt-2">{caption}</p>
+                            })}
+                            {image.credit.clone().map(|credit| view! {
+                                <p class="text-[#630000] text-sm">"© "{credit}</p>
+                            })}
+                            <div class="flex items-center mt-2 gap-2">
+                                <button class=button_class on:click=move |_| previous()>{t!(i18n, albums.previous)}</button>
+                                <span class="flex-grow text-center">{index + 1}" / "{total}</span>
+                                <button class=button_class on:click=move |_| next()>{t!(i18n, albums.next)}</button>
+                                <button class=button_class on:click=move |_| set_open.set(None) autofocus>{t!(i18n, albums.close)}</button>
+                            </div>
+                        </RetroContainer>
+                    </div>
+                </div>
+            }
+        })}
+    }
+}
@@ -0,0 +1,109 @@
+use chrono::Datelike;
+use leptos::*;
+use leptos_meta::*;
+use leptos_router::*;
+
+use crate::{
+    api::posts::get_archive,
+    components::{background::Brickwall, container::RetroContainer, navbar::NavbarReserved},
+};
+
+use crate::i18n::*;
+
+#[derive(Params, PartialEq)]
+pub struct ArchiveParams {
+    year: Option<i32>,
+    month: Option<u32>,
+}
+
+/// The archive page, renders on /archive, /archive/:year and /archive/:year/:month
+#[component]
+pub fn Archive() -> impl IntoView {
+    let i18n = use_i18n();
+    let params = use_params::<ArchiveParams>();
+
+    let period = move || {
+        params.with(|params| {
+            params
+                .as_ref()
+                .map(|params| (params.year, params.month))
+                .unwrap_or_default()
+        })
+    };
+
+    let archive = create_resource(period, |(year, month)| async move {
+        get_archive(year, month).await
+    });
+
+    view! {
+        <Title text="Archive"/>
+        <Brickwall>
+            <NavbarReserved/>
+            <div class="p-4 px-8">
+                <RetroContainer class="flex items-center">
+                    <hr class="border-t-2 mx-2 border-t-black flex-grow"/>
+                    <A href="/archive" class="hover:underline decoration-2 underline-offset-2 decoration-black">{t!(i18n, posts.archive)}</A>
+                    {move || period().0.map(|year| view! {
+                        <span class="mx-1">"/"</span>
+                        <A href=format!("/archive/{}", year) class="hover:underline decoration-2 underline-offset-2 decoration-black">{year}</A>
+                    })}
+                    <hr class="border-t-2 mx-2 border-t-black flex-grow"/>
+                </RetroContainer>
+                <ul class="pt-3">
+                <Suspense>
+                {move || {
+                    archive.get()
+                        .map(|archive| match archive {
+                            Ok(archive) if archive.is_empty() => view! {
+                                <li class="py-3">
+                                    <RetroContainer>
+                                        <p>{t!(i18n, posts.archive_empty)}</p>
+                                    </RetroContainer>
+                                </li>
+                            }.into_view(),
+                            Ok(archive) => archive.into_iter()
+                                .map(|group| {
+                                    let year = group.year;
+                                    let month = group.month;
+                                    let total = group.articles.len();
+                                    view! {
+                                        <li class="py-3">
+                                            <RetroContainer>
+                                                <div class="flex items-baseline">
+                                                    <A href=format!("/archive/{}/{}", year, month) class="hover:underline flex-grow decoration-2 underline-offset-2 decoration-white">
+                                                        <h2 class="text-2xl text-white font-bold my-1">{t!(i18n, common.month, count = move || month as i32, year = year)}</h2>
+                                                    </A>
+                                                    <p class="text-[#630000]">{t!(i18n, posts.article_count, count = move || total as i32, total = total)}</p>
+                                                </div>
+                                                <hr class="border-t-2"/>
+                                                <ul class="my-2">
+                                                {group.articles.into_iter()
+                                                    .map(|article| {
+                                                        let day = article.created_at.day();
+                                                        view! {
+                                                            <li class="my-1">
+                                                                <A href=format!("/articles/{}", article.slug) class="hover:underline decoration-2 underline-offset-2 decoration-[#630000]">{article.title}</A>
+                                                                <span class="text-[#630000]">" — "{t!(i18n, common.date, day = day, count = move || month as i32, year = year)}</span>
+                                                            </li>
+                                                        }
+                                                    }).collect_view()}
+                                                </ul>
+                                            </RetroContainer>
+                                        </li>
+                                    }
+                                }).collect_view(),
+                            Err(msg) => view! {
+                                <li class="py-3">
+                                    <RetroContainer>
+                                        {t!(i18n, home.post_error, msg = msg.to_string())}
+                                    </RetroContainer>
+                                </li>
+                            }.into_view()
+                        })
+                }}
+                </Suspense>
+                </ul>
+            </div>
+        </Brickwall>
+    }
+}
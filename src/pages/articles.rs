@@ -1,3 +1,4 @@
+pub mod featured;
 pub mod handler;
 pub mod list;
 
@@ -0,0 +1,47 @@
+use leptos::*;
+use leptos_router::*;
+
+use crate::{api::posts::get_featured_articles, components::container::RetroContainer};
+
+use crate::i18n::*;
+
+/// The pinned articles with their cover images, shown on top of the home page
+#[component]
+pub fn FeaturedArticles() -> impl IntoView {
+    let i18n = use_i18n();
+
+    let articles = create_resource(|| (), |_| async move { get_featured_articles().await });
+
+    view! {
+        <Suspense>
+        {move || {
+            articles.get()
+                .and_then(|articles| articles.ok())
+                .filter(|articles| !articles.is_empty())
+                .map(|articles| view! {
+                    <RetroContainer class="flex items-center">
+                        <hr class="border-t-2 mx-2 border-t-black flex-grow"/>
+                        <p>{t!(i18n, home.featured)}</p>
+                        <hr class="border-t-2 mx-2 border-t-black flex-grow"/>
+                    </RetroContainer>
+                    <ul class="grid grid-cols-1 md:grid-cols-2 gap-6 py-6">
+                    {articles.into_iter()
+                        .map(|article| view! {
+                            <li>
+                                <A href=format!("/articles/{}", article.slug) class="group block h-full">
+                                    <RetroContainer class="h-full">
+                                        {article.cover_image.clone().map(|cover| view! {
+                                            <img class="w-full aspect-video object-cover border-4 border-black mb-2" src=cover alt="" loading="lazy"/>
+                                        })}
+                                        <h2 class="text-2xl text-white font-bold my-1 group-hover:underline decoration-2 underline-offset-2 decoration-white">{article.title}</h2>
+                                        <p>{article.short_desc}</p>
+                                    </RetroContainer>
+                                </A>
+                            </li>
+                        }).collect_view()}
+                    </ul>
+                })
+        }}
+        </Suspense>
+    }
+}
@@ -5,7 +5,11 @@ use leptos_router::*;
 use web_sys::{FormData, HtmlFormElement};
 
 use crate::{
-    api::posts::{get_article, PostModel},
+    api::{
+        posts::{get_article, PostModel},
+        users::PublicProfileModel,
+        webmentions::get_webmentions,
+    },
     components::{
         background::Brickwall,
         container::RetroContainer,
@@ -22,20 +26,18 @@ pub struct ArticleParams {
 
 #[component]
 pub fn ArticleReader(
+    id: i32,
     title: String,
     date: DateTime<FixedOffset>,
-    writer: String,
-    content: String,
+    writer: PublicProfileModel,
+    /// The article already rendered to HTML by the server.
+    html: String,
 ) -> impl IntoView {
     let i18n = use_i18n();
     let day = date.day();
     let month = date.month();
     let year = date.year();
 
-    let parser = pulldown_cmark::Parser::new(&content);
-    let mut inner_html = String::new();
-    pulldown_cmark::html::push_html(&mut inner_html, parser);
-
     view! {
         <Brickwall>
             <NavbarReserved/>
@@ -45,13 +47,52 @@ pub fn ArticleReader(
                 <Title text={title}/>
                 <hr class="border-t-2"/>
 
-                <p class="text-[#630000] mb-2">{t!(i18n, posts.written)}" "{t!(i18n, common.date, day = day, count = move || month as i32, year = year)}</p>
-                <div class="mb-3 md-content-area" inner_html=inner_html></div>
+                <p class="text-[#630000] mb-2">
+                    {t!(i18n, posts.written)}" "{t!(i18n, common.date, day = day, count = move || month as i32, year = year)}" "
+                    {t!(i18n, posts.by)}" "<A href=format!("/authors/{}", writer.username) class="font-bold hover:underline decoration-2 underline-offset-2 decoration-[#630000]">{writer.name()}</A>
+                </p>
+                <div class="mb-3 md-content-area" inner_html=html></div>
             </RetroContainer>
+            <Webmentions post_id=id/>
             </div>
         </Brickwall>
     }
 }
+/// The approved webmentions of an article, hidden when there are none
+#[component]
+pub fn Webmentions(post_id: i32) -> impl IntoView {
+    let i18n = use_i18n();
+
+    let mentions = create_resource(move || post_id, get_webmentions);
+
+    view! {
+        <Suspense>
+        {move || {
+            mentions.get()
+                .and_then(|mentions| mentions.ok())
+                .filter(|mentions| !mentions.is_empty())
+                .map(|mentions| view! {
+                    <RetroContainer class="mt-4">
+                        <h2 class="text-2xl text-white font-bold my-1">{t!(i18n, posts.mentions)}</h2>
+                        <hr class="border-t-2"/>
+                        <p class="mb-2">{t!(i18n, posts.mentions_description)}</p>
+                        <ul class="list-disc pl-6">
+                        {mentions.into_iter()
+                            .map(|mention| view! {
+                                <li>
+                                    <a href=mention.source.clone() target="_blank" rel="noopener nofollow ugc" class="hover:underline break-all">
+                                        {mention.title.clone().unwrap_or_else(|| mention.source.clone())}
+                                    </a>
+                                </li>
+                            }).collect_view()}
+                        </ul>
+                    </RetroContainer>
+                })
+        }}
+        </Suspense>
+    }
+}
+
 /// The home page component, renders on / default path
 #[component]
 pub fn ArticleLoader() -> impl IntoView {
@@ -77,7 +118,7 @@ pub fn ArticleLoader() -> impl IntoView {
                     Ok(article_exists) => {
                         match article_exists {
                             Some(article) => view!{
-                                <ArticleReader title=article.title.clone() date=article.updated_at.clone() writer=article.writer.username.clone() content=article.content.clone()/>
+                                <ArticleReader id=article.id title=article.title.clone() date=article.updated_at.clone() writer=article.writer.clone() html=article.rendered_content.clone()/>
                             },
                             None => view! {<p>"ops"</p>}.into_view()
                         }
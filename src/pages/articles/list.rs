@@ -4,7 +4,7 @@ use leptos_router::*;
 use web_sys::{FormData, HtmlFormElement};
 
 use crate::{
-    api::posts::get_all_articles,
+    api::{posts::get_all_articles, users::get_all_authors},
     components::{background::Brickwall, container::RetroContainer, navbar::Navbar},
 };
 
@@ -16,6 +16,7 @@ pub fn ArticleList() -> impl IntoView {
     let i18n = use_i18n();
 
     let articles = create_resource(|| (), |_| async move { get_all_articles().await });
+    let authors = create_resource(|| (), |_| async move { get_all_authors().await });
 
     view! {
         <ul class="pt-3">
@@ -29,13 +30,21 @@ pub fn ArticleList() -> impl IntoView {
                             let day = date.day();
                             let month = date.month();
                             let year = date.year();
+                            let writer = authors.get()
+                                .and_then(|authors| authors.ok())
+                                .and_then(|authors| authors.into_iter().find(|author| author.id == article.writer_id));
                             view! {
                                 <li class="py-3">
                                     <RetroContainer>
                                         <A href=format!("/articles/{}", article.slug) class="hover:underline decoration-2 underline-offset-2 decoration-white"><h2 class="text-2xl text-white font-bold my-1">{article.title}</h2></A>
                                         <hr class="border-t-2"/>
 
-                                        <p class="text-[#630000] mb-2">{t!(i18n, posts.written)}" "{t!(i18n, common.date, day = day, count = move || month as i32, year = year)}</p>
+                                        <p class="text-[#630000] mb-2">
+                                            {t!(i18n, posts.written)}" "{t!(i18n, common.date, day = day, count = move || month as i32, year = year)}
+                                            {writer.map(|writer| view! {
+                                                " "{t!(i18n, posts.by)}" "<A href=format!("/authors/{}", writer.username) class="font-bold hover:underline decoration-2 underline-offset-2 decoration-[#630000]">{writer.name()}</A>
+                                            })}
+                                        </p>
                                         <p class="mb-3">{article.short_desc}</p>
                                         <A href=format!("/articles/{}", article.slug) class="hover:underline decoration-2 underline-offset-2 decoration-[#630000]"><p class="text-center text-[#630000]">{t!(i18n, posts.read_more)}</p></A>
                                     </RetroContainer>
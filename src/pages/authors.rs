@@ -0,0 +1,135 @@
+use chrono::Datelike;
+use leptos::*;
+use leptos_meta::*;
+use leptos_router::*;
+
+use crate::{
+    api::{posts::get_articles_by_writer, users::get_author},
+    components::{background::Brickwall, container::RetroContainer, navbar::NavbarReserved},
+};
+
+use crate::i18n::*;
+
+#[derive(Params, PartialEq)]
+pub struct AuthorParams {
+    username: String,
+}
+
+/// The public profile of an author, renders on /authors/:username
+#[component]
+pub fn AuthorPage() -> impl IntoView {
+    let i18n = use_i18n();
+    let params = use_params::<AuthorParams>();
+
+    let username = move || {
+        params.with(|params| {
+            params
+                .as_ref()
+                .map(|params| params.username.clone())
+                .unwrap_or_default()
+        })
+    };
+
+    let author = create_resource(username, get_author);
+    let articles = create_resource(
+        move || author.get().and_then(|author| author.ok().flatten()).map(|author| author.id),
+        |writer_id| async move {
+            match writer_id {
+                Some(writer_id) => get_articles_by_writer(writer_id).await,
+                None => Ok(vec![]),
+            }
+        },
+    );
+
+    view! {
+        <Brickwall>
+            <NavbarReserved/>
+            <div class="p-4 px-8">
+            <Suspense>
+            {move || {
+                author.get()
+                    .map(|author| match author {
+                        Ok(Some(author)) => {
+                            let name = author.name();
+                            let avatar = author.avatar.clone().map(|avatar| view! {
+                                <img class="h-24 w-24 mr-4 object-cover border-4 border-black" src=avatar alt=name.clone()/>
+                            });
+                            let author_articles = {
+                                let name = name.clone();
+                                t!(i18n, posts.author_articles, name = name)
+                            };
+                            view! {
+                            <Title text=name.clone()/>
+                            <RetroContainer>
+                                <div class="flex items-center">
+                                    {avatar}
+                                    <div>
+                                        <h1 class="text-4xl text-white font-bold my-1">{name.clone()}</h1>
+                                        <p class="text-[#630000]">"@"{author.username.clone()}</p>
+                                    </div>
+                                </div>
+                                <hr class="border-t-2 my-2"/>
+                                <p class="mb-3 whitespace-pre-line">{author.bio.clone()}</p>
+                                <ul class="flex flex-wrap">
+                                {author.social_links.clone().into_iter()
+                                    .map(|link| view! {
+                                        <li class="mr-4"><a class="text-black hover:underline decoration-2 underline-offset-2 decoration-black" rel="me noopener" target="_blank" href=link.url>{link.label}</a></li>
+                                    }).collect_view()}
+                                </ul>
+                            </RetroContainer>
+                            <RetroContainer class="flex items-center mt-6">
+                                <hr class="border-t-2 mx-2 border-t-black flex-grow"/>
+                                <p>{author_articles}</p>
+                                <hr class="border-t-2 mx-2 border-t-black flex-grow"/>
+                            </RetroContainer>
+                            }.into_view()
+                        }
+                        Ok(None) => view! {
+                            <RetroContainer>
+                                <p>{t!(i18n, posts.author_not_found)}</p>
+                            </RetroContainer>
+                        }.into_view(),
+                        Err(msg) => view! {
+                            <RetroContainer>
+                                {t!(i18n, home.post_error, msg = msg.to_string())}
+                            </RetroContainer>
+                        }.into_view()
+                    })
+            }}
+            </Suspense>
+            <ul class="pt-3">
+            <Suspense>
+            {move || {
+                articles.get()
+                    .map(|articles| match articles {
+                        Ok(articles) => articles.into_iter()
+                            .map(|article| {
+                                let date = article.updated_at.clone();
+                                let day = date.day();
+                                let month = date.month();
+                                let year = date.year();
+                                view! {
+                                    <li class="py-3">
+                                        <RetroContainer>
+                                            <A href=format!("/articles/{}", article.slug) class="hover:underline decoration-2 underline-offset-2 decoration-white"><h2 class="text-2xl text-white font-bold my-1">{article.title}</h2></A>
+                                            <hr class="border-t-2"/>
+
+                                            <p class="text-[#630000] mb-2">{t!(i18n, posts.written)}" "{t!(i18n, common.date, day = day, count = move || month as i32, year = year)}</p>
+                                            <p class="mb-3">{article.short_desc}</p>
+                                        </RetroContainer>
+                                    </li>
+                                }
+                            }).collect_view(),
+                        Err(msg) => view! {
+                            <RetroContainer>
+                                {t!(i18n, home.post_error, msg = msg.to_string())}
+                            </RetroContainer>
+                        }.into_view()
+                    })
+            }}
+            </Suspense>
+            </ul>
+            </div>
+        </Brickwall>
+    }
+}
@@ -9,8 +9,9 @@ use crate::{
         background::Brickwall,
         container::RetroContainer,
         navbar::{Navbar, NavbarReserved},
+        newsletter::NewsletterForm,
     },
-    pages::articles::list::ArticleList,
+    pages::articles::{featured::FeaturedArticles, list::ArticleList},
 };
 
 use crate::i18n::*;
@@ -28,6 +29,7 @@ pub fn Index() -> impl IntoView {
             <NavbarReserved/>
             <div class="flex md:flex-row flex-col py-2 px-9">
                 <section class="flex-grow md:mr-8">
+                    <FeaturedArticles/>
                     <RetroContainer class="flex items-center">
                         <hr class="border-t-2 mx-2 border-t-black flex-grow"/>
                         <p class="">{t!(i18n, home.recent_post)}</p>
@@ -47,6 +49,7 @@ pub fn Index() -> impl IntoView {
                             <li><a class="hover:invert inline-block text-black hover:underline decoration-2 underline-offset-2 decoration-black" href="mailto:orangethewell@gmail.com"><img class="h-8 w-auto m-1 inline-block" src="/email.svg"/>"E-mail"</a></li>
                         </ul>
                     </RetroContainer>
+                    <NewsletterForm/>
                 </aside>
             </div>
             <a class="fixed max-sm:hidden right-[4%] -bottom-10" target="_blank" href="/Stellarbonds.html"><img style="image-rendering: pixelated;" class="h-24 w-auto" on:mouseover=move |_| {
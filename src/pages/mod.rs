@@ -6,4 +6,12 @@ pub use about_me::AboutMe;
 
 pub mod articles;
 
+pub mod archive;
+
+pub mod albums;
+
+pub mod authors;
+
+pub mod newsletter;
+
 pub mod admin;
@@ -0,0 +1,102 @@
+use leptos::*;
+use leptos_meta::*;
+use leptos_router::*;
+
+use crate::{
+    api::newsletter::{confirm_subscription, unsubscribe},
+    components::{background::Brickwall, container::RetroContainer, navbar::NavbarReserved},
+};
+
+use crate::i18n::*;
+
+#[derive(Params, PartialEq)]
+pub struct NewsletterParams {
+    token: String,
+}
+
+fn use_token() -> impl Fn() -> String + Copy {
+    let params = use_params::<NewsletterParams>();
+
+    move || {
+        params.with(|params| {
+            params
+                .as_ref()
+                .map(|params| params.token.clone())
+                .unwrap_or_default()
+        })
+    }
+}
+
+/// Confirms a newsletter subscription once the reader clicks, so link previews and mail scanners
+/// opening the link don't confirm it, renders on /newsletter/confirm/:token
+#[component]
+pub fn NewsletterConfirm() -> impl IntoView {
+    let i18n = use_i18n();
+    let token = use_token();
+
+    let confirm_action = create_action(|token: &String| confirm_subscription(token.clone()));
+
+    view! {
+        <Title text="Newsletter"/>
+        <Brickwall>
+            <NavbarReserved/>
+            <div class="p-4 px-8">
+            <RetroContainer>
+                <h1 class="text-4xl text-white font-bold my-1">{t!(i18n, home.newsletter)}</h1>
+                <hr class="border-t-2"/>
+                {move || match confirm_action.value().get() {
+                    Some(Ok(true)) => view! { <p class="my-2">{t!(i18n, home.newsletter_confirmed)}</p> }.into_view(),
+                    Some(Ok(false)) => view! { <p class="my-2">{t!(i18n, home.newsletter_invalid_link)}</p> }.into_view(),
+                    Some(Err(err)) => view! { <p class="my-2">{err.to_string()}</p> }.into_view(),
+                    None => view! {
+                        <p class="my-2">{t!(i18n, home.newsletter_confirm)}</p>
+                        <button
+                            on:click=move |_| confirm_action.dispatch(token())
+                            class="bg-orange-400 border-4 border-b-orange-900 active:border-t-orange-900 border-r-orange-800 active:border-l-orange-900 border-l-orange-300 active:border-r-orange-300 border-t-orange-300 active:border-b-orange-300 w-full cursor-pointer py-2"
+                        >
+                            {t!(i18n, home.newsletter_confirm_button)}
+                        </button>
+                    }.into_view(),
+                }}
+            </RetroContainer>
+            </div>
+        </Brickwall>
+    }
+}
+
+/// Cancels a newsletter subscription after the reader confirms it, renders on
+/// /newsletter/unsubscribe/:token
+#[component]
+pub fn NewsletterUnsubscribe() -> impl IntoView {
+    let i18n = use_i18n();
+    let token = use_token();
+
+    let unsubscribe_action = create_action(|token: &String| unsubscribe(token.clone()));
+
+    view! {
+        <Title text="Newsletter"/>
+        <Brickwall>
+            <NavbarReserved/>
+            <div class="p-4 px-8">
+            <RetroContainer>
+                <h1 class="text-4xl text-white font-bold my-1">{t!(i18n, home.newsletter)}</h1>
+                <hr class="border-t-2"/>
+                {move || match unsubscribe_action.value().get() {
+                    Some(Ok(true)) => view! { <p class="my-2">{t!(i18n, home.newsletter_unsubscribed)}</p> }.into_view(),
+                    Some(Ok(false)) => view! { <p class="my-2">{t!(i18n, home.newsletter_invalid_link)}</p> }.into_view(),
+                    Some(Err(err)) => view! { <p class="my-2">{err.to_string()}</p> }.into_view(),
+                    None => view! {
+                        <p class="my-2">{t!(i18n, home.newsletter_unsubscribe)}</p>
+                        <button
+                            on:click=move |_| unsubscribe_action.dispatch(token())
+                            class="bg-orange-400 border-4 border-b-orange-900 active:border-t-orange-900 border-r-orange-800 active:border-l-orange-900 border-l-orange-300 active:border-r-orange-300 border-t-orange-300 active:border-b-orange-300 w-full cursor-pointer py-2"
+                        >
+                            {t!(i18n, home.newsletter_unsubscribe_button)}
+                        </button>
+                    }.into_view(),
+                }}
+            </RetroContainer>
+            </div>
+        </Brickwall>
+    }
+}
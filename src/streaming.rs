@@ -0,0 +1,351 @@
+//! ## Streaming
+//! Files are served from disk as streams, never read whole into memory, with a `Content-Length`
+//! and `Accept-Ranges: bytes`. A `Range` asking for one span gets a `206 Partial Content` with
+//! just those bytes, so big downloads can be resumed and videos seeked. Ranges with many spans
+//! get the whole file, as RFC 9110 allows.
+//!
+//! Hashing a file to get it's `ETag` would mean reading it whole, so callers that know a hash of
+//! the content, like the one saved on upload, pass it; otherwise the `ETag` comes from the size
+//! and modification time of the file. The same goes for `Last-Modified`: callers that know when
+//! the content was made, like the upload date of an image, pass it, as copying or restoring the
+//! file changes it's modification time without changing the content. Files fetched from elsewhere, like an object storage, are
+//! sent from memory by [`serve_bytes`], with the same validators and ranges.
+
+use crate::caching::{http_date, is_not_modified, not_modified, set_validators};
+use axum::{
+    body::Body,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use std::{io::SeekFrom, path::Path, time::SystemTime};
+use tokio::{
+    fs::File,
+    io::{AsyncReadExt, AsyncSeekExt},
+};
+use tokio_util::io::ReaderStream;
+
+/// How much of a file is read at a time.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// A strong `ETag` from a hex hash of the content.
+pub fn hash_etag(hash: &str) -> String {
+    format!("\"{}\"", hash)
+}
+
+/// A strong `ETag` from the size and modification time of a file. Files are written aside and
+/// renamed, so they never change without their modification time changing too.
+fn metadata_etag(len: u64, modified: Option<SystemTime>) -> String {
+    let modified = modified
+        .and_then(|modified| modified.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map(|modified| modified.as_nanos())
+        .unwrap_or_default();
+    format!("\"{:x}-{:x}\"", len, modified)
+}
+
+/// The span asked by a `Range` header, as the first and last byte, both included. `None` means
+/// the header should be ignored and the whole file served, `Some(Err(()))` that it can't be
+/// satisfied.
+fn parse_range(value: &str, len: u64) -> Option<Result<(u64, u64), ()>> {
+    let spec = value.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        return None;
+    }
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = (start.trim(), end.trim());
+
+    let span = match (start.is_empty(), end.is_empty()) {
+        // The last `end` bytes.
+        (true, false) => {
+            let suffix = end.parse::<u64>().ok()?;
+            if suffix == 0 || len == 0 {
+                return Some(Err(()));
+            }
+            (len.saturating_sub(suffix), len - 1)
+        }
+        (false, _) => {
+            let start = start.parse::<u64>().ok()?;
+            let end = match end.is_empty() {
+                true => u64::MAX,
+                false => end.parse::<u64>().ok()?,
+            };
+            if end < start {
+                return None;
+            }
+            if start >= len {
+                return Some(Err(()));
+            }
+            (start, end.min(len - 1))
+        }
+        (true, true) => return None,
+    };
+
+    Some(Ok(span))
+}
+
+/// Whether the `If-Range` of the request, if any, still describes the file, so the `Range` can be
+/// honored. Dates are only trusted when they are the exact modification time.
+fn if_range_matches(headers: &HeaderMap, etag: &str, modified: Option<SystemTime>) -> bool {
+    let Some(if_range) = headers
+        .get(header::IF_RANGE)
+        .and_then(|value| value.to_str().ok())
+    else {
+        return true;
+    };
+
+    if if_range.starts_with('"') || if_range.starts_with("W/") {
+        // Ranges need the strong comparison.
+        return if_range == etag;
+    }
+
+    match modified {
+        Some(modified) => httpdate::parse_http_date(if_range)
+            .is_ok_and(|since| http_date(since) == http_date(modified)),
+        None => false,
+    }
+}
+
+/// The part of a file a response sends.
+struct Span {
+    status: StatusCode,
+    start: u64,
+    end: u64,
+    content_length: u64,
+}
+
+/// Check the validators and the `Range` of the request, giving the part of the file to send, or
+/// the response when there's nothing to send.
+fn plan(
+    headers: &HeaderMap,
+    len: u64,
+    etag: &str,
+    modified: Option<SystemTime>,
+    cache_control: &'static str,
+) -> Result<Span, Response> {
+    if is_not_modified(headers, Some(etag), modified) {
+        return Err(not_modified(etag, modified, cache_control));
+    }
+
+    let range = headers
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok())
+        .filter(|_| if_range_matches(headers, etag, modified))
+        .and_then(|value| parse_range(value, len));
+
+    let (status, start, end) = match range {
+        None => (StatusCode::OK, 0, len.saturating_sub(1)),
+        Some(Ok((start, end))) => (StatusCode::PARTIAL_CONTENT, start, end),
+        Some(Err(())) => {
+            let mut response = StatusCode::RANGE_NOT_SATISFIABLE.into_response();
+            if let Ok(content_range) = HeaderValue::from_str(&format!("bytes */{}", len)) {
+                response
+                    .headers_mut()
+                    .insert(header::CONTENT_RANGE, content_range);
+            }
+            return Err(response);
+        }
+    };
+    let content_length = if len == 0 { 0 } else { end - start + 1 };
+
+    Ok(Span {
+        status,
+        start,
+        end,
+        content_length,
+    })
+}
+
+/// The response sending `span` of a file of `len` bytes.
+fn respond(
+    body: Body,
+    span: Span,
+    len: u64,
+    etag: &str,
+    modified: Option<SystemTime>,
+    cache_control: &'static str,
+) -> Response {
+    let mut response = body.into_response();
+    *response.status_mut() = span.status;
+
+    let response_headers = response.headers_mut();
+    response_headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    response_headers.insert(
+        header::CONTENT_LENGTH,
+        HeaderValue::from(span.content_length),
+    );
+    if span.status == StatusCode::PARTIAL_CONTENT {
+        if let Ok(content_range) =
+            HeaderValue::from_str(&format!("bytes {}-{}/{}", span.start, span.end, len))
+        {
+            response_headers.insert(header::CONTENT_RANGE, content_range);
+        }
+    }
+    set_validators(response_headers, etag, modified, cache_control);
+
+    response
+}
+
+/// Stream a file, or part of it when the request asks for a `Range`. The caller sets the
+/// `Content-Type` and any other header of the response. Without `last_modified`, the modification
+/// time of the file is used.
+pub async fn serve_file(
+    headers: &HeaderMap,
+    path: impl AsRef<Path>,
+    content_hash: Option<&str>,
+    last_modified: Option<SystemTime>,
+    cache_control: &'static str,
+) -> Response {
+    let mut file = match File::open(path).await {
+        Ok(file) => file,
+        Err(_) => return StatusCode::NOT_FOUND.into_response(),
+    };
+    let metadata = match file.metadata().await {
+        Ok(metadata) if metadata.is_file() => metadata,
+        _ => return StatusCode::NOT_FOUND.into_response(),
+    };
+    let len = metadata.len();
+    let file_modified = metadata.modified().ok();
+    let modified = last_modified.or(file_modified);
+
+    let etag = match content_hash {
+        Some(hash) => hash_etag(hash),
+        None => metadata_etag(len, file_modified),
+    };
+    let span = match plan(headers, len, &etag, modified, cache_control) {
+        Ok(span) => span,
+        Err(response) => return response,
+    };
+
+    if span.start > 0 && file.seek(SeekFrom::Start(span.start)).await.is_err() {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+    let stream = ReaderStream::with_capacity(file.take(span.content_length), CHUNK_SIZE);
+
+    respond(
+        Body::from_stream(stream),
+        span,
+        len,
+        &etag,
+        modified,
+        cache_control,
+    )
+}
+
+/// Send a file already in memory, like one fetched from an object storage, the same way as
+/// [`serve_file`]. Without a hash, the `ETag` is hashed from the content.
+pub fn serve_bytes(
+    headers: &HeaderMap,
+    content: Vec<u8>,
+    content_hash: Option<&str>,
+    last_modified: Option<SystemTime>,
+    cache_control: &'static str,
+) -> Response {
+    let len = content.len() as u64;
+    let etag = match content_hash {
+        Some(hash) => hash_etag(hash),
+        None => hash_etag(&crate::gallery::storage::content_hash(&content)),
+    };
+    let span = match plan(headers, len, &etag, last_modified, cache_control) {
+        Ok(span) => span,
+        Err(response) => return response,
+    };
+
+    let body = match len {
+        0 => Body::empty(),
+        _ => Body::from(content[span.start as usize..=span.end as usize].to_vec()),
+    };
+    respond(body, span, len, &etag, last_modified, cache_control)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_spans() {
+        assert_eq!(parse_range("bytes=0-99", 1000), Some(Ok((0, 99))));
+        assert_eq!(parse_range(" bytes=10 - 19 ", 1000), Some(Ok((10, 19))));
+        assert_eq!(parse_range("bytes=999-999", 1000), Some(Ok((999, 999))));
+    }
+
+    #[test]
+    fn parses_suffixes() {
+        assert_eq!(parse_range("bytes=-100", 1000), Some(Ok((900, 999))));
+        assert_eq!(parse_range("bytes=-1", 1000), Some(Ok((999, 999))));
+        // Asking for more than the file has gives the whole file
+        assert_eq!(parse_range("bytes=-5000", 1000), Some(Ok((0, 999))));
+        assert_eq!(parse_range("bytes=-0", 1000), Some(Err(())));
+    }
+
+    #[test]
+    fn parses_open_ended_spans() {
+        assert_eq!(parse_range("bytes=0-", 1000), Some(Ok((0, 999))));
+        assert_eq!(parse_range("bytes=500-", 1000), Some(Ok((500, 999))));
+        assert_eq!(parse_range("bytes=1000-", 1000), Some(Err(())));
+    }
+
+    #[test]
+    fn clamps_spans_past_the_end() {
+        assert_eq!(parse_range("bytes=900-5000", 1000), Some(Ok((900, 999))));
+        assert_eq!(
+            parse_range(&format!("bytes=0-{}", u64::MAX), 1000),
+            Some(Ok((0, 999)))
+        );
+        assert_eq!(parse_range("bytes=1000-1999", 1000), Some(Err(())));
+        // Too big for a number, so not a range we understand
+        assert_eq!(parse_range("bytes=0-99999999999999999999999", 1000), None);
+    }
+
+    #[test]
+    fn ignores_what_it_cant_serve() {
+        // Many spans would need a multipart response
+        assert_eq!(parse_range("bytes=0-10,20-30", 1000), None);
+        assert_eq!(parse_range("bytes=-10, -20", 1000), None);
+        // Inverted spans are invalid, so the header is ignored
+        assert_eq!(parse_range("bytes=20-10", 1000), None);
+        assert_eq!(parse_range("bytes=-", 1000), None);
+        assert_eq!(parse_range("bytes=a-b", 1000), None);
+        assert_eq!(parse_range("bytes=--1", 1000), None);
+        assert_eq!(parse_range("items=0-10", 1000), None);
+        assert_eq!(parse_range("0-10", 1000), None);
+    }
+
+    #[test]
+    fn cant_satisfy_ranges_of_empty_files() {
+        assert_eq!(parse_range("bytes=0-", 0), Some(Err(())));
+        assert_eq!(parse_range("bytes=0-0", 0), Some(Err(())));
+        assert_eq!(parse_range("bytes=-10", 0), Some(Err(())));
+    }
+
+    fn range(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::RANGE, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn serves_ranges() {
+        let response = serve_bytes(&range("bytes=-3"), b"0123456789".to_vec(), None, None, "");
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes 7-9/10");
+        assert_eq!(response.headers()[header::CONTENT_LENGTH], "3");
+
+        let response = serve_bytes(&range("bytes=20-"), b"0123456789".to_vec(), None, None, "");
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes */10");
+
+        let response = serve_bytes(&range("bytes=5-2"), b"0123456789".to_vec(), None, None, "");
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_LENGTH], "10");
+    }
+
+    #[test]
+    fn serves_empty_files() {
+        let response = serve_bytes(&HeaderMap::new(), vec![], None, None, "");
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_LENGTH], "0");
+
+        let response = serve_bytes(&range("bytes=0-"), vec![], None, None, "");
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes */0");
+    }
+}
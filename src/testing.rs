@@ -0,0 +1,154 @@
+//! Fakes shared by the tests: a database in memory, with the tables made from the entities, a
+//! [`Fetcher`] serving pages given by the test, and an `AppState` built from them.
+
+use crate::gallery::{media::MediaStore, storage::UploadLimits};
+use crate::newsletter::{Mail, Mailer};
+use crate::webmention::{FetchedPage, Fetcher};
+use crate::AppState;
+use async_trait::async_trait;
+use sea_orm::{ConnectOptions, ConnectionTrait, Database, DatabaseConnection, EntityTrait, Schema};
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::Mutex;
+
+/// A SQLite database in memory with every table. Foreign keys aren't checked, so tests only
+/// insert the rows they need.
+pub async fn database() -> DatabaseConnection {
+    use entities::prelude::*;
+
+    // A single connection, as each one would get it's own database
+    let mut options = ConnectOptions::new("sqlite::memory:");
+    options
+        .max_connections(1)
+        .min_connections(1)
+        .sqlx_logging(false);
+    let conn = Database::connect(options).await.unwrap();
+    conn.execute_unprepared("PRAGMA foreign_keys = OFF")
+        .await
+        .unwrap();
+
+    async fn create<E: EntityTrait>(conn: &DatabaseConnection, entity: E) {
+        let schema = Schema::new(conn.get_database_backend());
+        conn.execute(
+            conn.get_database_backend()
+                .build(&schema.create_table_from_entity(entity)),
+        )
+        .await
+        .unwrap();
+    }
+    create(&conn, User).await;
+    create(&conn, UserProfile).await;
+    create(&conn, PostMetadata).await;
+    create(&conn, ImageMetadata).await;
+    create(&conn, ImageAltText).await;
+    create(&conn, ImageUsage).await;
+    create(&conn, Webmention).await;
+
+    conn
+}
+
+/// A [`Fetcher`] answering from the pages it was given, and 404 for any other URL. Posts are
+/// recorded, and answered with `post_status`.
+#[derive(Debug, Default)]
+pub struct FakeFetcher {
+    pub pages: HashMap<String, FetchedPage>,
+    pub post_status: u16,
+    pub posted: std::sync::Mutex<Vec<(String, Vec<(String, String)>)>>,
+}
+
+impl FakeFetcher {
+    pub fn serving(pages: &[FetchedPage]) -> Self {
+        Self {
+            pages: pages
+                .iter()
+                .map(|page| (page.url.clone(), page.clone()))
+                .collect(),
+            post_status: 202,
+            ..Default::default()
+        }
+    }
+
+    /// The URLs posted to, in order.
+    pub fn posted_urls(&self) -> Vec<String> {
+        self.posted
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(url, _)| url.clone())
+            .collect()
+    }
+}
+
+/// An HTML page on `url`.
+pub fn html_page(url: &str, body: &str) -> FetchedPage {
+    FetchedPage {
+        url: url.to_string(),
+        status: 200,
+        content_type: Some("text/html; charset=utf-8".to_string()),
+        link_headers: vec![],
+        body: body.to_string(),
+    }
+}
+
+#[async_trait]
+impl Fetcher for FakeFetcher {
+    async fn get(&self, url: &str) -> Result<FetchedPage, String> {
+        Ok(self.pages.get(url).cloned().unwrap_or(FetchedPage {
+            url: url.to_string(),
+            status: 404,
+            ..Default::default()
+        }))
+    }
+
+    async fn get_activity(&self, url: &str) -> Result<FetchedPage, String> {
+        self.get(url).await
+    }
+
+    async fn post_form(&self, url: &str, form: &[(&str, &str)]) -> Result<u16, String> {
+        self.posted.lock().unwrap().push((
+            url.to_string(),
+            form.iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        ));
+        Ok(self.post_status)
+    }
+
+    async fn post(
+        &self,
+        url: &str,
+        _headers: &[(String, String)],
+        _body: Vec<u8>,
+    ) -> Result<u16, String> {
+        self.posted.lock().unwrap().push((url.to_string(), vec![]));
+        Ok(self.post_status)
+    }
+}
+
+/// A [`Mailer`] that drops every mail.
+#[derive(Debug, Default)]
+pub struct NoMailer;
+
+#[async_trait]
+impl Mailer for NoMailer {
+    async fn send(&self, _mail: Mail) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// An `AppState` on `conn`, keeping files on `media`.
+pub fn app_state(
+    conn: DatabaseConnection,
+    fetcher: Arc<dyn Fetcher>,
+    media: Arc<dyn MediaStore>,
+) -> Arc<Mutex<AppState>> {
+    Arc::new(Mutex::new(AppState {
+        conn,
+        secret_key: "secret".to_string(),
+        site_url: "https://blog.example".to_string(),
+        fetcher,
+        mailer: Arc::new(NoMailer),
+        upload_limits: UploadLimits::default(),
+        media,
+        gallery_lock: Arc::new(Mutex::new(())),
+    }))
+}
@@ -0,0 +1,998 @@
+//! ## Webmentions
+//! Lets the blog take part in the IndieWeb. Other sites tell us they linked to an article by
+//! posting to `/webmention`, and we do the same for every link found on our articles when they
+//! are published. See <https://www.w3.org/TR/webmention/>.
+//!
+//! Every request to another site, including the ActivityPub ones, goes through a [`Fetcher`],
+//! kept on `AppState`, so tests can swap the network for a local fake. [`HttpFetcher`] only
+//! connects to public addresses: names are resolved and checked before connecting, and so is
+//! every redirect, so a page can't point us to this machine or to the local network.
+
+use crate::AppState;
+use async_trait::async_trait;
+use axum::{http::StatusCode, response::IntoResponse, Form};
+use chrono::{FixedOffset, Utc};
+use leptos::logging::log;
+use reqwest::Url;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, ModelTrait,
+    QueryFilter, Set,
+};
+use serde::Deserialize;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::Mutex;
+
+/// Pages bigger than this are not read to the end.
+const MAX_BODY_SIZE: usize = 1024 * 1024;
+
+/// How many redirects are followed before giving up.
+const MAX_REDIRECTS: usize = 5;
+
+/// A page fetched from another site.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct FetchedPage {
+    /// The URL of the page after following redirects.
+    pub url: String,
+    pub status: u16,
+    pub content_type: Option<String>,
+    /// The values of every `Link` header on the response.
+    pub link_headers: Vec<String>,
+    pub body: String,
+}
+
+#[async_trait]
+pub trait Fetcher: Send + Sync + std::fmt::Debug {
+    async fn get(&self, url: &str) -> Result<FetchedPage, String>;
+
+    /// Fetch an ActivityPub object, asking for it's JSON representation.
+    async fn get_activity(&self, url: &str) -> Result<FetchedPage, String>;
+
+    /// Post a form encoded body, returning the response status.
+    async fn post_form(&self, url: &str, form: &[(&str, &str)]) -> Result<u16, String>;
+
+    /// Post `body` with the extra `headers`, returning the response status.
+    async fn post(&self, url: &str, headers: &[(String, String)], body: Vec<u8>)
+        -> Result<u16, String>;
+}
+
+/// The `Fetcher` used in production, doing real HTTP requests.
+#[derive(Clone, Debug)]
+pub struct HttpFetcher {
+    client: reqwest::Client,
+}
+
+impl HttpFetcher {
+    pub fn new() -> Self {
+        let redirect = reqwest::redirect::Policy::custom(|attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS {
+                attempt.error("too many redirects")
+            } else if is_private_host(attempt.url()) {
+                attempt.error("redirected to a private address")
+            } else {
+                attempt.follow()
+            }
+        });
+
+        let client = reqwest::Client::builder()
+            .user_agent("Orange Museum (+https://orangethewell.is-a.dev)")
+            .timeout(Duration::from_secs(10))
+            .redirect(redirect)
+            .dns_resolver(Arc::new(PublicResolver))
+            // A proxy would resolve names itself, skipping `PublicResolver`
+            .no_proxy()
+            .build()
+            .expect("Unable to build the HTTP client");
+
+        Self { client }
+    }
+
+    /// Refuse URLs naming a private address, the ones naming a domain are checked by
+    /// [`PublicResolver`] once resolved.
+    fn checked(url: &str) -> Result<Url, String> {
+        let url = Url::parse(url).map_err(|err| err.to_string())?;
+        if is_private_host(&url) {
+            return Err(format!("{} is not a public address", url));
+        }
+        Ok(url)
+    }
+
+    async fn read_page(request: reqwest::RequestBuilder) -> Result<FetchedPage, String> {
+        let mut response = request.send().await.map_err(|err| err.to_string())?;
+
+        let mut page = FetchedPage {
+            url: response.url().to_string(),
+            status: response.status().as_u16(),
+            content_type: response
+                .headers()
+                .get(reqwest::header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string),
+            link_headers: response
+                .headers()
+                .get_all(reqwest::header::LINK)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .map(str::to_string)
+                .collect(),
+            body: String::new(),
+        };
+
+        let mut body = vec![];
+        while let Some(chunk) = response.chunk().await.map_err(|err| err.to_string())? {
+            body.extend_from_slice(&chunk);
+            if body.len() >= MAX_BODY_SIZE {
+                body.truncate(MAX_BODY_SIZE);
+                break;
+            }
+        }
+
+        page.body = String::from_utf8_lossy(&body).into_owned();
+        Ok(page)
+    }
+}
+
+#[async_trait]
+impl Fetcher for HttpFetcher {
+    async fn get(&self, url: &str) -> Result<FetchedPage, String> {
+        Self::read_page(self.client.get(Self::checked(url)?)).await
+    }
+
+    async fn get_activity(&self, url: &str) -> Result<FetchedPage, String> {
+        Self::read_page(self.client.get(Self::checked(url)?).header(
+            reqwest::header::ACCEPT,
+            "application/activity+json, application/ld+json; profile=\"https://www.w3.org/ns/activitystreams\"",
+        ))
+        .await
+    }
+
+    async fn post_form(&self, url: &str, form: &[(&str, &str)]) -> Result<u16, String> {
+        self.client
+            .post(Self::checked(url)?)
+            .form(form)
+            .send()
+            .await
+            .map(|response| response.status().as_u16())
+            .map_err(|err| err.to_string())
+    }
+
+    async fn post(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        body: Vec<u8>,
+    ) -> Result<u16, String> {
+        let mut request = self.client.post(Self::checked(url)?).body(body);
+        for (name, value) in headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+
+        request
+            .send()
+            .await
+            .map(|response| response.status().as_u16())
+            .map_err(|err| err.to_string())
+    }
+}
+
+/// Resolves names for [`HttpFetcher`], refusing the ones with any private address, so the client
+/// only connects to addresses that were checked.
+#[derive(Clone, Copy, Debug)]
+struct PublicResolver;
+
+impl reqwest::dns::Resolve for PublicResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        Box::pin(async move {
+            let host = name.as_str();
+            if is_local_domain(host) {
+                return Err(format!("{} is not a public address", host).into());
+            }
+
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, 0)).await?.collect();
+            if addrs.is_empty() {
+                return Err(format!("{} has no address", host).into());
+            }
+            if let Some(addr) = addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+                return Err(format!("{} resolves to the private address {}", host, addr.ip()).into());
+            }
+
+            let addrs: reqwest::dns::Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum WebmentionError {
+    #[error("{0}")]
+    InvalidRequest(String),
+    #[error("The target is not an article of this site.")]
+    UnknownTarget,
+    #[error("Couldn't fetch the source: {0}")]
+    SourceUnavailable(String),
+    #[error("The source doesn't link to the target.")]
+    NoLink,
+    #[error("A error happened when saving the webmention, try again later. DbErr: {0}")]
+    Database(#[from] DbErr),
+}
+
+impl WebmentionError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            WebmentionError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+/// The absolute URL of an article, as seen by other sites.
+pub fn article_url(site_url: &str, slug: &str) -> String {
+    format!("{}/articles/{}", site_url.trim_end_matches('/'), slug)
+}
+
+// Receiving
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct WebmentionForm {
+    pub source: String,
+    pub target: String,
+}
+
+/// Check the request right away, answering `202 Accepted`, and verify the source on the
+/// background, as fetching it may take a while.
+pub async fn receive_webmention_handler(
+    Form(form): Form<WebmentionForm>,
+    state: Arc<Mutex<AppState>>,
+) -> impl IntoResponse {
+    let (conn, fetcher, site_url) = {
+        let state = state.as_ref().lock().await;
+        (state.conn.clone(), state.fetcher.clone(), state.site_url.clone())
+    };
+
+    let (source, article) = match webmention_target(&conn, &site_url, &form.source, &form.target)
+        .await
+    {
+        Ok(checked) => checked,
+        Err(err) => return (err.status_code(), err.to_string()),
+    };
+
+    tokio::spawn(async move {
+        let verified =
+            verify_webmention(&conn, fetcher.as_ref(), &site_url, &source, &article).await;
+        if let Err(err) = verified {
+            log!("Webmention from {} wasn't saved: {}", source, err);
+        }
+    });
+
+    (
+        StatusCode::ACCEPTED,
+        "The webmention will be verified and then await moderation.".to_string(),
+    )
+}
+
+/// Verify that `source` links to the article on `target` and store the mention, waiting for
+/// moderation. See [`verify_webmention`].
+pub async fn receive_webmention(
+    conn: &DatabaseConnection,
+    fetcher: &dyn Fetcher,
+    site_url: &str,
+    source: &str,
+    target: &str,
+) -> Result<entities::webmention::Model, WebmentionError> {
+    let (source, article) = webmention_target(conn, site_url, source, target).await?;
+    verify_webmention(conn, fetcher, site_url, &source, &article).await
+}
+
+/// Check a webmention without fetching the source: both URLs must be valid, the source public
+/// and the target one of our articles, which is returned with the parsed source.
+pub async fn webmention_target(
+    conn: &DatabaseConnection,
+    site_url: &str,
+    source: &str,
+    target: &str,
+) -> Result<(Url, entities::post_metadata::Model), WebmentionError> {
+    use entities::post_metadata;
+    use entities::prelude::PostMetadata;
+
+    let source_url = parse_http_url(source)?;
+    let target_url = parse_http_url(target)?;
+
+    if source_url == target_url {
+        return Err(WebmentionError::InvalidRequest(
+            "The source and the target must be different.".to_string(),
+        ));
+    }
+
+    if is_private_host(&source_url) {
+        return Err(WebmentionError::InvalidRequest(
+            "The source must be a public URL.".to_string(),
+        ));
+    }
+
+    let slug = match target_slug(site_url, &target_url) {
+        Some(slug) => slug,
+        None => return Err(WebmentionError::UnknownTarget),
+    };
+
+    match PostMetadata::find()
+        .filter(post_metadata::Column::Slug.eq(slug))
+        .one(conn)
+        .await?
+    {
+        Some(article) => Ok((source_url, article)),
+        None => Err(WebmentionError::UnknownTarget),
+    }
+}
+
+/// Fetch `source` and store it's mention of `article`, waiting for moderation. A stored mention
+/// is removed when the source is gone (`410 Gone`) or no longer links to the article, other
+/// failures keep it, as the source may only be down for a while.
+pub async fn verify_webmention(
+    conn: &DatabaseConnection,
+    fetcher: &dyn Fetcher,
+    site_url: &str,
+    source_url: &Url,
+    article: &entities::post_metadata::Model,
+) -> Result<entities::webmention::Model, WebmentionError> {
+    use entities::prelude::Webmention;
+    use entities::webmention;
+
+    let existing = Webmention::find()
+        .filter(webmention::Column::PostId.eq(article.id))
+        .filter(webmention::Column::Source.eq(source_url.as_str()))
+        .one(conn)
+        .await?;
+
+    let page = fetcher
+        .get(source_url.as_str())
+        .await
+        .map_err(WebmentionError::SourceUnavailable)?;
+
+    let target = article_url(site_url, &article.slug);
+    let is_ok = (200..300).contains(&page.status);
+    if page.status == 410 || (is_ok && !links_to(&page, &target)) {
+        if let Some(existing) = existing {
+            existing.delete(conn).await?;
+        }
+        return Err(WebmentionError::NoLink);
+    }
+    if !is_ok {
+        return Err(WebmentionError::SourceUnavailable(format!(
+            "the source answered with status {}",
+            page.status
+        )));
+    }
+
+    let now = Utc::now().with_timezone(&FixedOffset::west_opt(3 * 3600).expect("Invalid Timezone"));
+    let title = page_title(&page.body);
+
+    let mention = match existing {
+        Some(existing) => {
+            let mut mention: webmention::ActiveModel = existing.into();
+            mention.title = Set(title);
+            mention.updated_at = Set(now);
+            mention.update(conn).await?
+        }
+        None => {
+            webmention::ActiveModel {
+                post_id: Set(article.id),
+                source: Set(source_url.to_string()),
+                title: Set(title),
+                approved: Set(false),
+                created_at: Set(now),
+                updated_at: Set(now),
+                ..Default::default()
+            }
+            .insert(conn)
+            .await?
+        }
+    };
+
+    Ok(mention)
+}
+
+fn parse_http_url(url: &str) -> Result<Url, WebmentionError> {
+    match Url::parse(url.trim()) {
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => Ok(url),
+        _ => Err(WebmentionError::InvalidRequest(format!(
+            "\"{}\" is not a valid http(s) URL.",
+            url
+        ))),
+    }
+}
+
+/// Refuse URLs pointing to this machine or to the local network, so the endpoint can't be used
+/// to reach internal services. Only the host as written is looked at, names are checked once
+/// resolved, by [`PublicResolver`].
+pub fn is_private_host(url: &Url) -> bool {
+    match url.host() {
+        Some(url::Host::Domain(domain)) => is_local_domain(domain),
+        Some(url::Host::Ipv4(ip)) => !is_public_ip(IpAddr::V4(ip)),
+        Some(url::Host::Ipv6(ip)) => !is_public_ip(IpAddr::V6(ip)),
+        None => true,
+    }
+}
+
+fn is_local_domain(domain: &str) -> bool {
+    let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+    domain == "localhost" || domain.ends_with(".localhost")
+}
+
+/// Whether `ip` is reachable on the internet, and not on this machine or the local network.
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                // 0.0.0.0/8, shared 100.64.0.0/10, benchmarking 198.18.0.0/15 and 240.0.0.0/4
+                || a == 0
+                || (a == 100 && (64..128).contains(&b))
+                || (a == 198 && (18..20).contains(&b))
+                || a >= 240)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(ip));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // Unique local fc00::/7, link-local fe80::/10, site-local fec0::/10 and
+                // documentation 2001:db8::/32
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+                || (first & 0xffc0) == 0xfec0
+                || (first == 0x2001 && ip.segments()[1] == 0x0db8))
+        }
+    }
+}
+
+/// The slug of the article `target` points to, if it's an article of this site.
+fn target_slug(site_url: &str, target: &Url) -> Option<String> {
+    let site_url = Url::parse(site_url).ok()?;
+    if target.host_str() != site_url.host_str()
+        || target.port_or_known_default() != site_url.port_or_known_default()
+    {
+        return None;
+    }
+
+    let slug = target
+        .path()
+        .strip_prefix("/articles/")?
+        .trim_end_matches('/');
+
+    (!slug.is_empty() && !slug.contains('/')).then(|| slug.to_string())
+}
+
+/// Whether the page links to `target`. HTML pages need a `href` to it, other pages only need to
+/// mention the URL.
+pub fn links_to(page: &FetchedPage, target: &str) -> bool {
+    let is_html = page
+        .content_type
+        .as_deref()
+        .map_or(true, |content_type| content_type.contains("html"));
+
+    if !is_html {
+        return page.body.contains(target);
+    }
+
+    let target = target.trim_end_matches('/');
+    html_tags(&page.body, &["a", "link", "img", "video", "audio", "source"])
+        .into_iter()
+        .filter_map(|(_, attributes)| {
+            attributes
+                .into_iter()
+                .find(|(name, _)| name == "href" || name == "src")
+                .map(|(_, value)| value)
+        })
+        .any(|href| href.trim_end_matches('/') == target)
+}
+
+fn page_title(body: &str) -> Option<String> {
+    let lower = body.to_ascii_lowercase();
+    let start = lower.find("<title")?;
+    let start = start + lower[start..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+
+    let title: String = body[start..end].split_whitespace().collect::<Vec<_>>().join(" ");
+    (!title.is_empty()).then(|| title.chars().take(200).collect())
+}
+
+// Sending
+
+/// The external links of an article's markdown, without repetitions.
+pub fn external_links(markdown: &str) -> Vec<String> {
+    use pulldown_cmark::{Event, Parser, Tag};
+
+    let mut links: Vec<String> = vec![];
+    for event in Parser::new(markdown) {
+        if let Event::Start(Tag::Link { dest_url, .. }) = event {
+            let url = dest_url.to_string();
+            if (url.starts_with("https://") || url.starts_with("http://")) && !links.contains(&url)
+            {
+                links.push(url);
+            }
+        }
+    }
+
+    links
+}
+
+/// Find the webmention endpoint of `target`, looking at it's `Link` headers first and then at
+/// `<link>` and `<a>` tags with `rel="webmention"`.
+pub async fn discover_endpoint(fetcher: &dyn Fetcher, target: &str) -> Option<String> {
+    let page = fetcher.get(target).await.ok()?;
+    if !(200..300).contains(&page.status) {
+        return None;
+    }
+
+    let base = Url::parse(&page.url).ok()?;
+
+    let from_header = page
+        .link_headers
+        .iter()
+        .flat_map(|header| header.split(','))
+        .find_map(|link| {
+            let (href, params) = link.trim().split_once(';')?;
+            let href = href.trim().strip_prefix('<')?.strip_suffix('>')?;
+            params
+                .split(';')
+                .filter_map(|param| param.trim().strip_prefix("rel="))
+                .any(|rel| has_webmention_rel(rel.trim_matches('"')))
+                .then(|| href.to_string())
+        });
+
+    let endpoint = from_header.or_else(|| {
+        let is_html = page
+            .content_type
+            .as_deref()
+            .map_or(true, |content_type| content_type.contains("html"));
+        if !is_html {
+            return None;
+        }
+
+        html_tags(&page.body, &["link", "a"])
+            .into_iter()
+            .find_map(|(_, attributes)| {
+                let rel = attributes.iter().find(|(name, _)| name == "rel")?;
+                let href = attributes.iter().find(|(name, _)| name == "href")?;
+                has_webmention_rel(&rel.1).then(|| href.1.clone())
+            })
+    })?;
+
+    let endpoint = base.join(&endpoint).ok()?;
+    (endpoint.scheme() == "http" || endpoint.scheme() == "https").then(|| endpoint.to_string())
+}
+
+/// The external links added to or removed from an article when `old` was edited into `new`.
+/// Removed links are notified too, so their sites can drop the mention.
+pub fn changed_links(old: &str, new: &str) -> Vec<String> {
+    let old = external_links(old);
+    let new = external_links(new);
+
+    new.iter()
+        .filter(|link| !old.contains(link))
+        .chain(old.iter().filter(|link| !new.contains(link)))
+        .cloned()
+        .collect()
+}
+
+fn has_webmention_rel(rel: &str) -> bool {
+    rel.split_whitespace()
+        .any(|rel| rel.eq_ignore_ascii_case("webmention"))
+}
+
+/// Send a webmention from the article on `source` to every one of `targets`, usually it's
+/// [`external_links`] or [`changed_links`]. Links without an endpoint are skipped, failures are
+/// only logged.
+pub async fn send_webmentions(fetcher: Arc<dyn Fetcher>, source: String, targets: Vec<String>) {
+    for target in targets {
+        let endpoint = match discover_endpoint(fetcher.as_ref(), &target).await {
+            Some(endpoint) => endpoint,
+            None => continue,
+        };
+
+        match fetcher
+            .post_form(&endpoint, &[("source", &source), ("target", &target)])
+            .await
+        {
+            Ok(status) if (200..300).contains(&status) => {
+                log!("Webmention sent from {} to {}", source, target)
+            }
+            Ok(status) => log!(
+                "Webmention from {} to {} was refused with status {}",
+                source,
+                target,
+                status
+            ),
+            Err(err) => log!("Webmention from {} to {} failed: {}", source, target, err),
+        }
+    }
+}
+
+// HTML scanning
+
+/// Find the tags named as one of `names` on `html`, with their attributes. Attribute names are
+/// lowercased and values are kept as they are written.
+fn html_tags(html: &str, names: &[&str]) -> Vec<(String, Vec<(String, String)>)> {
+    let mut tags = vec![];
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+
+        let name_end = rest
+            .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            .unwrap_or(rest.len());
+        let name = rest[..name_end].to_ascii_lowercase();
+
+        if !names.contains(&name.as_str()) {
+            continue;
+        }
+
+        let end = match rest.find('>') {
+            Some(end) => end,
+            None => break,
+        };
+
+        tags.push((name, tag_attributes(&rest[name_end..end])));
+        rest = &rest[end + 1..];
+    }
+
+    tags
+}
+
+fn tag_attributes(source: &str) -> Vec<(String, String)> {
+    let mut attributes = vec![];
+    let mut chars = source.trim_end_matches('/').chars().peekable();
+
+    loop {
+        while chars
+            .peek()
+            .is_some_and(|c| c.is_whitespace() || *c == '/')
+        {
+            chars.next();
+        }
+
+        let mut name = String::new();
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() || c == '=' {
+                break;
+            }
+            name.push(c.to_ascii_lowercase());
+            chars.next();
+        }
+
+        if name.is_empty() {
+            break;
+        }
+
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+
+        let mut value = String::new();
+        if chars.peek() == Some(&'=') {
+            chars.next();
+            while chars.peek().is_some_and(|c| c.is_whitespace()) {
+                chars.next();
+            }
+
+            match chars.peek() {
+                Some(&quote) if quote == '"' || quote == '\'' => {
+                    chars.next();
+                    for c in chars.by_ref() {
+                        if c == quote {
+                            break;
+                        }
+                        value.push(c);
+                    }
+                }
+                _ => {
+                    while let Some(&c) = chars.peek() {
+                        if c.is_whitespace() {
+                            break;
+                        }
+                        value.push(c);
+                        chars.next();
+                    }
+                }
+            }
+        }
+
+        attributes.push((name, value.replace("&amp;", "&")));
+    }
+
+    attributes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{database, html_page, FakeFetcher};
+
+    fn private(url: &str) -> bool {
+        is_private_host(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn refuses_private_hosts() {
+        for url in [
+            "http://localhost/",
+            "http://api.localhost./",
+            "http://127.0.0.1/",
+            "http://10.0.0.8/",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://100.64.0.1/",
+            "http://0.0.0.0/",
+            "http://[::1]/",
+            "http://[::ffff:127.0.0.1]/",
+            "http://[fd00::1]/",
+            "http://[fc12::1]/",
+            "http://[fe80::1]/",
+        ] {
+            assert!(private(url), "{} should be private", url);
+        }
+    }
+
+    #[test]
+    fn accepts_public_hosts() {
+        for url in [
+            "https://example.com/post",
+            "http://93.184.216.34/",
+            "http://[2606:4700::1111]/",
+        ] {
+            assert!(!private(url), "{} should be public", url);
+        }
+    }
+
+    #[tokio::test]
+    async fn resolver_refuses_names_of_private_addresses() {
+        use reqwest::dns::Resolve;
+
+        let name = "localhost".parse().unwrap();
+        assert!(PublicResolver.resolve(name).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn fetcher_refuses_private_urls_before_connecting() {
+        let fetcher = HttpFetcher::new();
+
+        assert!(fetcher.get("http://127.0.0.1:9/").await.is_err());
+        assert!(fetcher
+            .post_form("http://[fe80::1]/webmention", &[("source", "a")])
+            .await
+            .unwrap_err()
+            .contains("not a public address"));
+    }
+
+    const TARGET: &str = "https://blog.example/articles/hello";
+    const SOURCE: &str = "https://other.example/reply";
+
+    async fn article(conn: &DatabaseConnection) {
+        let now = Utc::now().fixed_offset();
+        entities::post_metadata::ActiveModel {
+            title: Set("Hello".to_string()),
+            slug: Set("hello".to_string()),
+            writer_id: Set(1),
+            created_at: Set(now),
+            updated_at: Set(now),
+            content_path: Set("data/hello.md".to_string()),
+            pinned: Set(false),
+            pin_order: Set(0),
+            ..Default::default()
+        }
+        .insert(conn)
+        .await
+        .unwrap();
+    }
+
+    async fn mentions(conn: &DatabaseConnection) -> Vec<entities::webmention::Model> {
+        entities::prelude::Webmention::find().all(conn).await.unwrap()
+    }
+
+    fn reply(body: &str) -> FakeFetcher {
+        FakeFetcher::serving(&[html_page(SOURCE, body)])
+    }
+
+    #[tokio::test]
+    async fn accepts_a_source_linking_to_the_article() {
+        let conn = database().await;
+        article(&conn).await;
+        let fetcher = reply(&format!(
+            "<title>A  reply</title><a class=\"u-in-reply-to\" href=\"{}/\">Hello</a>",
+            TARGET
+        ));
+
+        let mention = receive_webmention(&conn, &fetcher, "https://blog.example", SOURCE, TARGET)
+            .await
+            .unwrap();
+        assert_eq!(mention.source, SOURCE);
+        assert_eq!(mention.title.as_deref(), Some("A reply"));
+        assert!(!mention.approved);
+
+        // Verifying again updates the same mention
+        receive_webmention(&conn, &fetcher, "https://blog.example", SOURCE, TARGET)
+            .await
+            .unwrap();
+        assert_eq!(mentions(&conn).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn refuses_unknown_targets_and_private_sources() {
+        let conn = database().await;
+        article(&conn).await;
+        let fetcher = reply("");
+
+        for (source, target) in [
+            (SOURCE, "https://blog.example/articles/missing"),
+            (SOURCE, "https://elsewhere.example/articles/hello"),
+            ("http://127.0.0.1/reply", TARGET),
+            (TARGET, TARGET),
+            ("ftp://other.example/reply", TARGET),
+        ] {
+            assert!(
+                receive_webmention(&conn, &fetcher, "https://blog.example", source, target)
+                    .await
+                    .is_err(),
+                "{} -> {} should be refused",
+                source,
+                target
+            );
+        }
+        assert!(fetcher.posted_urls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn removes_the_mention_when_the_link_is_gone() {
+        let conn = database().await;
+        article(&conn).await;
+        let linking = reply(&format!("<a href=\"{}\">Hello</a>", TARGET));
+        receive_webmention(&conn, &linking, "https://blog.example", SOURCE, TARGET)
+            .await
+            .unwrap();
+
+        let unlinked = reply("<p>Nothing to see here</p>");
+        let result =
+            receive_webmention(&conn, &unlinked, "https://blog.example", SOURCE, TARGET).await;
+        assert!(matches!(result, Err(WebmentionError::NoLink)));
+        assert!(mentions(&conn).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn removes_the_mention_only_when_the_source_is_gone() {
+        let conn = database().await;
+        article(&conn).await;
+        let linking = reply(&format!("<a href=\"{}\">Hello</a>", TARGET));
+        receive_webmention(&conn, &linking, "https://blog.example", SOURCE, TARGET)
+            .await
+            .unwrap();
+
+        // A source that is down for a while keeps it's mention
+        for status in [404, 500] {
+            let mut page = html_page(SOURCE, "");
+            page.status = status;
+            let fetcher = FakeFetcher::serving(&[page]);
+            let result =
+                receive_webmention(&conn, &fetcher, "https://blog.example", SOURCE, TARGET).await;
+            assert!(matches!(result, Err(WebmentionError::SourceUnavailable(_))));
+            assert_eq!(mentions(&conn).await.len(), 1);
+        }
+
+        let mut gone = html_page(SOURCE, "");
+        gone.status = 410;
+        let fetcher = FakeFetcher::serving(&[gone]);
+        let result =
+            receive_webmention(&conn, &fetcher, "https://blog.example", SOURCE, TARGET).await;
+        assert!(matches!(result, Err(WebmentionError::NoLink)));
+        assert!(mentions(&conn).await.is_empty());
+    }
+
+    #[test]
+    fn finds_links_on_html_and_other_pages() {
+        let page = html_page(SOURCE, &format!("<p><A HREF='{}/'>Hello</A></p>", TARGET));
+        assert!(links_to(&page, TARGET));
+
+        // Only mentioning the URL isn't a link on HTML
+        let page = html_page(SOURCE, &format!("<p>{}</p>", TARGET));
+        assert!(!links_to(&page, TARGET));
+        let page = html_page(SOURCE, &format!("<a href=\"{}-2\">Other</a>", TARGET));
+        assert!(!links_to(&page, TARGET));
+
+        let page = FetchedPage {
+            content_type: Some("text/plain".to_string()),
+            body: format!("See {}", TARGET),
+            ..html_page(SOURCE, "")
+        };
+        assert!(links_to(&page, TARGET));
+    }
+
+    #[test]
+    fn lists_external_links_once() {
+        let markdown = "[a](https://a.example/) and [again](https://a.example/), \
+            [local](/articles/other), [mail](mailto:me@a.example) and [b](http://b.example/post)";
+        assert_eq!(
+            external_links(markdown),
+            vec!["https://a.example/", "http://b.example/post"]
+        );
+    }
+
+    #[test]
+    fn lists_added_and_removed_links() {
+        let old = "[a](https://a.example/) [b](https://b.example/)";
+        let new = "[b](https://b.example/) edited [c](https://c.example/)";
+        assert_eq!(
+            changed_links(old, new),
+            vec!["https://c.example/", "https://a.example/"]
+        );
+        assert!(changed_links(old, old).is_empty());
+    }
+
+    #[tokio::test]
+    async fn discovers_endpoints_on_headers_and_tags() {
+        let mut from_header = html_page(
+            "https://a.example/post",
+            "<link rel=\"webmention\" href=\"/from-tag\">",
+        );
+        from_header.link_headers = vec![
+            "<https://a.example/feed>; rel=\"alternate\", </webmention?x=1>; rel=\"webmention\""
+                .to_string(),
+        ];
+        let from_tag = html_page(
+            "https://b.example/post",
+            "<head><link href=\"https://endpoint.example/b\" rel=\"me webmention\"></head>",
+        );
+        let without = html_page("https://c.example/post", "<a href=\"/webmention\">Here</a>");
+        let fetcher = FakeFetcher::serving(&[from_header, from_tag, without]);
+
+        assert_eq!(
+            discover_endpoint(&fetcher, "https://a.example/post").await.as_deref(),
+            Some("https://a.example/webmention?x=1")
+        );
+        assert_eq!(
+            discover_endpoint(&fetcher, "https://b.example/post").await.as_deref(),
+            Some("https://endpoint.example/b")
+        );
+        assert_eq!(discover_endpoint(&fetcher, "https://c.example/post").await, None);
+        assert_eq!(discover_endpoint(&fetcher, "https://d.example/post").await, None);
+    }
+
+    #[tokio::test]
+    async fn sends_webmentions_to_the_links_with_an_endpoint() {
+        let fetcher = Arc::new(FakeFetcher::serving(&[
+            html_page(
+                "https://a.example/post",
+                "<link rel=\"webmention\" href=\"https://a.example/webmention\">",
+            ),
+            html_page("https://b.example/post", "<p>No endpoint</p>"),
+        ]));
+
+        send_webmentions(
+            fetcher.clone(),
+            TARGET.to_string(),
+            external_links("[a](https://a.example/post) [b](https://b.example/post)"),
+        )
+        .await;
+
+        let posted = fetcher.posted.lock().unwrap().clone();
+        assert_eq!(
+            posted,
+            vec![(
+                "https://a.example/webmention".to_string(),
+                vec![
+                    ("source".to_string(), TARGET.to_string()),
+                    ("target".to_string(), "https://a.example/post".to_string()),
+                ]
+            )]
+        );
+    }
+}